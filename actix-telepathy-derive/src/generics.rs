@@ -0,0 +1,60 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Generics, Result};
+
+/// Identifier of a possibly generic type: `base` followed by the `TypeName`s of its type parameters,
+/// e.g. `Worker<u32>`, so that each instantiation has its own.
+pub fn identifier(generics: &Generics, base: TokenStream2) -> Result<TokenStream2> {
+    if let Some(param) = generics.const_params().next() {
+        return Err(syn::Error::new_spanned(
+            param,
+            "Const generic parameters are not supported by the remote derives",
+        ));
+    }
+    let params: Vec<_> = generics
+        .type_params()
+        .map(|param| {
+            let ident = &param.ident;
+            quote!(<#ident as ::actix_telepathy::TypeName>::TYPE_NAME)
+        })
+        .collect();
+    if params.is_empty() {
+        return Ok(base);
+    }
+
+    let mut parts = vec![base, quote!("<")];
+    for (i, param) in params.into_iter().enumerate() {
+        if i > 0 {
+            parts.push(quote!(","));
+        }
+        parts.push(param);
+    }
+    parts.push(quote!(">"));
+    Ok(quote! {
+        ::actix_telepathy::__private::IdentifierBuf::concat(&[#(#parts),*]).as_str()
+    })
+}
+
+/// Whether the type has type parameters, which the generated impls need extra bounds for.
+pub fn is_generic(generics: &Generics) -> bool {
+    generics.type_params().next().is_some()
+}
+
+/// `generics` with the given `predicates` and, if the identifier is composed of their names,
+/// a `TypeName` bound on each type parameter.
+pub fn with_bounds(
+    generics: &Generics,
+    type_names: bool,
+    predicates: Vec<syn::WherePredicate>,
+) -> Generics {
+    let mut generics = generics.clone();
+    if type_names {
+        for param in generics.type_params_mut() {
+            param
+                .bounds
+                .push(syn::parse_quote!(::actix_telepathy::TypeName));
+        }
+    }
+    generics.make_where_clause().predicates.extend(predicates);
+    generics
+}
@@ -1,81 +1,117 @@
 #![recursion_limit = "128"]
 use proc_macro::TokenStream;
 
+mod generics;
 mod remote_actor;
 mod remote_message;
 
-// todo remove log dependency
 // todo rename remotable to remoteactor
 
 /// Helper to prepare actors for remote messages
 /// # Example
-/// ```
-/// #[derive(Message, RemoteMessage)]
+/// ```no_run
+/// # use actix::prelude::*;
+/// # use actix_telepathy::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(RemoteMessage, Serialize, Deserialize)]
 /// struct MyMessage {}
-/// impl Sendable for MyMessage {}
-/// // ...
 ///
 /// #[derive(RemoteActor)]
 /// #[remote_messages(MyMessage)]
 /// struct MyActor {}
-/// // ...
+///
+/// impl Actor for MyActor {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl Handler<MyMessage> for MyActor {
+///     type Result = ();
+///
+///     fn handle(&mut self, _msg: MyMessage, _ctx: &mut Self::Context) -> Self::Result {}
+/// }
 /// ```
 ///
 /// # Background
 ///
 /// In the previous example, the MyActor struct gets extended the following way:
 ///
-/// ```
-/// impl Handler<RemoteMessage> for MyActor {
+/// ```ignore
+/// impl ::actix::Handler<::actix_telepathy::RemoteWrapper> for MyActor {
 ///     type Result = ();
 ///
-///     fn handle(&mut self, mut msg: RemoteMessage, ctx: &mut actix::prelude::Context<Self>) -> Self::Result {
-///         if MyMessage::is_message(&(msg.message)) {
-///             let deserialized_msg: MyMessage = MyMessage::from_packed(&(msg.message))
-///                 .expect("Cannot deserialized #name message");
-///             ctx.address().do_send(deserialized_msg);
-///         } else {
-///             warn!("Message dropped because identifier of {} is unknown", &(msg.message));
+///     fn handle(&mut self, msg: ::actix_telepathy::RemoteWrapper, ctx: &mut Self::Context) -> Self::Result {
+///         if msg.identifier == <MyMessage as ::actix_telepathy::RemoteMessage>::IDENTIFIER {
+///             // deserializes MyMessage and sets its source
+///             let response = <Self as ::actix::Handler<MyMessage>>::handle(self, deserialized_msg, ctx);
+///             // resolves the response, answering the sending node if it waits for the result
+///             ::actix::dev::MessageResponse::<Self, MyMessage>::handle(response, ctx, tx);
+///             return;
 ///         }
+///         warn!("Message dropped because identifier {} is unknown", &(msg.identifier));
 ///     }
 /// }
 /// ```
 ///
+/// Messages are handled directly in the context of the `RemoteWrapper`, without another trip through the mailbox,
+/// so they keep their order with local messages.
+///
+/// The generated code only uses fully qualified paths, so it does not depend on the imports of the module.
+/// Misuse, like a missing or empty `remote_messages` attribute, is reported as compile error.
+///
 /// `remote_messages` can take multiple Message Types which get checked for their identifiers.
-#[proc_macro_derive(RemoteActor, attributes(remote_messages, remote_ask_messages))]
+///
+/// # Generics
+///
+/// The `ACTOR_ID` of a generic actor is followed by the `TypeName`s of its type parameters, e.g. `Worker<u32>`.
+/// Its messages may depend on the type parameters, e.g. `#[remote_messages(Job<T>)]`.
+/// `#[remote_actor(id = "...")]` sets a fixed `ACTOR_ID` instead, for actors that are only started with one
+/// instantiation per node, and lifts the `TypeName` requirement of the type parameters.
+/// `TypeName` is implemented for primitives, `String`, the common containers of `std`, tuples and arrays;
+/// other type arguments, e.g. your own data types, implement it by hand.
+#[proc_macro_derive(
+    RemoteActor,
+    attributes(remote_messages, remote_ask_messages, remote_actor)
+)]
 pub fn remote_actor_macro(input: TokenStream) -> TokenStream {
     remote_actor::remote_actor_macro(input)
 }
 
 /// Helper to make messages sendable over network
 /// # Example
-/// ```
-/// #[derive(Message, Serialize, Deserialize, RemoteMessage)]
+/// ```no_run
+/// # use actix::prelude::*;
+/// # use actix_telepathy::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(RemoteMessage, Serialize, Deserialize)]
 /// struct MyMessage {}
 ///
-/// // ...
-///
 /// #[derive(RemoteActor)]
 /// #[remote_messages(MyMessage)]
 /// struct MyActor {}
-/// // ...
+/// # impl Actor for MyActor {
+/// #     type Context = Context<Self>;
+/// # }
+/// # impl Handler<MyMessage> for MyActor {
+/// #     type Result = ();
+/// #     fn handle(&mut self, _msg: MyMessage, _ctx: &mut Self::Context) -> Self::Result {}
+/// # }
 /// ```
 ///
 /// # Background
 ///
 /// In the previous example, the MyMessage struct gets extended the following way:
 ///
-/// ```
-/// impl RemoteMessage for MyMessage {
-///     type Serializer = DefaultSerialization;
+/// ```ignore
+/// impl ::actix_telepathy::RemoteMessage for MyMessage {
+///     type Serializer = ::actix_telepathy::MessageSerialization;
 ///     const IDENTIFIER: &'static str = "MyMessage";
 ///
 ///     fn get_serializer(&self) -> Box<Self::Serializer> {
-///         Box::new(DefaultSerialization {})
+///         Box::new(MessageSerialization {})
 ///     }
 ///
 ///     fn generate_serializer() -> Box<Self::Serializer> {
-///         Box::new(DefaultSerialization {})
+///         Box::new(MessageSerialization {})
 ///     }
 ///
 ///     fn set_source(&mut self, addr: Addr<NetworkInterface>) {
@@ -90,15 +126,74 @@ pub fn remote_actor_macro(input: TokenStream) -> TokenStream {
 /// That attribute needs to have the following type: `RemoteAddr`
 ///
 /// ## Example
-/// ```
-/// #[derive(Message, Serialize, Deserialize, RemoteMessage)]
+/// ```no_run
+/// # use actix::prelude::*;
+/// # use actix_telepathy::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(RemoteMessage, Serialize, Deserialize)]
 /// #[with_source(source)]
 /// struct MyMessage {
-///     source: RemoteAddr
+///     source: RemoteAddr,
 /// }
 /// ```
 ///
-#[proc_macro_derive(RemoteMessage, attributes(with_source))]
+/// ## Identifier
+///
+/// Receivers tell messages apart by their `IDENTIFIER`, which is the type name by default.
+/// `#[remote_message(id = "...")]` sets a stable identifier that survives renaming the type;
+/// `#[remote_message(module_path)]` prefixes the type name with its module path, so equally named messages of
+/// different modules do not collide. The `RemoteActor` derive fails to compile if two of its messages share an identifier.
+///
+/// ```no_run
+/// # use actix::prelude::*;
+/// # use actix_telepathy::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(RemoteMessage, Serialize, Deserialize)]
+/// #[remote_message(id = "billing.Ping.v1")]
+/// struct Ping {}
+/// ```
+///
+/// The identifier of a generic message is followed by the `TypeName`s of its type parameters, e.g. `Job<u32>`,
+/// so that each instantiation is told apart, see the `RemoteActor` derive for the types implementing `TypeName`.
+/// An explicit `id` is taken as is. Const generic parameters are not supported.
+///
+/// ## Result
+///
+/// The derive also implements `Message`. Its result is `()` unless `#[rtype(result = "...")]` sets another type.
+/// With `#[remote_message(reply)]`, the result is serialized and returned to the sending node, so that
+/// `RemoteAddr::send` resolves with it; the result then has to be serializable. Without it, there is no reply
+/// and the message does not implement `RemoteReply`, which `RemoteAddr::send` requires.
+///
+/// ```no_run
+/// # use actix::prelude::*;
+/// # use actix_telepathy::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(RemoteMessage, Serialize, Deserialize)]
+/// #[rtype(result = "usize")]
+/// #[remote_message(reply)]
+/// struct CountRequest {}
+/// ```
+///
+/// ## Serializer
+///
+/// Messages are serialized with `MessageSerialization` unless the attribute `serializer` names another
+/// `CustomSerialization`. `MessageSerialization` is selected by the `default-serializer-*` features of actix-telepathy.
+/// A `custom_serializer` in the `telepathy.yaml` next to the crate's `Cargo.toml` still changes the serializer of
+/// all messages of the crate without the attribute, but is deprecated.
+///
+/// ```no_run
+/// # use actix::prelude::*;
+/// # use actix_telepathy::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(RemoteMessage, Serialize, Deserialize)]
+/// #[serializer(DefaultSerialization)]
+/// struct MyMessage {}
+/// ```
+///
+#[proc_macro_derive(
+    RemoteMessage,
+    attributes(with_source, serializer, remote_message, rtype)
+)]
 pub fn remote_message_macro(input: TokenStream) -> TokenStream {
     remote_message::remote_message_macro(input)
 }
@@ -1,123 +1,227 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::__private::Span;
-use syn::parse::Parser;
+use quote::{quote, quote_spanned};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::{parse_macro_input, DeriveInput, Result};
-type AttributeArgs = syn::punctuated::Punctuated<syn::Meta, syn::Token![,]>;
+
+use crate::generics;
 
 const REMOTE_MESSAGES: &str = "remote_messages";
+const REMOTE_ACTOR: &str = "remote_actor";
 
 pub fn remote_actor_macro(input: TokenStream) -> TokenStream {
-    //let ask_remote = proc_macro2::TokenStream::from(remote_actor_remote_ask_messages_macro(input.clone()));
-    remote_actor_remote_messages_macro(input)
+    let input = parse_macro_input!(input as DeriveInput);
+    remote_actor_remote_messages_macro(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-pub fn remote_actor_remote_messages_macro(input: TokenStream) -> TokenStream {
-    // Parse the input tokens into a syntax tree
-    let input = parse_macro_input!(input as DeriveInput);
+fn remote_actor_remote_messages_macro(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
     let name = &input.ident;
-    let (impl_generics, ty_generics, where_clause) = &input.generics.split_for_impl();
-    let messages =
-        get_message_types_attr(&input, REMOTE_MESSAGES).expect("Expected at least one Message");
-
-    let mut match_statement = quote! {};
-
-    for attr in messages.iter() {
-        let name = attr.as_ref().unwrap();
-        let matching = quote! {
-            #name::IDENTIFIER => {
-                let mut deserialized_msg: #name = #name::generate_serializer().deserialize(&(msg.message_buffer)[..]).expect("Cannot deserialized #name message");
-                if msg.source.clone().is_some() {
-                    deserialized_msg.set_source(msg.source.unwrap());
+    let messages = get_message_types_attr(input, REMOTE_MESSAGES)?;
+    let id = get_id_attr(input)?;
+    let is_generic = generics::is_generic(&input.generics);
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    // the messages of generic actors may depend on the type parameters, so the handler needs their bounds
+    let mut predicates: Vec<syn::WherePredicate> = vec![];
+    if is_generic {
+        predicates.push(syn::parse_quote!(Self: ::actix::Actor));
+        for message in messages.iter() {
+            predicates.push(syn::parse_quote!(Self: ::actix::Handler<#message>));
+            predicates.push(syn::parse_quote!(
+                #message: ::actix_telepathy::RemoteMessage + ::actix_telepathy::__private::DeserializeOwned + 'static
+            ));
+        }
+    }
+    let handler_generics = generics::with_bounds(&input.generics, id.is_none(), predicates);
+    let (impl_generics, _, where_clause) = handler_generics.split_for_impl();
+    let type_name_generics = generics::with_bounds(&input.generics, id.is_none(), vec![]);
+    let (type_name_impl_generics, _, type_name_where_clause) = type_name_generics.split_for_impl();
+
+    let mut dispatch = quote! {};
+    for message in messages.iter() {
+        dispatch = quote! {
+            #dispatch
+            if msg.identifier == <#message as ::actix_telepathy::RemoteMessage>::IDENTIFIER {
+                let mut deserialized_msg: #message = match ::actix_telepathy::CustomSerialization::deserialize(
+                    &*<#message as ::actix_telepathy::RemoteMessage>::generate_serializer(),
+                    &(msg.message_buffer)[..],
+                ) {
+                    ::std::result::Result::Ok(deserialized_msg) => deserialized_msg,
+                    ::std::result::Result::Err(_) => {
+                        ::actix_telepathy::__private::warn!("Message dropped because {} could not be deserialized", &(msg.identifier));
+                        return;
+                    }
+                };
+                let reply_to = msg.reply_to();
+                if let ::std::option::Option::Some(source) = msg.source {
+                    ::actix_telepathy::RemoteMessage::set_source(&mut deserialized_msg, source);
                 }
-                ctx.address().do_send(deserialized_msg);
-            },
-        };
-        match_statement = quote! {
-            #match_statement
-            #matching
+                let response = <Self as ::actix::Handler<#message>>::handle(self, deserialized_msg, ctx);
+                match reply_to {
+                    ::std::option::Option::Some(reply_to) => {
+                        let (tx, mut rx) = ::actix_telepathy::__private::oneshot::channel();
+                        ::actix::dev::MessageResponse::<Self, #message>::handle(response, ctx, ::std::option::Option::Some(tx));
+                        // synchronous results are answered right away, futures once they resolve
+                        match rx.try_recv() {
+                            ::std::result::Result::Ok(result) => reply_to.reply::<#message>(&result),
+                            ::std::result::Result::Err(_) => {
+                                let trace_context = ::actix_telepathy::TraceContext::current();
+                                ::actix::spawn(async move {
+                                    if let ::std::result::Result::Ok(result) = rx.await {
+                                        ::actix_telepathy::TraceContext::scope(trace_context, || {
+                                            reply_to.reply::<#message>(&result)
+                                        });
+                                    }
+                                });
+                            }
+                        }
+                    }
+                    ::std::option::Option::None => {
+                        ::actix::dev::MessageResponse::<Self, #message>::handle(response, ctx, ::std::option::Option::None)
+                    }
+                }
+                return;
+            }
         };
     }
-    match_statement = quote! {
-        match msg.identifier.as_str() {
-            #match_statement
-            _ => warn!("Message dropped because identifier {} is unknown", &(msg.identifier))
-        }
-    };
 
     let name_str = name.to_string();
+    let actor_id = match id {
+        Some(id) => quote!(#id),
+        None => generics::identifier(&input.generics, quote!(#name_str))?,
+    };
+
+    // two messages with the same identifier could not be told apart
+    let mut distinct_identifiers = quote! {};
+    for (i, first) in messages.iter().enumerate() {
+        for second in messages.iter().skip(i + 1) {
+            let error = format!(
+                "`{}` and `{}` of the remote actor `{}` have the same IDENTIFIER",
+                quote!(#first),
+                quote!(#second),
+                name_str
+            );
+            let check = quote_spanned! {second.span()=>
+                ::std::assert!(
+                    !::actix_telepathy::__private::identifiers_equal(
+                        <#first as ::actix_telepathy::RemoteMessage>::IDENTIFIER,
+                        <#second as ::actix_telepathy::RemoteMessage>::IDENTIFIER
+                    ),
+                    #error
+                );
+            };
+            distinct_identifiers = quote! {
+                #distinct_identifiers
+                #check
+            };
+        }
+    }
+
+    // the identifiers of generic messages are only known once the actor is instantiated,
+    // so the check is deferred to the handler, which evaluates it for each instantiation
+    let (distinct_identifiers, check_distinct_identifiers) = if is_generic {
+        (
+            quote! {
+                impl #impl_generics #name #ty_generics #where_clause {
+                    #[doc(hidden)]
+                    const __DISTINCT_REMOTE_MESSAGE_IDENTIFIERS: () = { #distinct_identifiers };
+                }
+            },
+            quote! {
+                #[allow(clippy::let_unit_value)]
+                let () = Self::__DISTINCT_REMOTE_MESSAGE_IDENTIFIERS;
+            },
+        )
+    } else {
+        (
+            quote! { const _: () = { #distinct_identifiers }; },
+            quote! {},
+        )
+    };
 
-    // Build the output, possibly using quasi-quotation
-    let expanded = quote! {
-        use log::*;
+    Ok(quote! {
+        impl #impl_generics ::actix_telepathy::RemoteActor for #name #ty_generics #where_clause {
+            const ACTOR_ID: &'static str = #actor_id;
+        }
 
-        impl #impl_generics RemoteActor for #name #ty_generics #where_clause {
-            const ACTOR_ID: &'static str = #name_str;
+        impl #type_name_impl_generics ::actix_telepathy::TypeName for #name #ty_generics #type_name_where_clause {
+            const TYPE_NAME: &'static str = #actor_id;
         }
 
-        impl #impl_generics Handler<RemoteWrapper> for #name #ty_generics #where_clause {
+        #distinct_identifiers
+
+        impl #impl_generics ::actix::Handler<::actix_telepathy::RemoteWrapper> for #name #ty_generics #where_clause {
             type Result = ();
 
-            fn handle(&mut self, mut msg: RemoteWrapper, ctx: &mut Self::Context) -> Self::Result {
-                #match_statement
+            fn handle(&mut self, mut msg: ::actix_telepathy::RemoteWrapper, ctx: &mut Self::Context) -> Self::Result {
+                #check_distinct_identifiers
+                let _stage = msg.enter_receive_stage("actor");
+                // remote messages sent while handling `msg` continue its trace
+                ::actix_telepathy::TraceContext::scope(msg.trace_context, || {
+                    #dispatch
+                    ::actix_telepathy::__private::warn!("Message dropped because identifier {} is unknown", &(msg.identifier));
+                })
             }
         }
-    };
-
-    // Hand the output tokens back to the compiler
-    TokenStream::from(expanded)
+    })
 }
 
-fn get_message_types_attr(ast: &DeriveInput, ident: &str) -> Result<Vec<Option<syn::Type>>> {
+fn get_message_types_attr(ast: &DeriveInput, ident: &str) -> Result<Vec<syn::Type>> {
+    let syntax = format!("The correct syntax is #[{}(Message, Message, ...)]", ident);
     let attr = ast
         .attrs
         .iter()
-        .find_map(|attr| {
-            if attr.path().is_ident(ident) {
-                Some(attr.meta.clone())
-            } else {
-                None
-            }
-        })
+        .find(|attr| attr.path().is_ident(ident))
         .ok_or_else(|| {
-            syn::Error::new(Span::call_site(), format!("Expect an attribute `{ident}`"))
+            syn::Error::new_spanned(
+                &ast.ident,
+                format!(
+                    "A RemoteActor needs an attribute #[{}(...)] listing the messages it receives",
+                    ident
+                ),
+            )
         })?;
 
-    match attr {
-        syn::Meta::List(ref list) => {
-            let parser = AttributeArgs::parse_terminated;
-            let args = match parser.parse2(list.tokens.clone()) {
-                Ok(args) => args,
-                Err(_) => {
-                    return Err(syn::Error::new_spanned(
-                        attr,
-                        format!("The correct syntax is #[{}(Message, Message, ...)]", ident),
-                    ))
-                }
-            };
-            Ok(args.iter().map(|m| meta_item_to_struct(m).ok()).collect())
-        }
-        syn::Meta::Path(path) => match path.get_ident() {
-            Some(ident) => syn::parse_str::<syn::Type>(&ident.to_string())
-                .map(|ty| vec![Some(ty)])
-                .map_err(|_| syn::Error::new_spanned(ident, "Expect type")),
-            None => Err(syn::Error::new_spanned(path, "Expect type")),
-        },
-        _ => Err(syn::Error::new_spanned(
+    let messages = match &attr.meta {
+        syn::Meta::List(list) => list
+            .parse_args_with(Punctuated::<syn::Type, syn::Token![,]>::parse_terminated)
+            .map_err(|err| syn::Error::new(err.span(), "Expected a Message type"))?,
+        _ => return Err(syn::Error::new_spanned(attr, syntax)),
+    };
+    if messages.is_empty() {
+        return Err(syn::Error::new_spanned(
             attr,
-            format!("The correct syntax is #[{}(Message, Message, ...)]", ident),
-        )),
+            "Expected at least one Message",
+        ));
     }
+    Ok(messages.into_iter().collect())
 }
 
-fn meta_item_to_struct(meta_item: &syn::Meta) -> syn::Result<syn::Type> {
-    match meta_item {
-        syn::Meta::Path(ref path) => match path.get_ident() {
-            Some(ident) => syn::parse_str::<syn::Type>(&ident.to_string())
-                .map_err(|_| syn::Error::new_spanned(ident, "Expect Message")),
-            None => Err(syn::Error::new_spanned(path, "Expect Message")),
-        },
-        syn::Meta::NameValue(val) => Err(syn::Error::new_spanned(val, "Expect Message")),
-        meta => Err(syn::Error::new_spanned(meta, "Expect type")),
+/// The `id` of `#[remote_actor(id = "...")]`, which replaces the type name and the type parameters in the `ACTOR_ID`.
+fn get_id_attr(ast: &DeriveInput) -> Result<Option<syn::LitStr>> {
+    let mut id = None;
+    for attr in ast
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident(REMOTE_ACTOR))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                if value.value().is_empty() {
+                    return Err(meta.error("the id must not be empty"));
+                }
+                id = Some(value);
+                Ok(())
+            } else {
+                Err(meta.error(format!(
+                    "The correct syntax is #[{}(id = \"...\")]",
+                    REMOTE_ACTOR
+                )))
+            }
+        })?;
     }
+    Ok(id)
 }
@@ -1,114 +1,296 @@
-use log::*;
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::Span;
+use quote::{quote, quote_spanned};
 use serde_derive::{Deserialize, Serialize};
-use std::fs::File;
+use std::path::Path;
 use syn::{parse_macro_input, DeriveInput, Result};
 
+use crate::generics;
+
 const TELEPATHY_CONFIG_FILE: &str = "telepathy.yaml";
 const WITH_SOURCE: &str = "with_source";
+const SERIALIZER: &str = "serializer";
+const REMOTE_MESSAGE: &str = "remote_message";
+const RTYPE: &str = "rtype";
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct Config {
     pub custom_serializer: String,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            custom_serializer: "DefaultSerialization".to_string(),
-        }
+/// Reads the `telepathy.yaml` next to the `Cargo.toml` of the crate being compiled, if there is one.
+fn load_config_yaml() -> Result<Option<Config>> {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let path = Path::new(&dir).join(TELEPATHY_CONFIG_FILE);
+    if !path.exists() {
+        return Ok(None);
     }
+    let error = |err: String| {
+        syn::Error::new(
+            Span::call_site(),
+            format!("Invalid {}: {}", path.display(), err),
+        )
+    };
+    let content = std::fs::read_to_string(&path).map_err(|err| error(err.to_string()))?;
+    serde_yaml::from_str(&content)
+        .map(Some)
+        .map_err(|err| error(err.to_string()))
 }
 
-fn load_config_yaml() -> Config {
-    let f = File::open(TELEPATHY_CONFIG_FILE);
-    match f {
-        Ok(file_reader) => {
-            serde_yaml::from_reader(file_reader).expect("Config file is no valid YAML")
-        }
-        Err(e) => {
-            error!("{}, using default Config", e.to_string());
-            Config::default()
+/// The serializer given by `#[serializer(...)]`, else the one of `telepathy.yaml`, else `MessageSerialization`.
+/// Returns whether it was read from the deprecated `telepathy.yaml`.
+fn get_serializer(ast: &DeriveInput) -> Result<(syn::Path, bool)> {
+    if let Some(attr) = ast
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident(SERIALIZER))
+    {
+        return attr
+            .parse_args::<syn::Path>()
+            .map(|path| (path, false))
+            .map_err(|_| {
+                syn::Error::new_spanned(
+                    attr,
+                    format!("The correct syntax is #[{}(MySerializer)]", SERIALIZER),
+                )
+            });
+    }
+    match load_config_yaml()? {
+        Some(config) => syn::parse_str::<syn::Path>(&config.custom_serializer)
+            .map(|path| (path, true))
+            .map_err(|_| {
+                syn::Error::new(
+                    Span::call_site(),
+                    format!(
+                        "custom_serializer `{}` in {} is no type path",
+                        config.custom_serializer, TELEPATHY_CONFIG_FILE
+                    ),
+                )
+            }),
+        None => Ok((
+            syn::parse_quote!(::actix_telepathy::MessageSerialization),
+            false,
+        )),
+    }
+}
+
+/// Options of `#[remote_message(...)]`.
+#[derive(Default)]
+struct RemoteMessageAttr {
+    id: Option<syn::LitStr>,
+    module_path: bool,
+    reply: bool,
+}
+
+fn get_remote_message_attr(ast: &DeriveInput) -> Result<RemoteMessageAttr> {
+    let mut options = RemoteMessageAttr::default();
+    for attr in ast
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident(REMOTE_MESSAGE))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                if value.value().is_empty() {
+                    return Err(meta.error("the id must not be empty"));
+                }
+                options.id = Some(value);
+                Ok(())
+            } else if meta.path.is_ident("module_path") {
+                options.module_path = true;
+                Ok(())
+            } else if meta.path.is_ident("reply") {
+                options.reply = true;
+                Ok(())
+            } else {
+                Err(meta.error(format!(
+                    "The correct syntax is #[{0}(id = \"...\")], #[{0}(module_path)] or #[{0}(reply)]",
+                    REMOTE_MESSAGE
+                )))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+/// `IDENTIFIER` of the message: the `id` of `#[remote_message(...)]`, the type name prefixed by its module path
+/// with `#[remote_message(module_path)]`, else the type name; the latter two followed by the type parameters of
+/// generic messages. Returns whether the identifier is composed of the type parameters' `TypeName`s.
+fn get_identifier(
+    ast: &DeriveInput,
+    options: &RemoteMessageAttr,
+) -> Result<(proc_macro2::TokenStream, bool)> {
+    let name = ast.ident.to_string();
+    let base = match (&options.id, options.module_path) {
+        (Some(id), true) => {
+            return Err(syn::Error::new_spanned(
+                id,
+                "`id` and `module_path` exclude each other",
+            ))
         }
+        (Some(id), false) => return Ok((quote! { #id }, false)),
+        (None, true) => quote! { ::std::concat!(::std::module_path!(), "::", #name) },
+        (None, false) => quote! { #name },
+    };
+    Ok((generics::identifier(&ast.generics, base)?, true))
+}
+
+/// The result type of `#[rtype(result = "...")]` or `#[rtype(...)]` like with actix' `Message` derive, else `()`.
+fn get_result_type(ast: &DeriveInput) -> Result<syn::Type> {
+    let attr = match ast.attrs.iter().find(|attr| attr.path().is_ident(RTYPE)) {
+        Some(attr) => attr,
+        None => return Ok(syn::parse_quote!(())),
+    };
+    if let Ok(ty) = attr.parse_args::<syn::Type>() {
+        return Ok(ty);
     }
+    let mut result = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("result") {
+            let value: syn::LitStr = meta.value()?.parse()?;
+            result = Some(value.parse::<syn::Type>()?);
+            Ok(())
+        } else {
+            Err(meta.error(format!(
+                "The correct syntax is #[{}(result = \"...\")]",
+                RTYPE
+            )))
+        }
+    })?;
+    result.ok_or_else(|| {
+        syn::Error::new_spanned(
+            attr,
+            format!("The correct syntax is #[{}(result = \"...\")]", RTYPE),
+        )
+    })
 }
 
 pub fn remote_message_macro(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = &input.ident;
-    let (impl_generics, ty_generics, where_clause) = &input.generics.split_for_impl();
-    let s = name.to_string();
-    let sources = get_with_source_attr(&input).expect("Expected correct syntax");
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let set_source = match sources.first() {
-        Some(source) => {
-            let attr = source.clone().unwrap();
-            quote! {
-                self.#attr.node.network_interface = Some(addr);
-            }
+fn expand(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let (message_impl_generics, ty_generics, message_where_clause) =
+        input.generics.split_for_impl();
+    let options = get_remote_message_attr(input)?;
+    // generic messages are only remote messages for serializable type parameters
+    let mut predicates: Vec<syn::WherePredicate> = vec![];
+    if generics::is_generic(&input.generics) {
+        predicates.push(
+            syn::parse_quote!(Self: ::std::marker::Send + ::actix_telepathy::__private::Serialize),
+        );
+        if options.reply {
+            predicates.push(syn::parse_quote!(<Self as ::actix::Message>::Result: ::actix_telepathy::__private::Serialize));
         }
+    }
+    let (identifier, type_names) = get_identifier(input, &options)?;
+    let generics = generics::with_bounds(&input.generics, type_names, predicates);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let type_name_generics = generics::with_bounds(&input.generics, type_names, vec![]);
+    let (type_name_impl_generics, _, type_name_where_clause) = type_name_generics.split_for_impl();
+
+    let set_source = match get_with_source_attr(input)? {
+        Some(field) => quote! {
+            self.#field.node.network_interface = ::std::option::Option::Some(addr);
+        },
         None => quote! {},
     };
-
-    let config: Config = load_config_yaml();
-    let serializer = syn::parse_str::<syn::Type>(&config.custom_serializer).unwrap_or_else(|_| {
-        panic!(
-            "custom_serializer {} could not be found",
-            &config.custom_serializer
+    let (serializer, from_config_yaml) = get_serializer(input)?;
+    // proc macros cannot warn on stable, but using a deprecated item does
+    let config_yaml_deprecation = if from_config_yaml {
+        quote_spanned! {name.span()=>
+            const _: () = ::actix_telepathy::__private::TELEPATHY_YAML;
+        }
+    } else {
+        quote! {}
+    };
+    let result_type = get_result_type(input)?;
+    // without `reply`, the default of the trait does not answer, so the result need not be serializable
+    let (serialize_result, reply_impl) = if options.reply {
+        (
+            quote! {
+                fn serialize_result(result: &Self::Result) -> ::std::option::Option<::std::vec::Vec<u8>> {
+                    ::actix_telepathy::CustomSerialization::serialize(&*Self::generate_serializer(), result).ok()
+                }
+            },
+            quote! {
+                impl #impl_generics ::actix_telepathy::RemoteReply for #name #ty_generics #where_clause {}
+            },
         )
-    });
+    } else {
+        (quote! {}, quote! {})
+    };
 
-    let expanded = quote! {
-        use log::*;
+    Ok(quote! {
+        #config_yaml_deprecation
 
-        impl #impl_generics RemoteMessage for #name #ty_generics #where_clause {
+        impl #impl_generics ::actix_telepathy::RemoteMessage for #name #ty_generics #where_clause {
             type Serializer = #serializer;
-            const IDENTIFIER: &'static str = #s;
+            const IDENTIFIER: &'static str = #identifier;
 
-            fn get_serializer(&self) -> Box<Self::Serializer> {
-                Box::new(#serializer {})
+            fn get_serializer(&self) -> ::std::boxed::Box<Self::Serializer> {
+                ::std::boxed::Box::new(#serializer {})
             }
 
-            fn generate_serializer() -> Box<Self::Serializer> {
-                Box::new(#serializer {})
+            fn generate_serializer() -> ::std::boxed::Box<Self::Serializer> {
+                ::std::boxed::Box::new(#serializer {})
             }
 
-            fn set_source(&mut self, addr: Addr<NetworkInterface>) {
+            fn set_source(&mut self, addr: ::actix::Addr<::actix_telepathy::NetworkInterface>) {
                 #set_source
             }
+
+            #serialize_result
         }
 
-        impl #impl_generics Message for #name #ty_generics #where_clause {
-            type Result = ();
+        #reply_impl
+
+        impl #message_impl_generics ::actix::Message for #name #ty_generics #message_where_clause {
+            type Result = #result_type;
         }
-    };
 
-    TokenStream::from(expanded)
+        impl #type_name_impl_generics ::actix_telepathy::TypeName for #name #ty_generics #type_name_where_clause {
+            const TYPE_NAME: &'static str = #identifier;
+        }
+    })
 }
 
-fn get_with_source_attr(ast: &DeriveInput) -> Result<Vec<Option<syn::Type>>> {
-    let attr = ast.attrs.iter().find_map(|attr| {
-        if attr.path().is_ident(WITH_SOURCE) {
-            attr.parse_args().ok()
-        } else {
-            None
-        }
-    });
-
-    match attr {
-        Some(a) => match a {
-            syn::Meta::Path(path) => match path.get_ident() {
-                Some(ident) => syn::parse_str::<syn::Type>(&ident.to_string())
-                    .map(|ty| vec![Some(ty)])
-                    .map_err(|_| syn::Error::new_spanned(ident, "Expect type")),
-                None => Err(syn::Error::new_spanned(path, "Expect type")),
-            },
-            _ => Err(syn::Error::new_spanned(
-                a,
-                format!("The correct syntax is #[{}(<RemoteAddr>)]", WITH_SOURCE),
-            )),
-        },
-        None => Ok(vec![]),
+/// The field named by `#[with_source(field)]`, which must be a `RemoteAddr`.
+fn get_with_source_attr(ast: &DeriveInput) -> Result<Option<syn::Ident>> {
+    let attr = match ast
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident(WITH_SOURCE))
+    {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let field: syn::Ident = attr.parse_args().map_err(|_| {
+        syn::Error::new_spanned(
+            attr,
+            format!(
+                "The correct syntax is #[{}(<RemoteAddr field>)]",
+                WITH_SOURCE
+            ),
+        )
+    })?;
+
+    let has_field = match &ast.data {
+        syn::Data::Struct(data) => data.fields.iter().any(|f| f.ident.as_ref() == Some(&field)),
+        _ => false,
+    };
+    if has_field {
+        Ok(Some(field))
+    } else {
+        Err(syn::Error::new_spanned(
+            &field,
+            format!("`{}` has no field `{}`", ast.ident, field),
+        ))
     }
 }
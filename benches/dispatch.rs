@@ -0,0 +1,112 @@
+use actix::prelude::*;
+use actix_telepathy::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct Increment(u64);
+
+#[derive(Message)]
+#[rtype(result = "u64")]
+struct Total;
+
+/// Handles remote messages with the generated handler, which calls `Handler<Increment>` directly.
+#[derive(RemoteActor, Default)]
+#[remote_messages(Increment)]
+struct Direct {
+    total: u64,
+}
+
+impl Actor for Direct {
+    type Context = Context<Self>;
+}
+
+impl Handler<Increment> for Direct {
+    type Result = ();
+
+    fn handle(&mut self, msg: Increment, _ctx: &mut Self::Context) -> Self::Result {
+        self.total += msg.0;
+    }
+}
+
+impl Handler<Total> for Direct {
+    type Result = u64;
+
+    fn handle(&mut self, _msg: Total, _ctx: &mut Self::Context) -> Self::Result {
+        self.total
+    }
+}
+
+/// Handles remote messages like the derive did before: deserialized messages take another trip through the mailbox.
+#[derive(Default)]
+struct Forwarding {
+    total: u64,
+}
+
+impl Actor for Forwarding {
+    type Context = Context<Self>;
+}
+
+impl Handler<RemoteWrapper> for Forwarding {
+    type Result = ();
+
+    fn handle(&mut self, msg: RemoteWrapper, ctx: &mut Self::Context) -> Self::Result {
+        let increment: Increment = Increment::generate_serializer()
+            .deserialize(&msg.message_buffer)
+            .unwrap();
+        ctx.address().do_send(increment);
+    }
+}
+
+impl Handler<Increment> for Forwarding {
+    type Result = ();
+
+    fn handle(&mut self, msg: Increment, _ctx: &mut Self::Context) -> Self::Result {
+        self.total += msg.0;
+    }
+}
+
+impl Handler<Total> for Forwarding {
+    type Result = u64;
+
+    fn handle(&mut self, _msg: Total, _ctx: &mut Self::Context) -> Self::Result {
+        self.total
+    }
+}
+
+/// Time from sending a wrapped message until a following local message sees its effect.
+fn dispatch(c: &mut Criterion) {
+    let system = System::new();
+    let (direct, forwarding) =
+        system.block_on(async { (Direct::default().start(), Forwarding::default().start()) });
+
+    let mut group = c.benchmark_group("remote_wrapper_dispatch");
+    group.bench_function("direct", |b| {
+        b.iter(|| {
+            system.block_on(async {
+                direct.do_send(RemoteWrapper::new(
+                    RemoteAddr::default(),
+                    Increment(1),
+                    None,
+                ));
+                direct.send(Total).await.unwrap()
+            })
+        })
+    });
+    group.bench_function("mailbox_hop", |b| {
+        b.iter(|| {
+            system.block_on(async {
+                forwarding.do_send(RemoteWrapper::new(
+                    RemoteAddr::default(),
+                    Increment(1),
+                    None,
+                ));
+                forwarding.send(Total).await.unwrap()
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -12,6 +12,7 @@ use std::net::SocketAddr;
 use std::str::FromStr;
 
 #[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+#[serializer(DefaultSerialization)]
 pub struct GossipEvent {
     members: Vec<SocketAddr>,
 }
@@ -0,0 +1,483 @@
+use crate::network::DEFAULT_CONNECT_TIMEOUT;
+use crate::{
+    ConnectionProtocol, DnsSrvSeeds, FileSeeds, NodeAddr, SeedProvider, StaticSeeds, TcpTransport,
+    Transport,
+};
+use derive_more::Display;
+#[cfg(feature = "toml")]
+use serde::Deserialize;
+use std::fmt;
+use std::io;
+#[cfg(feature = "toml")]
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8000";
+const DEFAULT_RECONNECT_ATTEMPTS: u8 = 2;
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_GOSSIP_FANOUT: usize = 3;
+const DEFAULT_DISCOVERY_INTERVAL: Duration = Duration::from_secs(5);
+const ENV_PREFIX: &str = "TELEPATHY_";
+
+#[derive(Debug, Display)]
+pub enum ConfigError {
+    /// The configuration file could not be read
+    #[display(fmt = "Failed reading configuration: {}", _0)]
+    Io(io::Error),
+
+    /// The configuration is no valid TOML or has unknown keys or environment variables
+    #[display(fmt = "Failed parsing configuration: {}", _0)]
+    Parse(String),
+
+    /// A required key is not set
+    #[display(fmt = "Missing configuration key '{}'", _0)]
+    Missing(&'static str),
+
+    /// (key, reason)
+    #[display(fmt = "Invalid value for '{}': {}", _0, _1)]
+    Invalid(&'static str, String),
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Settings of a cluster node. Built with [ClusterConfig::builder](#method.builder), which validates them,
+/// or loaded from environment variables or, with the `toml` feature, from a TOML file.
+///
+/// ```toml
+/// own_addr = "0.0.0.0:1992"
+/// advertised_addr = "10.0.0.5:31992" # optional
+/// seed_nodes = ["127.0.0.1:1993"] # or one of
+/// # seed_file = "/etc/telepathy/seeds"
+/// # seed_srv = "_telepathy._tcp.cluster.local"
+/// discovery_interval_ms = 5000
+/// connection_protocol = "gossip" # or "single_seed"
+/// connect_timeout_ms = 1000
+/// reconnect_attempts = 2
+/// reconnect_delay_ms = 1000
+/// gossip_fanout = 3
+/// ```
+///
+/// The environment variables have the same names in upper case with the prefix `TELEPATHY_`, e.g. `TELEPATHY_OWN_ADDR`;
+/// `TELEPATHY_SEED_NODES` separates the addresses by commas.
+#[derive(Clone)]
+pub struct ClusterConfig {
+    own_addr: NodeAddr,
+    advertised_addr: Option<NodeAddr>,
+    seed_nodes: Vec<NodeAddr>,
+    seed_provider: Option<Arc<dyn SeedProvider>>,
+    discovery_interval: Duration,
+    connection_protocol: ConnectionProtocol,
+    connect_timeout: Duration,
+    reconnect_attempts: u8,
+    reconnect_delay: Duration,
+    gossip_fanout: usize,
+    transport: Arc<dyn Transport>,
+}
+
+impl ClusterConfig {
+    pub fn builder<A: Into<NodeAddr>>(own_addr: A) -> ClusterConfigBuilder {
+        ClusterConfigBuilder {
+            config: Self {
+                own_addr: own_addr.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Configuration of the constructors taking the addresses directly, which are not validated.
+    pub(crate) fn new(
+        own_addr: NodeAddr,
+        seed_nodes: Vec<NodeAddr>,
+        connection_protocol: ConnectionProtocol,
+        transport: Arc<dyn Transport>,
+    ) -> Self {
+        Self {
+            own_addr,
+            seed_nodes,
+            connection_protocol,
+            transport,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        let raw: RawConfig =
+            toml::from_str(toml).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        raw.into_config()
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let toml = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml(&toml)
+    }
+
+    /// Reads the `TELEPATHY_*` environment variables; unknown ones are rejected like unknown keys of a file.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(std::env::vars())
+    }
+
+    pub(crate) fn from_vars<I: IntoIterator<Item = (String, String)>>(
+        vars: I,
+    ) -> Result<Self, ConfigError> {
+        let mut raw = RawConfig::default();
+        for (var, value) in vars {
+            let key = match var.strip_prefix(ENV_PREFIX) {
+                Some(key) => key.to_lowercase(),
+                None => continue,
+            };
+            match key.as_str() {
+                "own_addr" => raw.own_addr = Some(value),
+                "advertised_addr" => raw.advertised_addr = Some(value),
+                "seed_nodes" => {
+                    raw.seed_nodes = Some(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|addr| !addr.is_empty())
+                            .map(String::from)
+                            .collect(),
+                    )
+                }
+                "seed_file" => raw.seed_file = Some(value),
+                "seed_srv" => raw.seed_srv = Some(value),
+                "discovery_interval_ms" => {
+                    raw.discovery_interval_ms = Some(parse_var(&value, "discovery_interval_ms")?)
+                }
+                "connection_protocol" => raw.connection_protocol = Some(value),
+                "connect_timeout_ms" => {
+                    raw.connect_timeout_ms = Some(parse_var(&value, "connect_timeout_ms")?)
+                }
+                "reconnect_attempts" => {
+                    raw.reconnect_attempts = Some(parse_var(&value, "reconnect_attempts")?)
+                }
+                "reconnect_delay_ms" => {
+                    raw.reconnect_delay_ms = Some(parse_var(&value, "reconnect_delay_ms")?)
+                }
+                "gossip_fanout" => raw.gossip_fanout = Some(parse_var(&value, "gossip_fanout")?),
+                _ => {
+                    return Err(ConfigError::Parse(format!(
+                        "unknown environment variable {}",
+                        var
+                    )))
+                }
+            }
+        }
+        raw.into_config()
+    }
+
+    /// Address the node binds its listener to.
+    pub fn own_addr(&self) -> &NodeAddr {
+        &self.own_addr
+    }
+
+    /// Address the node is reached at and identified by in the cluster; the address it is bound to,
+    /// unless another one is advertised, e.g. behind NAT or a port mapping.
+    pub fn advertised_addr(&self) -> &NodeAddr {
+        self.advertised_addr.as_ref().unwrap_or(&self.own_addr)
+    }
+
+    pub fn seed_nodes(&self) -> &[NodeAddr] {
+        &self.seed_nodes
+    }
+
+    /// Where the node finds its seed nodes; the [seed_nodes](#method.seed_nodes) unless another provider is set.
+    pub fn seed_provider(&self) -> Arc<dyn SeedProvider> {
+        match &self.seed_provider {
+            Some(seed_provider) => seed_provider.clone(),
+            None => Arc::new(StaticSeeds::new(self.seed_nodes.clone())),
+        }
+    }
+
+    /// How often a lonely node asks the seed provider for seed nodes again.
+    pub fn discovery_interval(&self) -> Duration {
+        self.discovery_interval
+    }
+
+    /// `SingleSeed` unless set otherwise, like for [Cluster::new](./struct.Cluster.html#method.new), although
+    /// `ConnectionProtocol::default()` is `Gossip`: a configuration without a protocol behaves like the
+    /// constructors taking the addresses directly.
+    pub fn connection_protocol(&self) -> ConnectionProtocol {
+        self.connection_protocol
+    }
+
+    /// How long connecting to another node may take.
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// How often a NetworkInterface connects again after losing its connection, before the node is reported down.
+    pub fn reconnect_attempts(&self) -> u8 {
+        self.reconnect_attempts
+    }
+
+    pub fn reconnect_delay(&self) -> Duration {
+        self.reconnect_delay
+    }
+
+    /// Number of members the Gossip connector forwards a member event to.
+    pub fn gossip_fanout(&self) -> usize {
+        self.gossip_fanout
+    }
+
+    pub fn transport(&self) -> Arc<dyn Transport> {
+        self.transport.clone()
+    }
+
+    pub(crate) fn with_own_addr(mut self, own_addr: NodeAddr) -> Self {
+        self.own_addr = own_addr;
+        self
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            own_addr: NodeAddr::from_str(DEFAULT_ADDR).unwrap(),
+            advertised_addr: None,
+            seed_nodes: vec![],
+            seed_provider: None,
+            discovery_interval: DEFAULT_DISCOVERY_INTERVAL,
+            connection_protocol: ConnectionProtocol::SingleSeed,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            reconnect_attempts: DEFAULT_RECONNECT_ATTEMPTS,
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            gossip_fanout: DEFAULT_GOSSIP_FANOUT,
+            transport: Arc::new(TcpTransport),
+        }
+    }
+}
+
+impl fmt::Debug for ClusterConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClusterConfig")
+            .field("own_addr", &self.own_addr)
+            .field("advertised_addr", &self.advertised_addr)
+            .field("seed_nodes", &self.seed_nodes)
+            .field("discovery_interval", &self.discovery_interval)
+            .field("connection_protocol", &self.connection_protocol)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("reconnect_attempts", &self.reconnect_attempts)
+            .field("reconnect_delay", &self.reconnect_delay)
+            .field("gossip_fanout", &self.gossip_fanout)
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct ClusterConfigBuilder {
+    config: ClusterConfig,
+}
+
+impl ClusterConfigBuilder {
+    pub fn advertised_addr<A: Into<NodeAddr>>(mut self, advertised_addr: A) -> Self {
+        self.config.advertised_addr = Some(advertised_addr.into());
+        self
+    }
+
+    pub fn seed_nodes<A: Into<NodeAddr>>(mut self, seed_nodes: Vec<A>) -> Self {
+        self.config.seed_nodes = seed_nodes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Finds the seed nodes via `seed_provider`, e.g. [DnsSrvSeeds](./struct.DnsSrvSeeds.html), instead of a fixed list.
+    pub fn seed_provider(mut self, seed_provider: impl SeedProvider) -> Self {
+        self.config.seed_provider = Some(Arc::new(seed_provider));
+        self
+    }
+
+    pub fn discovery_interval(mut self, discovery_interval: Duration) -> Self {
+        self.config.discovery_interval = discovery_interval;
+        self
+    }
+
+    pub fn connection_protocol(mut self, connection_protocol: ConnectionProtocol) -> Self {
+        self.config.connection_protocol = connection_protocol;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.config.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn reconnect_attempts(mut self, reconnect_attempts: u8) -> Self {
+        self.config.reconnect_attempts = reconnect_attempts;
+        self
+    }
+
+    pub fn reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.config.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    pub fn gossip_fanout(mut self, gossip_fanout: usize) -> Self {
+        self.config.gossip_fanout = gossip_fanout;
+        self
+    }
+
+    /// Connects to other nodes via `transport` instead of TCP.
+    pub fn transport(mut self, transport: impl Transport) -> Self {
+        self.config.transport = Arc::new(transport);
+        self
+    }
+
+    pub fn build(self) -> Result<ClusterConfig, ConfigError> {
+        let config = self.config;
+        if config.connect_timeout.is_zero() {
+            return Err(ConfigError::Invalid(
+                "connect_timeout",
+                "must be greater than zero".to_string(),
+            ));
+        }
+        if config.discovery_interval.is_zero() {
+            return Err(ConfigError::Invalid(
+                "discovery_interval",
+                "must be greater than zero".to_string(),
+            ));
+        }
+        if config.seed_provider.is_some() && !config.seed_nodes.is_empty() {
+            return Err(ConfigError::Invalid(
+                "seed_nodes",
+                "are replaced by the seed provider".to_string(),
+            ));
+        }
+        if config.gossip_fanout == 0 {
+            return Err(ConfigError::Invalid(
+                "gossip_fanout",
+                "must be at least 1".to_string(),
+            ));
+        }
+        if let Some(NodeAddr::Tcp(advertised)) = &config.advertised_addr {
+            if advertised.ip().is_unspecified() || advertised.port() == 0 {
+                return Err(ConfigError::Invalid(
+                    "advertised_addr",
+                    format!("{} is not reachable by other nodes", advertised),
+                ));
+            }
+        }
+        if config.seed_nodes.contains(&config.own_addr)
+            || config.seed_nodes.contains(config.advertised_addr())
+        {
+            return Err(ConfigError::Invalid(
+                "seed_nodes",
+                format!(
+                    "must not contain the own address {}",
+                    config.advertised_addr()
+                ),
+            ));
+        }
+        if matches!(config.connection_protocol, ConnectionProtocol::SingleSeed)
+            && config.seed_nodes.len() > 1
+        {
+            return Err(ConfigError::Invalid(
+                "seed_nodes",
+                "SingleSeed expects at most one seed node".to_string(),
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// Configuration as written in a file or the environment.
+#[derive(Default)]
+#[cfg_attr(feature = "toml", derive(Deserialize), serde(deny_unknown_fields))]
+struct RawConfig {
+    own_addr: Option<String>,
+    advertised_addr: Option<String>,
+    seed_nodes: Option<Vec<String>>,
+    seed_file: Option<String>,
+    seed_srv: Option<String>,
+    discovery_interval_ms: Option<u64>,
+    connection_protocol: Option<String>,
+    connect_timeout_ms: Option<u64>,
+    reconnect_attempts: Option<u8>,
+    reconnect_delay_ms: Option<u64>,
+    gossip_fanout: Option<usize>,
+}
+
+impl RawConfig {
+    fn into_config(self) -> Result<ClusterConfig, ConfigError> {
+        let own_addr = parse_addr(
+            &self.own_addr.ok_or(ConfigError::Missing("own_addr"))?,
+            "own_addr",
+        )?;
+        let mut builder = ClusterConfig::builder(own_addr);
+        if let Some(advertised_addr) = self.advertised_addr {
+            builder = builder.advertised_addr(parse_addr(&advertised_addr, "advertised_addr")?);
+        }
+        if let Some(seed_nodes) = self.seed_nodes {
+            let seed_nodes = seed_nodes
+                .iter()
+                .map(|addr| parse_addr(addr, "seed_nodes"))
+                .collect::<Result<Vec<_>, _>>()?;
+            builder = builder.seed_nodes(seed_nodes);
+        }
+        if self.seed_file.is_some() && self.seed_srv.is_some() {
+            return Err(ConfigError::Invalid(
+                "seed_srv",
+                "either seed_file or seed_srv can be set".to_string(),
+            ));
+        }
+        if let Some(seed_file) = self.seed_file {
+            builder = builder.seed_provider(FileSeeds::new(seed_file));
+        }
+        if let Some(seed_srv) = self.seed_srv {
+            let seeds = DnsSrvSeeds::new(seed_srv)
+                .map_err(|err| ConfigError::Invalid("seed_srv", err.to_string()))?;
+            builder = builder.seed_provider(seeds);
+        }
+        if let Some(discovery_interval_ms) = self.discovery_interval_ms {
+            builder = builder.discovery_interval(Duration::from_millis(discovery_interval_ms));
+        }
+        if let Some(connection_protocol) = self.connection_protocol {
+            builder = builder.connection_protocol(match connection_protocol.as_str() {
+                "single_seed" => ConnectionProtocol::SingleSeed,
+                "gossip" => ConnectionProtocol::Gossip,
+                other => {
+                    return Err(ConfigError::Invalid(
+                        "connection_protocol",
+                        format!("'{}' is neither 'single_seed' nor 'gossip'", other),
+                    ))
+                }
+            });
+        }
+        if let Some(connect_timeout_ms) = self.connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(connect_timeout_ms));
+        }
+        if let Some(reconnect_attempts) = self.reconnect_attempts {
+            builder = builder.reconnect_attempts(reconnect_attempts);
+        }
+        if let Some(reconnect_delay_ms) = self.reconnect_delay_ms {
+            builder = builder.reconnect_delay(Duration::from_millis(reconnect_delay_ms));
+        }
+        if let Some(gossip_fanout) = self.gossip_fanout {
+            builder = builder.gossip_fanout(gossip_fanout);
+        }
+        builder.build()
+    }
+}
+
+fn parse_addr(addr: &str, key: &'static str) -> Result<NodeAddr, ConfigError> {
+    addr.parse()
+        .map_err(|err| ConfigError::Invalid(key, format!("'{}': {}", addr, err)))
+}
+
+fn parse_var<T: FromStr>(value: &str, key: &'static str) -> Result<T, ConfigError>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|err| ConfigError::Invalid(key, format!("'{}': {}", value, err)))
+}
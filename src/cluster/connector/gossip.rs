@@ -1,16 +1,17 @@
 use crate::cluster::connector::messages::{
-    GossipEvent, GossipJoining, GossipMessage, NodeResolving,
+    GossipEvent, GossipJoining, GossipMessage, NodeResolving, SeedResolved, SeedsDiscovered,
 };
 use crate::cluster::connector::{Connector, ConnectorVariant};
 use crate::network::NetworkInterface;
-use crate::{Cluster, ConnectToNode, CustomSystemService, Node, NodeEvent, RemoteAddr};
+use crate::{
+    cluster::ConnectToSeed, Cluster, ClusterConfig, ConnectToNode, CustomSystemService, Node,
+    NodeAddr, NodeEvent, RemoteAddr,
+};
 use actix::prelude::*;
 use log::*;
 use rand::prelude::{IteratorRandom, ThreadRng};
 use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
-use std::net::SocketAddr;
-use std::str::FromStr;
 
 const CONNECTOR: &str = "Connector";
 
@@ -24,25 +25,28 @@ enum GossipState {
 /// The Gossip connector variant can connect the nodes to each other. Each node can have a different seed node.
 /// When joining the cluster, the node will connect to its seed node and receives the number of nodes that are about to join.
 /// The seed node of that node will then send the joining node's information to the other nodes via the Gossip protocol.
-/// Thereby, the seed node randomly chooses 3 nodes and sends the information to them. These 3 nodes will connect to the joining node.
+/// Thereby, the seed node randomly chooses 3 nodes (the fanout, see [ClusterConfig::gossip_fanout](../struct.ClusterConfig.html#method.gossip_fanout))
+/// and sends the information to them. These 3 nodes will connect to the joining node.
 /// Then the 3 nodes will send the information to 3 other nodes and so on.
 /// This variant is recommended if the seed node is not always available.
 /// This variant is not recommended if the cluster is very large, because the gossip protocol takes more time the larger the cluster is.
 pub struct Gossip {
-    own_addr: SocketAddr,
-    members: HashMap<SocketAddr, Addr<NetworkInterface>>,
-    waiting_to_add: HashSet<SocketAddr>,
+    own_addr: NodeAddr,
+    members: HashMap<NodeAddr, Addr<NetworkInterface>>,
+    waiting_to_add: HashSet<NodeAddr>,
     state: GossipState,
     about_to_join: Option<usize>,
     gossip_msgs: Vec<GossipMessage>,
     info_msgs_to_send: Vec<Node>,
-    seed_nodes: Vec<SocketAddr>,
+    seed_nodes: Vec<NodeAddr>,
+    cluster: Option<Addr<Cluster>>,
+    fanout: usize,
 }
 
 impl Default for Gossip {
     fn default() -> Self {
         Self {
-            own_addr: SocketAddr::from_str("127.0.0.1:8000").unwrap(),
+            own_addr: ClusterConfig::default().own_addr().clone(),
             members: HashMap::new(),
             waiting_to_add: HashSet::new(),
             state: GossipState::Lonely,
@@ -50,12 +54,14 @@ impl Default for Gossip {
             gossip_msgs: vec![],
             info_msgs_to_send: vec![],
             seed_nodes: vec![],
+            cluster: None,
+            fanout: ClusterConfig::default().gossip_fanout(),
         }
     }
 }
 
 impl Gossip {
-    pub fn new(own_addr: SocketAddr, seed_nodes: Vec<SocketAddr>) -> Self {
+    pub fn new(own_addr: NodeAddr, seed_nodes: Vec<NodeAddr>) -> Self {
         Self {
             own_addr,
             seed_nodes,
@@ -63,40 +69,50 @@ impl Gossip {
         }
     }
 
+    /// Number of members a member event is forwarded to.
+    pub fn with_fanout(mut self, fanout: usize) -> Self {
+        self.fanout = fanout;
+        self
+    }
+
+    pub(crate) fn set_cluster(&mut self, cluster: Addr<Cluster>) {
+        self.cluster = Some(cluster);
+    }
+
     fn add_member(&mut self, node: Node) {
+        debug!(target: &self.own_addr.to_string(), "Member {} added!", node.addr);
         self.members.insert(
-            node.socket_addr,
+            node.addr,
             node.network_interface.expect("Empty network interface"),
         );
-        debug!(target: &self.own_addr.to_string(), "Member {} added!", node.socket_addr.to_string());
     }
 
-    fn remove_member(&mut self, addr: SocketAddr) {
-        self.members.remove(&addr);
-        debug!(target: &self.own_addr.to_string(), "Member {} removed", addr.to_string());
+    fn remove_member(&mut self, addr: &NodeAddr) {
+        self.members.remove(addr);
+        debug!(target: &self.own_addr.to_string(), "Member {} removed", addr);
     }
 
-    fn ignite_member_up(&self, new_addr: SocketAddr) {
-        debug!(target: &self.own_addr.to_string(), "Igniting member up {}", new_addr.to_string());
+    fn ignite_member_up(&self, new_addr: NodeAddr) {
+        debug!(target: &self.own_addr.to_string(), "Igniting member up {}", new_addr);
         self.gossip_member_event(
-            new_addr,
+            new_addr.clone(),
             GossipEvent::Join,
-            HashSet::from_iter([self.own_addr, new_addr]),
+            HashSet::from_iter([self.own_addr.clone(), new_addr]),
         );
     }
 
-    fn ignite_member_down(&self, leaving_addr: SocketAddr) {
-        debug!(target: &self.own_addr.to_string(), "Igniting member down {}", leaving_addr.to_string());
+    fn ignite_member_down(&self, leaving_addr: NodeAddr) {
+        debug!(target: &self.own_addr.to_string(), "Igniting member down {}", leaving_addr);
         self.gossip_member_event(
             leaving_addr,
             GossipEvent::Leave,
-            HashSet::from_iter([self.own_addr]),
+            HashSet::from_iter([self.own_addr.clone()]),
         );
     }
 
-    fn gossip_member_event(&self, addr: SocketAddr, event: GossipEvent, seen: HashSet<SocketAddr>) {
-        debug!(target: &self.own_addr.to_string(), "Gossiping member event {} {:?} {:?}", addr.to_string(), event, seen);
-        let random_members = self.choose_random_members(3, &seen);
+    fn gossip_member_event(&self, addr: NodeAddr, event: GossipEvent, seen: HashSet<NodeAddr>) {
+        debug!(target: &self.own_addr.to_string(), "Gossiping member event {} {:?} {:?}", addr, event, seen);
+        let random_members = self.choose_random_members(self.fanout, &seen);
 
         let gossip_message = GossipMessage { event, addr, seen };
 
@@ -105,33 +121,32 @@ impl Gossip {
         }
     }
 
-    fn choose_random_members(
-        &self,
-        amount: usize,
-        except: &HashSet<SocketAddr>,
-    ) -> Vec<RemoteAddr> {
+    fn choose_random_members(&self, amount: usize, except: &HashSet<NodeAddr>) -> Vec<RemoteAddr> {
         let mut rng = ThreadRng::default();
         self.members
             .iter()
             .filter(|(addr, _)| !except.contains(addr))
             .choose_multiple(&mut rng, amount)
             .into_iter()
-            .map(|(socket_addr, network_interface)| {
-                RemoteAddr::new_connector(*socket_addr, Some(network_interface.clone()))
+            .map(|(addr, network_interface)| {
+                RemoteAddr::new_connector(addr.clone(), Some(network_interface.clone()))
             })
             .collect()
     }
 
-    fn connect_to_node(&mut self, addr: &SocketAddr) {
-        self.waiting_to_add.insert(*addr);
-        Cluster::from_custom_registry().do_send(ConnectToNode(*addr))
+    fn connect_to_node(&mut self, addr: &NodeAddr) {
+        self.waiting_to_add.insert(addr.clone());
+        self.cluster
+            .clone()
+            .unwrap_or_else(Cluster::from_custom_registry)
+            .do_send(ConnectToNode(addr.clone()))
     }
 
-    fn all_seen(&self, seen: &HashSet<SocketAddr>) -> bool {
-        let members: HashSet<SocketAddr> = self.members.keys().cloned().collect();
+    fn all_seen(&self, seen: &HashSet<NodeAddr>) -> bool {
+        let members: HashSet<NodeAddr> = self.members.keys().cloned().collect();
         members
             .difference(seen)
-            .collect::<HashSet<&SocketAddr>>()
+            .collect::<HashSet<&NodeAddr>>()
             .is_empty()
     }
 
@@ -158,7 +173,7 @@ impl Gossip {
         let all_seen = self.all_seen(&msg.seen);
         let mut seen = msg.seen;
         let member_contains = self.members.contains_key(&msg.addr);
-        seen.insert(self.own_addr);
+        seen.insert(self.own_addr.clone());
 
         match &msg.event {
             GossipEvent::Join => {
@@ -213,12 +228,12 @@ impl Gossip {
     }
 
     fn share_info_with_joining_member(&self, node: Node) {
-        debug!(target: &self.own_addr.to_string(), "Sharing info with joining member {}", node.socket_addr.to_string());
+        debug!(target: &self.own_addr.to_string(), "Sharing info with joining member {}", node.addr);
         node.get_remote_addr(CONNECTOR.to_string())
             .do_send(GossipJoining {
                 about_to_join: self.members.len(),
             });
-        self.ignite_member_up(node.socket_addr);
+        self.ignite_member_up(node.addr);
     }
 
     fn share_info_with_joining_members(&mut self) {
@@ -227,6 +242,26 @@ impl Gossip {
         }
     }
 
+    /// Joins all seed nodes.
+    pub(crate) fn handle_seeds_discovered(&mut self, msg: SeedsDiscovered) {
+        self.seed_nodes = msg.0;
+        let cluster = self
+            .cluster
+            .clone()
+            .unwrap_or_else(Cluster::from_custom_registry);
+        for seed_node in self.seed_nodes.iter() {
+            cluster.do_send(ConnectToSeed(seed_node.clone()));
+        }
+    }
+
+    pub(crate) fn handle_seed_resolved(&mut self, msg: SeedResolved) {
+        for seed_node in self.seed_nodes.iter_mut() {
+            if *seed_node == msg.seed {
+                *seed_node = msg.addr.clone();
+            }
+        }
+    }
+
     fn seed_nodes_already_members(&self) -> bool {
         self.seed_nodes
             .iter()
@@ -239,7 +274,7 @@ impl ConnectorVariant for Gossip {
         match msg {
             NodeEvent::MemberUp(node, seed) => {
                 self.add_member(node.clone());
-                if !self.waiting_to_add.remove(&node.socket_addr) {
+                if !self.waiting_to_add.remove(&node.addr) {
                     match &self.state {
                         GossipState::Lonely => {
                             if seed {
@@ -279,7 +314,7 @@ impl ConnectorVariant for Gossip {
                 }
             }
             NodeEvent::MemberDown(host) => {
-                self.remove_member(host);
+                self.remove_member(&host);
                 self.ignite_member_down(host);
             }
         }
@@ -289,11 +324,11 @@ impl ConnectorVariant for Gossip {
         &mut self,
         _msg: NodeResolving,
         _ctx: &mut Context<Connector>,
-    ) -> &HashMap<SocketAddr, Addr<NetworkInterface>> {
+    ) -> &HashMap<NodeAddr, Addr<NetworkInterface>> {
         &self.members
     }
 
-    fn get_own_addr(&self) -> SocketAddr {
-        self.own_addr
+    fn get_own_addr(&self) -> NodeAddr {
+        self.own_addr.clone()
     }
 }
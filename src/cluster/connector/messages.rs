@@ -1,16 +1,28 @@
 use crate::NetworkInterface;
-use crate::{DefaultSerialization, RemoteMessage};
+use crate::{DefaultSerialization, NodeAddr, RemoteMessage};
 use actix::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::net::SocketAddr;
 
 #[derive(Message)]
 #[rtype(result = "Result<Vec<Addr<NetworkInterface>>, ()>")]
 pub struct NodeResolving {
-    pub addrs: Vec<SocketAddr>,
+    pub addrs: Vec<NodeAddr>,
 }
 
+/// A seed node is identified by the address `addr` it advertised instead of the one it was dialed with.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct SeedResolved {
+    pub seed: NodeAddr,
+    pub addr: NodeAddr,
+}
+
+/// Seed nodes the [SeedProvider](../trait.SeedProvider.html) returned.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct SeedsDiscovered(pub Vec<NodeAddr>);
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum GossipEvent {
     Join,
@@ -18,16 +30,19 @@ pub enum GossipEvent {
 }
 
 #[derive(RemoteMessage, Serialize, Deserialize, Debug, Clone)]
+#[serializer(DefaultSerialization)]
 pub struct GossipMessage {
     pub event: GossipEvent,
-    pub addr: SocketAddr,
-    pub seen: HashSet<SocketAddr>,
+    pub addr: NodeAddr,
+    pub seen: HashSet<NodeAddr>,
 }
 
 #[derive(RemoteMessage, Serialize, Deserialize, Debug, Clone)]
+#[serializer(DefaultSerialization)]
 pub struct GossipJoining {
     pub about_to_join: usize,
 }
 
 #[derive(RemoteMessage, Serialize, Deserialize, Debug, Clone)]
-pub struct SingleSeedMembers(pub Vec<SocketAddr>);
+#[serializer(DefaultSerialization)]
+pub struct SingleSeedMembers(pub Vec<NodeAddr>);
@@ -1,11 +1,14 @@
 pub use crate::cluster::connector::messages::NodeResolving;
 use crate::cluster::connector::messages::{GossipJoining, GossipMessage};
-use crate::{CustomSerialization, RemoteActor, RemoteMessage, RemoteWrapper};
-use crate::{CustomSystemService, Gossip, NetworkInterface, NodeEvent, SingleSeed};
+pub(crate) use crate::cluster::connector::messages::{SeedResolved, SeedsDiscovered};
+use crate::{AddrRequest, AddrResolver, RemoteActor};
+use crate::{
+    Cluster, ClusterConfig, CustomSystemService, Gossip, NetworkInterface, NodeAddr, NodeEvent,
+    SingleSeed,
+};
 use actix::prelude::*;
 use log::*;
 use std::collections::HashMap;
-use std::net::SocketAddr;
 
 use self::messages::SingleSeedMembers;
 
@@ -16,18 +19,13 @@ pub mod single_seed;
 #[cfg(test)]
 mod tests;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum ConnectionProtocol {
     SingleSeed,
+    #[default]
     Gossip,
 }
 
-impl Default for ConnectionProtocol {
-    fn default() -> Self {
-        Self::Gossip
-    }
-}
-
 #[derive(RemoteActor)]
 #[remote_messages(GossipMessage, GossipJoining, SingleSeedMembers)]
 pub enum Connector {
@@ -38,8 +36,8 @@ pub enum Connector {
 impl Connector {
     pub fn from_connection_protocol(
         connection_protocol: ConnectionProtocol,
-        own_address: SocketAddr,
-        seed_nodes: Vec<SocketAddr>,
+        own_address: NodeAddr,
+        seed_nodes: Vec<NodeAddr>,
     ) -> Self {
         match connection_protocol {
             ConnectionProtocol::Gossip => Self::Gossip(Gossip::new(own_address, seed_nodes)),
@@ -47,26 +45,52 @@ impl Connector {
         }
     }
 
+    pub fn from_config(config: &ClusterConfig) -> Self {
+        match config.connection_protocol() {
+            ConnectionProtocol::Gossip => Self::Gossip(
+                Gossip::new(
+                    config.advertised_addr().clone(),
+                    config.seed_nodes().to_vec(),
+                )
+                .with_fanout(config.gossip_fanout()),
+            ),
+            ConnectionProtocol::SingleSeed => {
+                Self::SingleSeed(SingleSeed::new(config.advertised_addr().clone()))
+            }
+        }
+    }
+
+    /// Sends requests for new connections to `cluster` instead of the system's Cluster.
+    pub fn set_cluster(&mut self, cluster: Addr<Cluster>) {
+        match self {
+            Connector::Gossip(gossip) => gossip.set_cluster(cluster),
+            Connector::SingleSeed(single_seed) => single_seed.set_cluster(cluster),
+        }
+    }
+
     pub fn start_service_from(
         connection_protocol: ConnectionProtocol,
-        own_address: SocketAddr,
-        seed_nodes: Vec<SocketAddr>,
+        own_address: NodeAddr,
+        seed_nodes: Vec<NodeAddr>,
     ) {
-        Self::start_service_with(move || {
+        let addr = Self::start_service_with(move || {
             Connector::from_connection_protocol(
                 connection_protocol,
-                own_address,
+                own_address.clone(),
                 seed_nodes.clone(),
             )
         });
+        AddrResolver::from_registry().do_send(AddrRequest::Register(
+            addr.recipient(),
+            Self::ACTOR_ID.to_string(),
+        ));
     }
 }
 
 impl Actor for Connector {
     type Context = Context<Self>;
 
-    fn started(&mut self, ctx: &mut Self::Context) {
-        self.register(ctx.address().recipient());
+    fn started(&mut self, _ctx: &mut Self::Context) {
         debug!("{} actor started", Self::ACTOR_ID);
     }
 }
@@ -91,8 +115,8 @@ pub trait ConnectorVariant {
         &mut self,
         msg: NodeResolving,
         ctx: &mut Context<Connector>,
-    ) -> &HashMap<SocketAddr, Addr<NetworkInterface>>;
-    fn get_own_addr(&self) -> SocketAddr;
+    ) -> &HashMap<NodeAddr, Addr<NetworkInterface>>;
+    fn get_own_addr(&self) -> NodeAddr;
 }
 
 impl Handler<NodeEvent> for Connector {
@@ -139,6 +163,27 @@ impl Handler<NodeResolving> for Connector {
     }
 }
 
+impl Handler<SeedResolved> for Connector {
+    type Result = ();
+
+    fn handle(&mut self, msg: SeedResolved, _ctx: &mut Self::Context) -> Self::Result {
+        if let Connector::Gossip(gossip) = self {
+            gossip.handle_seed_resolved(msg)
+        }
+    }
+}
+
+impl Handler<SeedsDiscovered> for Connector {
+    type Result = ();
+
+    fn handle(&mut self, msg: SeedsDiscovered, _ctx: &mut Self::Context) -> Self::Result {
+        match self {
+            Connector::Gossip(gossip) => gossip.handle_seeds_discovered(msg),
+            Connector::SingleSeed(single_seed) => single_seed.handle_seeds_discovered(msg),
+        }
+    }
+}
+
 // --- Gossip impl ---
 
 impl Handler<GossipMessage> for Connector {
@@ -1,67 +1,99 @@
-use super::{messages::SingleSeedMembers, ConnectorVariant};
+use super::{
+    messages::{SeedsDiscovered, SingleSeedMembers},
+    ConnectorVariant,
+};
+use crate::cluster::ConnectToSeed;
 use crate::{
-    Cluster, ConnectToNode, CustomSystemService, NetworkInterface, Node, NodeEvent, RemoteAddr,
+    Cluster, ClusterConfig, ConnectToNode, CustomSystemService, NetworkInterface, Node, NodeAddr,
+    NodeEvent, RemoteAddr,
 };
 use actix::Addr;
 use log::*;
-use std::{collections::HashMap, net::SocketAddr, str::FromStr};
+use std::collections::HashMap;
 
 /// The SingleSeed connector variant expects all nodes to have the same seed node (except the seed node itself, it has no seed node).
 /// If another node is added, it will be added to the cluster by the seed node.
 /// If a node has a different seed node, errors can occur.
+/// If the [SeedProvider](../trait.SeedProvider.html) returns several seed nodes, only the first one is joined.
 /// This variant is recommended for a fast connection setup, but it is not recommended if the seed node is not always available.
 pub struct SingleSeed {
-    own_addr: SocketAddr,
-    members: HashMap<SocketAddr, Addr<NetworkInterface>>,
+    own_addr: NodeAddr,
+    members: HashMap<NodeAddr, Addr<NetworkInterface>>,
+    cluster: Option<Addr<Cluster>>,
 }
 
 impl Default for SingleSeed {
     fn default() -> Self {
         Self {
-            own_addr: SocketAddr::from_str("127.0.0.1:8000").unwrap(),
+            own_addr: ClusterConfig::default().own_addr().clone(),
             members: HashMap::new(),
+            cluster: None,
         }
     }
 }
 
 impl SingleSeed {
-    pub fn new(own_addr: SocketAddr) -> Self {
+    pub fn new(own_addr: NodeAddr) -> Self {
         Self {
             own_addr,
             ..Default::default()
         }
     }
 
+    pub(crate) fn set_cluster(&mut self, cluster: Addr<Cluster>) {
+        self.cluster = Some(cluster);
+    }
+
     fn add_member(&mut self, node: &Node) {
         self.members
-            .insert(node.socket_addr, node.clone().network_interface.unwrap());
-        debug!(target: &self.own_addr.to_string(), "Member {} added!", node.socket_addr.to_string());
+            .insert(node.addr.clone(), node.clone().network_interface.unwrap());
+        debug!(target: &self.own_addr.to_string(), "Member {} added!", node.addr);
     }
 
-    fn remove_member(&mut self, addr: SocketAddr) {
-        self.members.remove(&addr);
-        debug!("Member {} removed", addr.to_string());
+    fn remove_member(&mut self, addr: &NodeAddr) {
+        self.members.remove(addr);
+        debug!("Member {} removed", addr);
     }
 
-    fn give_information(&mut self, member_addr: SocketAddr) {
-        let members: Vec<SocketAddr> = self
+    fn give_information(&mut self, member_addr: NodeAddr) {
+        let members: Vec<NodeAddr> = self
             .members
             .keys()
-            .filter_map(|x| if x.eq(&member_addr) { None } else { Some(*x) })
+            .filter(|x| !x.eq(&&member_addr))
+            .cloned()
             .collect();
 
         if !members.is_empty() {
             match self.members.get(&member_addr) {
-                Some(node) => RemoteAddr::new_connector(member_addr, Some(node.clone()))
+                Some(node) => RemoteAddr::new_connector(member_addr.clone(), Some(node.clone()))
                     .do_send(SingleSeedMembers(members)),
                 None => error!("Should be known by now"),
             }
         }
     }
 
+    fn cluster(&self) -> Addr<Cluster> {
+        self.cluster
+            .clone()
+            .unwrap_or_else(Cluster::from_custom_registry)
+    }
+
     pub(crate) fn handle_single_seed_members(&mut self, msg: SingleSeedMembers) {
+        let cluster = self.cluster();
         for addr in msg.0 {
-            Cluster::from_custom_registry().do_send(ConnectToNode(addr))
+            cluster.do_send(ConnectToNode(addr))
+        }
+    }
+
+    /// Joins the first seed node, the others are ignored.
+    pub(crate) fn handle_seeds_discovered(&mut self, msg: SeedsDiscovered) {
+        let mut seed_nodes = msg.0.into_iter();
+        if let Some(seed_node) = seed_nodes.next() {
+            let ignored: Vec<NodeAddr> = seed_nodes.collect();
+            if !ignored.is_empty() {
+                warn!(target: &self.own_addr.to_string(), "SingleSeed only joins the first seed node {}, ignoring {:?}", seed_node, ignored);
+            }
+            self.cluster().do_send(ConnectToSeed(seed_node));
         }
     }
 }
@@ -76,11 +108,11 @@ impl ConnectorVariant for SingleSeed {
             NodeEvent::MemberUp(node, seed) => {
                 self.add_member(&node);
                 if seed {
-                    self.give_information(node.socket_addr);
+                    self.give_information(node.addr);
                 }
             }
             NodeEvent::MemberDown(addr) => {
-                self.remove_member(addr);
+                self.remove_member(&addr);
             }
         }
     }
@@ -89,11 +121,11 @@ impl ConnectorVariant for SingleSeed {
         &mut self,
         _msg: crate::NodeResolving,
         _ctx: &mut actix::prelude::Context<crate::Connector>,
-    ) -> &HashMap<SocketAddr, Addr<NetworkInterface>> {
+    ) -> &HashMap<NodeAddr, Addr<NetworkInterface>> {
         &self.members
     }
 
-    fn get_own_addr(&self) -> SocketAddr {
-        self.own_addr
+    fn get_own_addr(&self) -> NodeAddr {
+        self.own_addr.clone()
     }
 }
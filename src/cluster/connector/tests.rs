@@ -1,446 +1,254 @@
+use std::collections::HashSet;
 use std::net::SocketAddr;
 
-use actix_rt::System;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use tokio::time::{timeout, Duration};
 
+use crate::testkit::ClusterEvents;
 use crate::{
-    test_utils::get_n_local_socket_addrs, Cluster, Connector, CustomSystemService, NodeResolving,
+    ClusterConfig, ClusterHandle, ClusterLog, ConnectionProtocol, FaultController, FaultyTransport,
+    MemoryNetwork, NodeAddr, NodeResolving,
 };
 
 const FAILED_TO_RESOLVE_NODES: &str = "Failed to resolve nodes";
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Nodes on one in-memory network
+struct Nodes {
+    addrs: Vec<NodeAddr>,
+    handles: Vec<ClusterHandle>,
+    events: Vec<ClusterEvents>,
+    controller: FaultController,
+}
+
+impl Nodes {
+    /// Starts `n` nodes, node i joins the nodes `seeds(i)`.
+    fn start(n: usize, protocol: ConnectionProtocol, seeds: impl Fn(usize) -> Vec<usize>) -> Self {
+        let network = MemoryNetwork::new();
+        let controller = FaultController::new();
+        let addrs: Vec<NodeAddr> = (0..n as u16)
+            .map(|i| SocketAddr::from(([127, 0, 0, 1], 22000 + i)).into())
+            .collect();
+        let mut handles = vec![];
+        let mut events = vec![];
+        for (i, addr) in addrs.iter().enumerate() {
+            let seed_nodes: Vec<NodeAddr> =
+                seeds(i).into_iter().map(|j| addrs[j].clone()).collect();
+            let config = ClusterConfig::builder(addr.clone())
+                .connection_protocol(protocol)
+                .seed_nodes(seed_nodes)
+                .transport(FaultyTransport::new(network.clone(), controller.clone()))
+                .connect_timeout(Duration::from_millis(100))
+                .reconnect_delay(Duration::from_millis(100))
+                .build()
+                .unwrap();
+            let handle = ClusterHandle::from_config(config);
+            events.push(ClusterEvents::of(&handle));
+            handles.push(handle);
+        }
+        Self {
+            addrs,
+            handles,
+            events,
+            controller,
+        }
+    }
 
-/// all nodes have the same seed node
-fn test_gossip_connector_one_seed(n: usize) {
-    let ips = get_n_local_socket_addrs(n);
+    fn others(&self, i: usize) -> Vec<usize> {
+        (0..self.addrs.len()).filter(|j| *j != i).collect()
+    }
 
-    let seed_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| if i == 0 { vec![] } else { vec![ips[0].clone()] })
-        .collect::<Vec<Vec<SocketAddr>>>();
+    /// Waits until all nodes know all other nodes.
+    async fn assert_converged(&mut self) {
+        for i in 0..self.addrs.len() {
+            let others = self.others(i);
+            self.assert_members(i, others).await;
+        }
+    }
 
-    let other_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| {
-            ips.iter()
-                .enumerate()
-                .filter_map(|(j, _)| if i == j { None } else { Some(ips[j].clone()) })
-                .collect::<Vec<SocketAddr>>()
+    /// Waits until `members` joined node `i` and checks that its connector resolves them.
+    async fn assert_members(&mut self, i: usize, members: Vec<usize>) {
+        let expected: HashSet<NodeAddr> = members.iter().map(|j| self.addrs[*j].clone()).collect();
+        let events = &mut self.events[i];
+        let mut joined = HashSet::new();
+        timeout(WAIT, async {
+            while !expected.is_subset(&joined) {
+                if let Some(ClusterLog::NewMember(node)) = events.next(WAIT).await {
+                    joined.insert(node.addr);
+                }
+            }
         })
-        .collect::<Vec<Vec<SocketAddr>>>();
+        .await
+        .unwrap_or_else(|_| panic!("Node {} did not see all members", self.addrs[i]));
+        self.assert_resolves(i, members).await;
+    }
 
-    let variables = ips
-        .iter()
-        .zip(seed_nodes.iter())
-        .zip(other_nodes.iter())
-        .map(|((own_ip, seed_nodes), other_ips)| {
-            (own_ip.clone(), seed_nodes.clone(), other_ips.clone())
-        })
-        .collect::<Vec<(SocketAddr, Vec<SocketAddr>, Vec<SocketAddr>)>>();
+    /// Checks that the connector of node `i` knows `members`.
+    async fn assert_resolves(&self, i: usize, members: Vec<usize>) {
+        let addrs = self.handles[i]
+            .connector()
+            .send(NodeResolving {
+                addrs: members.iter().map(|j| self.addrs[*j].clone()).collect(),
+            })
+            .await
+            .expect(FAILED_TO_RESOLVE_NODES)
+            .expect(FAILED_TO_RESOLVE_NODES);
+        assert_eq!(addrs.len(), members.len());
+    }
 
-    let results: Vec<Result<usize, ()>> = variables
-        .into_par_iter()
-        .map(|(own_ip, seed_nodes, other_ips)| build_gossip_cluster(own_ip, seed_nodes, other_ips))
-        .collect();
+    /// Node `i` can no longer be reached, like a node that stopped.
+    fn leave(&self, i: usize) {
+        let others: Vec<NodeAddr> = self
+            .others(i)
+            .into_iter()
+            .map(|j| self.addrs[j].clone())
+            .collect();
+        self.controller.partition(&[self.addrs[i].clone()], &others);
+        for other in others {
+            self.controller.disconnect(self.addrs[i].clone(), other);
+        }
+    }
 
-    for result in results {
-        assert_eq!(result.unwrap(), n - 1);
+    /// Waits until the other nodes noticed that node `left` left.
+    async fn assert_left(&mut self, left: usize) {
+        let left_addr = self.addrs[left].clone();
+        for i in self.others(left) {
+            let events = &mut self.events[i];
+            timeout(WAIT, async {
+                loop {
+                    if let Some(ClusterLog::MemberLeft(addr)) = events.next(WAIT).await {
+                        if addr == left_addr {
+                            break;
+                        }
+                    }
+                }
+            })
+            .await
+            .unwrap_or_else(|_| {
+                panic!(
+                    "Node {} did not notice that {} left",
+                    self.addrs[i], left_addr
+                )
+            });
+        }
     }
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_one_seed_2() {
-    test_gossip_connector_one_seed(2);
+/// all nodes have the same seed node
+async fn test_gossip_connector_one_seed(n: usize) {
+    let mut nodes = Nodes::start(n, ConnectionProtocol::Gossip, |i| {
+        if i == 0 {
+            vec![]
+        } else {
+            vec![0]
+        }
+    });
+    nodes.assert_converged().await;
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_one_seed_3() {
-    test_gossip_connector_one_seed(3);
+#[actix_rt::test]
+async fn test_gossip_connector_one_seed_2() {
+    test_gossip_connector_one_seed(2).await;
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_one_seed_8() {
-    test_gossip_connector_one_seed(8);
+#[actix_rt::test]
+async fn test_gossip_connector_one_seed_3() {
+    test_gossip_connector_one_seed(3).await;
 }
 
-/// Node i has seed nodes i-1
-fn test_gossip_connector_chain_seeds(n: usize) {
-    let ips = get_n_local_socket_addrs(n);
-
-    let seed_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| {
-            if i == 0 {
-                vec![]
-            } else {
-                vec![ips[i - 1].clone()]
-            }
-        })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let other_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| {
-            ips.iter()
-                .enumerate()
-                .filter_map(|(j, _)| if i == j { None } else { Some(ips[j].clone()) })
-                .collect::<Vec<SocketAddr>>()
-        })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let variables = ips
-        .iter()
-        .zip(seed_nodes.iter())
-        .zip(other_nodes.iter())
-        .map(|((own_ip, seed_nodes), other_ips)| {
-            (own_ip.clone(), seed_nodes.clone(), other_ips.clone())
-        })
-        .collect::<Vec<(SocketAddr, Vec<SocketAddr>, Vec<SocketAddr>)>>();
-
-    let results: Vec<Result<usize, ()>> = variables
-        .into_par_iter()
-        .map(|(own_ip, seed_nodes, other_ips)| build_gossip_cluster(own_ip, seed_nodes, other_ips))
-        .collect();
-
-    for result in results {
-        assert_eq!(result.unwrap(), n - 1);
-    }
+#[actix_rt::test]
+async fn test_gossip_connector_one_seed_8() {
+    test_gossip_connector_one_seed(8).await;
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_chain_seeds_3() {
-    test_gossip_connector_chain_seeds(3);
+/// Node i has seed nodes i-1
+async fn test_gossip_connector_chain_seeds(n: usize) {
+    let mut nodes = Nodes::start(n, ConnectionProtocol::Gossip, |i| {
+        if i == 0 {
+            vec![]
+        } else {
+            vec![i - 1]
+        }
+    });
+    nodes.assert_converged().await;
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_chain_seeds_8() {
-    test_gossip_connector_chain_seeds(8);
+#[actix_rt::test]
+async fn test_gossip_connector_chain_seeds_3() {
+    test_gossip_connector_chain_seeds(3).await;
 }
 
-#[actix_rt::main]
-async fn build_gossip_cluster(
-    local_ip: SocketAddr,
-    seed_nodes: Vec<SocketAddr>,
-    other_ips: Vec<SocketAddr>,
-) -> Result<usize, ()> {
-    let _cluster = Cluster::new_with_connection_protocol(
-        local_ip,
-        seed_nodes.clone(),
-        crate::ConnectionProtocol::Gossip,
-    );
-    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-    let connector = Connector::from_custom_registry();
-    let addrs = connector
-        .send(NodeResolving { addrs: other_ips })
-        .await
-        .expect(FAILED_TO_RESOLVE_NODES)
-        .expect(FAILED_TO_RESOLVE_NODES);
-    Ok(addrs.len())
+#[actix_rt::test]
+async fn test_gossip_connector_chain_seeds_8() {
+    test_gossip_connector_chain_seeds(8).await;
 }
 
 /// node leaves the cluster
-fn test_gossip_connector_leaving(n: usize) {
-    let ips = get_n_local_socket_addrs(n);
-
-    let seed_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| if i == 0 { vec![] } else { vec![ips[0].clone()] })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let other_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| {
-            ips.iter()
-                .enumerate()
-                .filter_map(|(j, _)| {
-                    if i == j || j == 0 {
-                        None
-                    } else {
-                        Some(ips[j].clone())
-                    }
-                })
-                .collect::<Vec<SocketAddr>>()
-        })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let is_leaving = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| if i == 0 { true } else { false })
-        .collect::<Vec<bool>>();
-
-    let variables = ips
-        .iter()
-        .zip(seed_nodes.iter())
-        .zip(other_nodes.iter())
-        .zip(is_leaving.iter())
-        .map(|(((own_ip, seed_nodes), other_ips), is_leaving)| {
-            (
-                own_ip.clone(),
-                seed_nodes.clone(),
-                other_ips.clone(),
-                *is_leaving,
-            )
-        })
-        .collect::<Vec<(SocketAddr, Vec<SocketAddr>, Vec<SocketAddr>, bool)>>();
-
-    let results: Vec<Result<usize, ()>> = variables
-        .into_par_iter()
-        .map(|(own_ip, seed_nodes, other_ips, is_leaving)| {
-            build_gossip_cluster_leaving(own_ip, seed_nodes, other_ips, is_leaving)
-        })
-        .collect();
-
-    for (result, is_leaving) in results.into_iter().zip(is_leaving.into_iter()) {
-        if !is_leaving {
-            assert_eq!(result.unwrap(), n - 2);
-        }
+async fn test_connector_leaving(n: usize, protocol: ConnectionProtocol) {
+    let mut nodes = Nodes::start(n, protocol, |i| if i == 0 { vec![] } else { vec![0] });
+    nodes.assert_converged().await;
+
+    nodes.leave(0);
+    nodes.assert_left(0).await;
+    for i in 1..n {
+        let remaining = nodes.others(i).into_iter().filter(|j| *j != 0).collect();
+        nodes.assert_resolves(i, remaining).await;
     }
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_leaving_2() {
-    test_gossip_connector_leaving(2);
+#[actix_rt::test]
+async fn test_gossip_connector_leaving_2() {
+    test_connector_leaving(2, ConnectionProtocol::Gossip).await;
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_leaving_3() {
-    test_gossip_connector_leaving(3);
+#[actix_rt::test]
+async fn test_gossip_connector_leaving_3() {
+    test_connector_leaving(3, ConnectionProtocol::Gossip).await;
 }
 
-#[test]
-#[ignore]
-fn test_gossip_connector_leaving_8() {
-    test_gossip_connector_leaving(8);
-}
-
-#[actix_rt::main]
-async fn build_gossip_cluster_leaving(
-    local_ip: SocketAddr,
-    seed_nodes: Vec<SocketAddr>,
-    other_ips: Vec<SocketAddr>,
-    is_leaving: bool,
-) -> Result<usize, ()> {
-    let _cluster = Cluster::new_with_connection_protocol(
-        local_ip,
-        seed_nodes.clone(),
-        crate::ConnectionProtocol::Gossip,
-    );
-    if is_leaving {
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-        System::current().stop();
-        return Err(());
-    }
-
-    tokio::time::sleep(tokio::time::Duration::from_secs(7)).await;
-    let connector = Connector::from_custom_registry();
-    let addrs = connector
-        .send(NodeResolving { addrs: other_ips })
-        .await
-        .expect(FAILED_TO_RESOLVE_NODES)
-        .expect(FAILED_TO_RESOLVE_NODES);
-    Ok(addrs.len())
+#[actix_rt::test]
+async fn test_gossip_connector_leaving_8() {
+    test_connector_leaving(8, ConnectionProtocol::Gossip).await;
 }
 
 // --- SingleSeed ---
 
-// todo: test joining and leaving
-
-fn test_single_seed_connector(n: usize) {
-    let ips = get_n_local_socket_addrs(n);
-
-    let seed_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| if i == 0 { vec![] } else { vec![ips[0].clone()] })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let other_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| {
-            ips.iter()
-                .enumerate()
-                .filter_map(|(j, _)| if i == j { None } else { Some(ips[j].clone()) })
-                .collect::<Vec<SocketAddr>>()
-        })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let variables = ips
-        .iter()
-        .zip(seed_nodes.iter())
-        .zip(other_nodes.iter())
-        .map(|((own_ip, seed_nodes), other_ips)| {
-            (own_ip.clone(), seed_nodes.clone(), other_ips.clone())
-        })
-        .collect::<Vec<(SocketAddr, Vec<SocketAddr>, Vec<SocketAddr>)>>();
-
-    let results: Vec<Result<usize, ()>> = variables
-        .into_par_iter()
-        .map(|(own_ip, seed_nodes, other_ips)| {
-            build_single_seed_cluster(own_ip, seed_nodes, other_ips)
-        })
-        .collect();
-
-    for result in results {
-        assert_eq!(result.unwrap(), n - 1);
-    }
-}
-
-#[actix_rt::main]
-async fn build_single_seed_cluster(
-    local_ip: SocketAddr,
-    seed_nodes: Vec<SocketAddr>,
-    other_ips: Vec<SocketAddr>,
-) -> Result<usize, ()> {
-    let _cluster = Cluster::new_with_connection_protocol(
-        local_ip,
-        seed_nodes.clone(),
-        crate::ConnectionProtocol::SingleSeed,
-    );
-    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-    let connector = Connector::from_custom_registry();
-    let addrs = connector
-        .send(NodeResolving { addrs: other_ips })
-        .await
-        .expect(FAILED_TO_RESOLVE_NODES)
-        .expect(FAILED_TO_RESOLVE_NODES);
-    Ok(addrs.len())
-}
-
-#[test]
-#[ignore]
-fn test_single_seed_connector_2() {
-    test_single_seed_connector(2);
-}
-
-#[test]
-#[ignore]
-fn test_single_seed_connector_3() {
-    test_single_seed_connector(3);
+async fn test_single_seed_connector(n: usize) {
+    let mut nodes = Nodes::start(n, ConnectionProtocol::SingleSeed, |i| {
+        if i == 0 {
+            vec![]
+        } else {
+            vec![0]
+        }
+    });
+    nodes.assert_converged().await;
 }
 
-#[test]
-#[ignore]
-fn test_single_seed_connector_8() {
-    test_single_seed_connector(8);
+#[actix_rt::test]
+async fn test_single_seed_connector_2() {
+    test_single_seed_connector(2).await;
 }
 
-fn test_single_seed_connector_leaving(n: usize) {
-    let ips = get_n_local_socket_addrs(n);
-
-    let seed_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| if i == 0 { vec![] } else { vec![ips[0].clone()] })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let other_nodes = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| {
-            ips.iter()
-                .enumerate()
-                .filter_map(|(j, _)| {
-                    if i == j || j == 0 {
-                        None
-                    } else {
-                        Some(ips[j].clone())
-                    }
-                })
-                .collect::<Vec<SocketAddr>>()
-        })
-        .collect::<Vec<Vec<SocketAddr>>>();
-
-    let is_leaving = ips
-        .iter()
-        .enumerate()
-        .map(|(i, _)| if i == 0 { true } else { false })
-        .collect::<Vec<bool>>();
-
-    let variables = ips
-        .iter()
-        .zip(seed_nodes.iter())
-        .zip(other_nodes.iter())
-        .zip(is_leaving.iter())
-        .map(|(((own_ip, seed_nodes), other_ips), is_leaving)| {
-            (
-                own_ip.clone(),
-                seed_nodes.clone(),
-                other_ips.clone(),
-                *is_leaving,
-            )
-        })
-        .collect::<Vec<(SocketAddr, Vec<SocketAddr>, Vec<SocketAddr>, bool)>>();
-
-    let results: Vec<Result<usize, ()>> = variables
-        .into_par_iter()
-        .map(|(own_ip, seed_nodes, other_ips, is_leaving)| {
-            build_single_seed_cluster_leaving(own_ip, seed_nodes, other_ips, is_leaving)
-        })
-        .collect();
-
-    for (result, is_leaving) in results.into_iter().zip(is_leaving.into_iter()) {
-        if !is_leaving {
-            assert_eq!(result.unwrap(), n - 2);
-        }
-    }
+#[actix_rt::test]
+async fn test_single_seed_connector_3() {
+    test_single_seed_connector(3).await;
 }
 
-#[actix_rt::main]
-async fn build_single_seed_cluster_leaving(
-    local_ip: SocketAddr,
-    seed_nodes: Vec<SocketAddr>,
-    other_ips: Vec<SocketAddr>,
-    is_leaving: bool,
-) -> Result<usize, ()> {
-    let _cluster = Cluster::new_with_connection_protocol(
-        local_ip,
-        seed_nodes.clone(),
-        crate::ConnectionProtocol::SingleSeed,
-    );
-    if is_leaving {
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-        System::current().stop();
-        return Err(());
-    }
-
-    tokio::time::sleep(tokio::time::Duration::from_secs(7)).await;
-    let connector = Connector::from_custom_registry();
-    let addrs = connector
-        .send(NodeResolving { addrs: other_ips })
-        .await
-        .expect(FAILED_TO_RESOLVE_NODES)
-        .expect(FAILED_TO_RESOLVE_NODES);
-    Ok(addrs.len())
+#[actix_rt::test]
+async fn test_single_seed_connector_8() {
+    test_single_seed_connector(8).await;
 }
 
-#[test]
-#[ignore]
-fn test_single_seed_connector_leaving_2() {
-    test_single_seed_connector_leaving(2);
+#[actix_rt::test]
+async fn test_single_seed_connector_leaving_2() {
+    test_connector_leaving(2, ConnectionProtocol::SingleSeed).await;
 }
 
-#[test]
-#[ignore]
-fn test_single_seed_connector_leaving_3() {
-    test_single_seed_connector_leaving(3);
+#[actix_rt::test]
+async fn test_single_seed_connector_leaving_3() {
+    test_connector_leaving(3, ConnectionProtocol::SingleSeed).await;
 }
 
-#[test]
-#[ignore]
-fn test_single_seed_connector_leaving_8() {
-    test_single_seed_connector_leaving(8);
+#[actix_rt::test]
+async fn test_single_seed_connector_leaving_8() {
+    test_connector_leaving(8, ConnectionProtocol::SingleSeed).await;
 }
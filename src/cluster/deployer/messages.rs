@@ -0,0 +1,48 @@
+use crate::cluster::deployer::SpawnError;
+use crate::{AddrResolver, DefaultSerialization, Node, RemoteAddr, RemoteMessage, RemoteWrapper};
+use actix::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub(crate) type SpawnFactory = Box<
+    dyn Fn(
+            &[u8],
+            Addr<AddrResolver>,
+        ) -> Result<(Recipient<RemoteWrapper>, &'static str), SpawnError>
+        + Send,
+>;
+
+/// Makes a `RemoteActor` spawnable under `name`, see [register_factory](./fn.register_factory.html).
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct RegisterFactory {
+    pub(crate) name: String,
+    pub(crate) factory: SpawnFactory,
+}
+
+/// Local request to the [Deployer](./struct.Deployer.html), see [spawn_remote](./fn.spawn_remote.html).
+#[derive(Message)]
+#[rtype(result = "Result<RemoteAddr, SpawnError>")]
+pub struct SpawnRemote {
+    pub node: Node,
+    pub factory_name: String,
+    pub args: Vec<u8>,
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+#[serializer(DefaultSerialization)]
+#[with_source(source)]
+pub(crate) struct SpawnRequest {
+    pub request_id: Uuid,
+    pub factory_name: String,
+    pub args: Vec<u8>,
+    pub source: RemoteAddr,
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+#[serializer(DefaultSerialization)]
+pub(crate) struct SpawnResponse {
+    pub request_id: Uuid,
+    /// `ACTOR_ID` under which the spawned actor is registered
+    pub result: Result<String, SpawnError>,
+}
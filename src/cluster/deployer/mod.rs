@@ -0,0 +1,220 @@
+pub use self::messages::{RegisterFactory, SpawnRemote};
+use self::messages::{SpawnFactory, SpawnRequest, SpawnResponse};
+use crate::{
+    AddrRepresentation, AddrRequest, AddrResolver, AddrResponse, ClusterHandle,
+    CustomSerialization, DefaultSerialization, Node, NodeAddr, RemoteActor, RemoteAddr,
+    RemoteWrapper,
+};
+use actix::prelude::*;
+use derive_more::Display;
+use futures::channel::oneshot;
+use log::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use uuid::Uuid;
+
+mod messages;
+#[cfg(test)]
+mod tests;
+
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Display, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpawnError {
+    #[display(fmt = "No factory registered for '{}'", _0)]
+    UnknownFactory(String),
+    #[display(fmt = "Arguments could not be deserialized")]
+    InvalidArguments,
+    #[display(fmt = "Spawned actor could not be registered")]
+    Registration,
+    #[display(fmt = "Node is not reachable")]
+    Unreachable,
+    #[display(fmt = "Node did not respond in time")]
+    Timeout,
+}
+
+/// System actor that starts registered `RemoteActor` factories on request of other nodes.
+/// Each node has its own, see [ClusterHandle::deployer](./struct.ClusterHandle.html#method.deployer).
+#[derive(RemoteActor)]
+#[remote_messages(SpawnRequest, SpawnResponse)]
+pub struct Deployer {
+    own_addr: NodeAddr,
+    factories: HashMap<String, SpawnFactory>,
+    pending: HashMap<Uuid, (Node, oneshot::Sender<Result<RemoteAddr, SpawnError>>)>,
+    addr_resolver: Addr<AddrResolver>,
+}
+
+impl Deployer {
+    /// Deployer of the node `own_addr` that registers spawned actors in `addr_resolver`.
+    pub fn new(own_addr: NodeAddr, addr_resolver: Addr<AddrResolver>) -> Self {
+        Self {
+            own_addr,
+            factories: HashMap::new(),
+            pending: HashMap::new(),
+            addr_resolver,
+        }
+    }
+
+    /// Starts the actor from the factory and registers it under a unique `ACTOR_ID`.
+    fn spawn(
+        &self,
+        factory_name: &str,
+        args: &[u8],
+    ) -> impl Future<Output = Result<String, SpawnError>> {
+        let addr_resolver = self.addr_resolver.clone();
+        let spawned = match self.factories.get(factory_name) {
+            Some(factory) => factory(args, addr_resolver.clone()),
+            None => Err(SpawnError::UnknownFactory(factory_name.to_string())),
+        };
+
+        async move {
+            let (rec, actor_id) = spawned?;
+            let id = format!("{}-{}", actor_id, Uuid::new_v4());
+            match addr_resolver
+                .send(AddrRequest::Register(rec.clone(), id.clone()))
+                .await
+            {
+                Ok(Ok(_)) => Ok(id),
+                // the actor registered itself already
+                Ok(Err(_)) => match addr_resolver.send(AddrRequest::ResolveRec(rec)).await {
+                    Ok(Ok(AddrResponse::ResolveRec(id))) => Ok(id),
+                    _ => Err(SpawnError::Registration),
+                },
+                Err(_) => Err(SpawnError::Registration),
+            }
+        }
+    }
+}
+
+impl Actor for Deployer {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Self::Context) {
+        debug!("{} actor started", Self::ACTOR_ID);
+    }
+}
+
+impl Handler<RegisterFactory> for Deployer {
+    type Result = ();
+
+    fn handle(&mut self, msg: RegisterFactory, _ctx: &mut Self::Context) -> Self::Result {
+        self.factories.insert(msg.name, msg.factory);
+    }
+}
+
+impl Handler<SpawnRemote> for Deployer {
+    type Result = ResponseFuture<Result<RemoteAddr, SpawnError>>;
+
+    fn handle(&mut self, msg: SpawnRemote, ctx: &mut Self::Context) -> Self::Result {
+        let node = msg.node;
+        if node.addr == self.own_addr {
+            let spawned = self.spawn(&msg.factory_name, &msg.args);
+            return Box::pin(async move {
+                spawned
+                    .await
+                    .map(|id| RemoteAddr::new(node, AddrRepresentation::Key(id)))
+            });
+        }
+
+        let network_interface = match &node.network_interface {
+            Some(network_interface) if network_interface.connected() => network_interface.clone(),
+            _ => return Box::pin(async { Err(SpawnError::Unreachable) }),
+        };
+
+        let request_id = Uuid::new_v4();
+        let (sender, receiver) = oneshot::channel();
+        let deployer =
+            RemoteAddr::new_from_key(node.addr.clone(), network_interface, Self::ACTOR_ID);
+        self.pending.insert(request_id, (node, sender));
+        deployer.do_send(SpawnRequest {
+            request_id,
+            factory_name: msg.factory_name,
+            args: msg.args,
+            source: RemoteAddr::new_from_id(self.own_addr.clone(), Self::ACTOR_ID),
+        });
+        ctx.run_later(SPAWN_TIMEOUT, move |act, _ctx| {
+            if let Some((_, sender)) = act.pending.remove(&request_id) {
+                let _ = sender.send(Err(SpawnError::Timeout));
+            }
+        });
+
+        Box::pin(async move { receiver.await.unwrap_or(Err(SpawnError::Timeout)) })
+    }
+}
+
+impl Handler<SpawnRequest> for Deployer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SpawnRequest, ctx: &mut Self::Context) -> Self::Result {
+        let spawned = self.spawn(&msg.factory_name, &msg.args);
+        let request_id = msg.request_id;
+        let source = msg.source;
+        ctx.spawn(
+            async move {
+                let result = spawned.await;
+                source.do_send(SpawnResponse { request_id, result });
+            }
+            .into_actor(self),
+        );
+    }
+}
+
+impl Handler<SpawnResponse> for Deployer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SpawnResponse, _ctx: &mut Self::Context) -> Self::Result {
+        if let Some((node, sender)) = self.pending.remove(&msg.request_id) {
+            let _ = sender.send(
+                msg.result
+                    .map(|id| RemoteAddr::new(node, AddrRepresentation::Key(id))),
+            );
+        }
+    }
+}
+
+/// Makes actors built by `factory` spawnable on this node under `name`. The arguments sent with
+/// [spawn_remote](./fn.spawn_remote.html) are deserialized with `DefaultSerialization`.
+pub fn register_factory<A, Args, F>(name: &str, factory: F)
+where
+    A: RemoteActor + Actor<Context = Context<A>>,
+    Args: DeserializeOwned,
+    F: Fn(Args) -> A + Send + 'static,
+{
+    ClusterHandle::current().register_factory(name, factory);
+}
+
+pub(crate) fn factory_from<A, Args, F>(factory: F) -> SpawnFactory
+where
+    A: RemoteActor + Actor<Context = Context<A>>,
+    Args: DeserializeOwned,
+    F: Fn(Args) -> A + Send + 'static,
+{
+    Box::new(move |args: &[u8], addr_resolver: Addr<AddrResolver>| {
+        let args = DefaultSerialization {}
+            .deserialize::<Args>(args)
+            .map_err(|_| SpawnError::InvalidArguments)?;
+        let ctx = Context::new();
+        let rec: Recipient<RemoteWrapper> = ctx.address().recipient();
+        let actor = ctx.into_future(factory(args));
+        let registered = rec.clone();
+        // the unique `ACTOR_ID` of the spawned actor is not reused, it is deregistered once the actor stopped
+        actix::spawn(async move {
+            actor.await;
+            addr_resolver.do_send(AddrRequest::Deregister(registered));
+        });
+        Ok((rec, A::ACTOR_ID))
+    })
+}
+
+/// Starts an actor from the factory `factory_name` on `node` and returns its address once it is
+/// registered there. `args` are the serialized arguments of the factory.
+pub fn spawn_remote(
+    node: &Node,
+    factory_name: &str,
+    args: Vec<u8>,
+) -> impl Future<Output = Result<RemoteAddr, SpawnError>> {
+    ClusterHandle::current().spawn_remote(node, factory_name, args)
+}
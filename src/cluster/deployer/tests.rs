@@ -0,0 +1,163 @@
+use crate::testkit::{free_local_addrs, TestCluster};
+use crate::{
+    register_factory, spawn_remote, AddrRequest, AddrResolver, AddrResponse, Cluster,
+    CustomSerialization, DefaultSerialization, MemoryNetwork, Node, RemoteActor, RemoteMessage,
+    SpawnError,
+};
+use actix::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use tokio::time::{sleep, timeout, Duration};
+
+static RECEIVED: once_cell::sync::Lazy<Mutex<Vec<(u32, u32)>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(vec![]));
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct WorkItem {
+    value: u32,
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct StopWorker {}
+
+#[derive(RemoteActor)]
+#[remote_messages(WorkItem, StopWorker)]
+struct SpawnedWorker {
+    offset: u32,
+}
+
+impl Actor for SpawnedWorker {
+    type Context = Context<Self>;
+}
+
+impl Handler<WorkItem> for SpawnedWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: WorkItem, _ctx: &mut Self::Context) -> Self::Result {
+        RECEIVED.lock().unwrap().push((self.offset, msg.value));
+    }
+}
+
+impl Handler<StopWorker> for SpawnedWorker {
+    type Result = ();
+
+    fn handle(&mut self, _msg: StopWorker, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
+
+fn serialize_offset(offset: u32) -> Vec<u8> {
+    DefaultSerialization {}
+        .serialize(&offset)
+        .unwrap_or_else(|_| panic!("Cannot serialize offset"))
+}
+
+#[actix_rt::test]
+async fn spawns_registered_factory_on_own_node() {
+    let own_addr = free_local_addrs(1)[0];
+    let _cluster = Cluster::new(own_addr, vec![]);
+    register_factory("worker", |offset: u32| SpawnedWorker { offset });
+
+    let node = Node::new(own_addr, None);
+    let first = spawn_remote(&node, "worker", serialize_offset(1))
+        .await
+        .unwrap();
+    let second = spawn_remote(&node, "worker", serialize_offset(2))
+        .await
+        .unwrap();
+    assert_ne!(first, second);
+    assert!(first.id.to_string().starts_with(SpawnedWorker::ACTOR_ID));
+
+    match AddrResolver::from_registry()
+        .send(AddrRequest::ResolveStr(first.id.to_string()))
+        .await
+        .unwrap()
+    {
+        Ok(AddrResponse::ResolveStr(_)) => (),
+        _ => panic!("Spawned actor is not registered"),
+    }
+}
+
+#[actix_rt::test]
+async fn spawn_fails_for_unknown_factory_or_arguments() {
+    let own_addr = free_local_addrs(1)[0];
+    let _cluster = Cluster::new(own_addr, vec![]);
+    register_factory("worker", |offset: u32| SpawnedWorker { offset });
+    let node = Node::new(own_addr, None);
+
+    assert_eq!(
+        spawn_remote(&node, "unknown", serialize_offset(1)).await,
+        Err(SpawnError::UnknownFactory("unknown".to_string()))
+    );
+    assert_eq!(
+        spawn_remote(&node, "worker", vec![]).await,
+        Err(SpawnError::InvalidArguments)
+    );
+    assert_eq!(
+        spawn_remote(
+            &Node::new("127.0.0.1:1992".parse::<SocketAddr>().unwrap(), None),
+            "worker",
+            serialize_offset(1)
+        )
+        .await,
+        Err(SpawnError::Unreachable)
+    );
+}
+
+#[actix_rt::test]
+async fn spawns_actor_on_other_node() {
+    let cluster = TestCluster::builder(2)
+        .in_memory(MemoryNetwork::new())
+        .start()
+        .await;
+    cluster
+        .handle(1)
+        .register_factory("worker", |offset: u32| SpawnedWorker { offset });
+
+    let spawned = cluster
+        .handle(0)
+        .spawn_remote(&cluster.node(0, 1), "worker", serialize_offset(10))
+        .await
+        .unwrap();
+    assert_eq!(spawned.node.addr, cluster.addr(1));
+    assert!(spawned.id.to_string().starts_with(SpawnedWorker::ACTOR_ID));
+
+    spawned.do_send(WorkItem { value: 5 });
+    timeout(Duration::from_secs(1), async {
+        while !RECEIVED.lock().unwrap().contains(&(10, 5)) {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Spawned actor did not receive the message");
+}
+
+#[actix_rt::test]
+async fn spawned_actor_is_deregistered_when_stopped() {
+    let cluster = TestCluster::builder(2)
+        .in_memory(MemoryNetwork::new())
+        .start()
+        .await;
+    cluster
+        .handle(1)
+        .register_factory("worker", |offset: u32| SpawnedWorker { offset });
+    let spawned = cluster
+        .handle(0)
+        .spawn_remote(&cluster.node(0, 1), "worker", serialize_offset(20))
+        .await
+        .unwrap();
+
+    spawned.do_send(StopWorker {});
+    let addr_resolver = cluster.handle(1).addr_resolver();
+    timeout(Duration::from_secs(1), async {
+        while let Ok(Ok(_)) = addr_resolver
+            .send(AddrRequest::ResolveStr(spawned.id.to_string()))
+            .await
+        {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Stopped actor is still registered");
+}
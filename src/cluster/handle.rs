@@ -0,0 +1,239 @@
+use crate::cluster::deployer::factory_from;
+use crate::transport::Listener;
+use crate::utils::{add_value_to_registry, value_from_registry};
+use crate::{
+    AddrRequest, AddrResolver, Cluster, ClusterConfig, ClusterLog, ConnectionProtocol, Connector,
+    CustomSystemService, Deployer, Node, NodeAddr, RegisterFactory, RemoteActor, RemoteAddr,
+    SpawnError, SpawnRemote, SubscribeClusterLog, TcpTransport, Transport,
+};
+use actix::prelude::*;
+use log::*;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+
+/// Handle to one cluster node with its own connector, address resolver and network interfaces.
+///
+/// [Cluster::new](./struct.Cluster.html#method.new) starts the node of the current actix system,
+/// which is reachable via the registries and [ClusterHandle::current](#method.current).
+/// Additional nodes in the same process are started with [ClusterHandle::new](#method.new); actors
+/// and addresses are bound to them via `RemoteActor::register_with` and the nodes reported to
+/// [subscribe](#method.subscribe)d listeners.
+#[derive(Clone)]
+pub struct ClusterHandle {
+    config: Arc<ClusterConfig>,
+    cluster: Addr<Cluster>,
+    connector: Addr<Connector>,
+    addr_resolver: Addr<AddrResolver>,
+    deployer: Addr<Deployer>,
+}
+
+impl ClusterHandle {
+    /// Starts a node. If its address cannot be bound, the error is logged and the node stops;
+    /// [bind](#method.bind) returns it instead.
+    pub fn new<A: Into<NodeAddr>>(ip_address: A, seed_nodes: Vec<A>) -> Self {
+        Self::new_with_connection_protocol(ip_address, seed_nodes, ConnectionProtocol::SingleSeed)
+    }
+
+    pub fn new_with_connection_protocol<A: Into<NodeAddr>>(
+        ip_address: A,
+        seed_nodes: Vec<A>,
+        connection_protocol: ConnectionProtocol,
+    ) -> Self {
+        Self::new_with_transport(ip_address, seed_nodes, connection_protocol, TcpTransport)
+    }
+
+    /// Starts a node that connects to other nodes via `transport`, e.g. a
+    /// [MemoryNetwork](./struct.MemoryNetwork.html) for clusters within one test.
+    pub fn new_with_transport<A: Into<NodeAddr>>(
+        ip_address: A,
+        seed_nodes: Vec<A>,
+        connection_protocol: ConnectionProtocol,
+        transport: impl Transport,
+    ) -> Self {
+        Self::from_config(ClusterConfig::new(
+            ip_address.into(),
+            seed_nodes.into_iter().map(Into::into).collect(),
+            connection_protocol,
+            Arc::new(transport),
+        ))
+    }
+
+    /// Starts a node with the settings of `config`, see [ClusterConfig](./struct.ClusterConfig.html).
+    pub fn from_config(config: ClusterConfig) -> Self {
+        Self::start(config, AddrResolver::new().start(), false, None)
+    }
+
+    /// Starts a node like [new](#method.new), but binds its address first and returns an error if that fails.
+    /// With port 0, the node advertises the port it got bound to, see [own_addr](#method.own_addr).
+    pub async fn bind<A: Into<NodeAddr>>(ip_address: A, seed_nodes: Vec<A>) -> io::Result<Self> {
+        Self::bind_with_transport(
+            ip_address,
+            seed_nodes,
+            ConnectionProtocol::SingleSeed,
+            TcpTransport,
+        )
+        .await
+    }
+
+    pub async fn bind_with_transport<A: Into<NodeAddr>>(
+        ip_address: A,
+        seed_nodes: Vec<A>,
+        connection_protocol: ConnectionProtocol,
+        transport: impl Transport,
+    ) -> io::Result<Self> {
+        Self::bind_with_config(ClusterConfig::new(
+            ip_address.into(),
+            seed_nodes.into_iter().map(Into::into).collect(),
+            connection_protocol,
+            Arc::new(transport),
+        ))
+        .await
+    }
+
+    pub async fn bind_with_config(config: ClusterConfig) -> io::Result<Self> {
+        let (listener, own_addr) = config.transport().bind(config.own_addr().clone()).await?;
+        Ok(Self::start(
+            config.with_own_addr(own_addr),
+            AddrResolver::new().start(),
+            false,
+            Some(listener),
+        ))
+    }
+
+    /// Starts the system actors of a node. They know each other via the handle, therefore their
+    /// contexts are created before any of them is started. Without a `listener`, the address is bound first.
+    pub(crate) fn start(
+        config: ClusterConfig,
+        addr_resolver: Addr<AddrResolver>,
+        issue_system_broker: bool,
+        listener: Option<Listener>,
+    ) -> Self {
+        // like `bind`, a node advertises the address it got bound to, e.g. the port chosen for port 0
+        let (config, listener) = match listener {
+            Some(listener) => (config, Some(listener)),
+            None => {
+                let bind_addr = config.own_addr().clone();
+                match Cluster::bind_listener(config.transport().as_ref(), bind_addr.clone()) {
+                    Ok((listener, own_addr)) => (config.with_own_addr(own_addr), Some(listener)),
+                    Err(err) => {
+                        error!("Could not bind {}, stopping the node: {}", bind_addr, err);
+                        (config, None)
+                    }
+                }
+            }
+        };
+        let cluster_ctx = Context::new();
+        let connector_ctx = Context::new();
+        let deployer_ctx = Context::new();
+        let handle = Self {
+            config: Arc::new(config),
+            cluster: cluster_ctx.address(),
+            connector: connector_ctx.address(),
+            addr_resolver,
+            deployer: deployer_ctx.address(),
+        };
+
+        let mut connector = Connector::from_config(&handle.config);
+        connector.set_cluster(handle.cluster.clone());
+        connector_ctx.run(connector);
+        deployer_ctx.run(Deployer::new(
+            handle.own_addr(),
+            handle.addr_resolver.clone(),
+        ));
+        cluster_ctx.run(Cluster::from_handle(
+            handle.clone(),
+            issue_system_broker,
+            listener,
+        ));
+
+        handle.addr_resolver.do_send(AddrRequest::Register(
+            handle.connector.clone().recipient(),
+            Connector::ACTOR_ID.to_string(),
+        ));
+        handle.addr_resolver.do_send(AddrRequest::Register(
+            handle.deployer.clone().recipient(),
+            Deployer::ACTOR_ID.to_string(),
+        ));
+        handle
+    }
+
+    /// Makes this the node of the current actix system, reachable via the registries.
+    pub(crate) fn add_to_registry(&self) {
+        Cluster::add_to_registry(self.cluster.clone());
+        Connector::add_to_registry(self.connector.clone());
+        add_value_to_registry(self.clone());
+    }
+
+    /// Handle of the node started with [Cluster::new](./struct.Cluster.html#method.new) in the current actix system.
+    pub fn current() -> Self {
+        value_from_registry::<Self>().expect("Please start a Cluster before asking for its handle!")
+    }
+
+    /// Address the node is identified by in the cluster, see
+    /// [ClusterConfig::advertised_addr](./struct.ClusterConfig.html#method.advertised_addr).
+    pub fn own_addr(&self) -> NodeAddr {
+        self.config.advertised_addr().clone()
+    }
+
+    pub fn config(&self) -> &ClusterConfig {
+        &self.config
+    }
+
+    pub fn cluster(&self) -> Addr<Cluster> {
+        self.cluster.clone()
+    }
+
+    pub fn connector(&self) -> Addr<Connector> {
+        self.connector.clone()
+    }
+
+    pub fn addr_resolver(&self) -> Addr<AddrResolver> {
+        self.addr_resolver.clone()
+    }
+
+    pub fn deployer(&self) -> Addr<Deployer> {
+        self.deployer.clone()
+    }
+
+    /// `recipient` receives the [ClusterLog](./enum.ClusterLog.html) events of this node.
+    pub fn subscribe(&self, recipient: Recipient<ClusterLog>) {
+        self.cluster.do_send(SubscribeClusterLog(recipient));
+    }
+
+    /// See [register_factory](./fn.register_factory.html).
+    pub fn register_factory<A, Args, F>(&self, name: &str, factory: F)
+    where
+        A: RemoteActor + Actor<Context = Context<A>>,
+        Args: DeserializeOwned,
+        F: Fn(Args) -> A + Send + 'static,
+    {
+        self.deployer.do_send(RegisterFactory {
+            name: name.to_string(),
+            factory: factory_from(factory),
+        });
+    }
+
+    /// See [spawn_remote](./fn.spawn_remote.html).
+    pub fn spawn_remote(
+        &self,
+        node: &Node,
+        factory_name: &str,
+        args: Vec<u8>,
+    ) -> impl Future<Output = Result<RemoteAddr, SpawnError>> {
+        let request = self.deployer.send(SpawnRemote {
+            node: node.clone(),
+            factory_name: factory_name.to_string(),
+            args,
+        });
+        async move { request.await.unwrap_or(Err(SpawnError::Unreachable)) }
+    }
+}
+
+impl fmt::Debug for ClusterHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ClusterHandle({})", self.config.own_addr())
+    }
+}
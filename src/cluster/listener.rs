@@ -1,23 +1,27 @@
-use crate::Node;
+use crate::{Node, NodeAddr};
 use actix::prelude::*;
-use std::net::SocketAddr;
 
 /// Message sent to ClusterListeners if members join or leave the cluster
 #[derive(Message)]
 #[rtype(result = "()")]
 pub enum ClusterLog {
     NewMember(Node),
-    MemberLeft(SocketAddr),
+    MemberLeft(NodeAddr),
 }
 
 impl Clone for ClusterLog {
     fn clone(&self) -> Self {
         match self {
             ClusterLog::NewMember(node) => ClusterLog::NewMember(node.clone()),
-            ClusterLog::MemberLeft(addr) => ClusterLog::MemberLeft(*addr),
+            ClusterLog::MemberLeft(addr) => ClusterLog::MemberLeft(addr.clone()),
         }
     }
 }
 
+/// Subscribes a recipient to the ClusterLog messages of one [ClusterHandle](./struct.ClusterHandle.html)
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SubscribeClusterLog(pub Recipient<ClusterLog>);
+
 /// Trait for actors to receive ClusterLog messages
 pub trait ClusterListener: Actor + Handler<ClusterLog> {}
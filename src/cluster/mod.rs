@@ -1,30 +1,44 @@
+mod config;
 mod connector;
+mod deployer;
+mod handle;
 mod listener;
+mod seeds;
 #[cfg(test)]
 mod tests;
 
-pub use self::listener::{ClusterListener, ClusterLog};
+pub use self::config::{ClusterConfig, ClusterConfigBuilder, ConfigError};
+pub use self::handle::ClusterHandle;
+pub use self::listener::{ClusterListener, ClusterLog, SubscribeClusterLog};
+pub use self::seeds::{DnsSrvSeeds, FileSeeds, SeedProvider, StaticSeeds};
 pub use connector::NodeResolving;
 pub use connector::{gossip::Gossip, single_seed::SingleSeed};
+pub use deployer::{
+    register_factory, spawn_remote, Deployer, RegisterFactory, SpawnError, SpawnRemote,
+};
 
 pub use crate::cluster::connector::ConnectionProtocol;
 pub use crate::cluster::connector::Connector;
-use crate::network::NetworkInterface;
-use crate::remote::Node;
+pub(crate) use crate::cluster::connector::{SeedResolved, SeedsDiscovered};
+use crate::network::{DeliveryLedger, NetworkInterface, Queued, ReliableClusterMessage};
+use crate::remote::{AddrResolver, Node, NodeAddr};
+use crate::telemetry::Queue;
+use crate::transport::{Connection, Listener, TcpTransport, Transport};
 use crate::CustomSystemService;
 use actix::prelude::*;
 use actix_broker::BrokerIssue;
 use futures::executor::block_on;
 use futures::StreamExt;
 use log::*;
+use parking_lot::Mutex;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::io::Result as IoResult;
-use std::net;
-use std::net::SocketAddr;
-use std::str::FromStr;
-use tokio::net::{TcpListener, TcpStream};
-use tokio_stream::wrappers::TcpListenerStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often unacknowledged reliable messages are checked for expiry
+const DELIVERY_PRUNE_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(MessageResponse)]
 pub enum ConnectionApprovalResponse {
@@ -35,110 +49,269 @@ pub enum ConnectionApprovalResponse {
 #[derive(Message)]
 #[rtype(result = "ConnectionApprovalResponse")]
 pub struct ConnectionApproval {
-    pub addr: SocketAddr,
-    pub send_addr: SocketAddr,
+    pub addr: NodeAddr,
+    pub send_addr: NodeAddr,
 }
 
+/// A node connected to this node's listener; the address is the peer's source address.
 #[derive(Message)]
 #[rtype(result = "()")]
-pub struct TcpConnect(pub TcpStream, pub SocketAddr);
+pub struct IncomingConnection(pub Connection, pub NodeAddr);
 
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub enum NodeEvent {
     /// (Node, and whether it is a seed node)
     MemberUp(Node, bool),
-    MemberDown(SocketAddr),
+    MemberDown(NodeAddr),
 }
 
 impl Display for NodeEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             NodeEvent::MemberUp(node, seed) => {
-                write!(f, "MemberUp: {} (seed: {})", node.socket_addr, seed)
+                write!(f, "MemberUp: {} (seed: {})", node.addr, seed)
             }
             NodeEvent::MemberDown(addr) => write!(f, "MemberDown: {}", addr),
         }
     }
 }
 
+/// The connection a peer dialed ended. The peer is considered down if it does not reconnect in time.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct ConnectionLost {
+    pub node: Addr<NetworkInterface>,
+    pub addr: NodeAddr,
+    /// Whether the connection was approved, i.e. the peer was a member
+    pub connected: bool,
+}
+
 #[derive(Message)]
 #[rtype(result = "()")]
-pub struct ConnectToNode(pub(crate) SocketAddr);
+pub struct ConnectToNode(pub(crate) NodeAddr);
+
+/// Joins the cluster via a seed node the connector chose.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct ConnectToSeed(pub NodeAddr);
 
 /// Central Actor for cluster handling
 pub struct Cluster {
-    ip_address: SocketAddr,
-    addrs: Vec<SocketAddr>,
+    ip_address: NodeAddr,
     own_addr: Option<Addr<Cluster>>,
-    nodes: HashMap<SocketAddr, Addr<NetworkInterface>>,
+    nodes: HashMap<NodeAddr, Addr<NetworkInterface>>,
+    delivery: Arc<Mutex<DeliveryLedger>>,
+    handle: Option<ClusterHandle>,
+    subscribers: Vec<Recipient<ClusterLog>>,
+    issue_system_broker: bool,
+    /// Listener bound before the node started; without one, binding failed and the node stops
+    listener: Option<Listener>,
 }
 
 impl Actor for Cluster {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        let listener = Cluster::bind(self.ip_address.to_string()).unwrap();
-
-        let st = Box::leak(listener).map(|st| {
-            let st = st.unwrap();
-            let addr = st.peer_addr().unwrap();
-            TcpConnect(st, addr)
+        let listener = match self.listener.take() {
+            Some(listener) => listener,
+            None => {
+                ctx.stop();
+                return;
+            }
+        };
+
+        let st = listener.filter_map(|incoming| async move {
+            match incoming {
+                Ok((connection, addr)) => Some(IncomingConnection(connection, addr)),
+                Err(err) => {
+                    warn!("Could not accept connection: {}", err);
+                    None
+                }
+            }
         });
 
         ctx.add_message_stream(st);
 
         self.own_addr = Some(ctx.address());
 
-        let addrs_len = self.addrs.len();
-        for node_addr in 0..addrs_len {
-            self.add_node(*self.addrs.get(node_addr).unwrap(), true);
-        }
-        debug!("Cluster started {}", self.ip_address.clone().to_string());
+        self.discover_seeds(ctx);
+        ctx.run_interval(DELIVERY_PRUNE_INTERVAL, |act, _ctx| {
+            act.delivery.lock().prune()
+        });
+        let discovery_interval = self.cluster_handle().config().discovery_interval();
+        ctx.run_interval(discovery_interval, |act, ctx| {
+            if act.nodes.is_empty() {
+                act.discover_seeds(ctx);
+            }
+        });
+        debug!("Cluster started {}", self.ip_address);
     }
 }
 
 impl Cluster {
-    pub fn new(ip_address: SocketAddr, seed_nodes: Vec<SocketAddr>) -> Addr<Cluster> {
+    /// Starts the node of the current actix system. Addresses are either `SocketAddr`s or, for nodes on
+    /// the same host, paths of Unix domain sockets, see [NodeAddr](./enum.NodeAddr.html).
+    /// If the address cannot be bound, the error is logged and the node stops; [bind](#method.bind)
+    /// returns it instead. With port 0, the node advertises the port it got bound to.
+    pub fn new<A: Into<NodeAddr>>(ip_address: A, seed_nodes: Vec<A>) -> Addr<Cluster> {
         Self::new_with_connection_protocol(ip_address, seed_nodes, ConnectionProtocol::SingleSeed)
     }
 
-    pub fn new_with_connection_protocol(
-        ip_address: SocketAddr,
-        seed_nodes: Vec<SocketAddr>,
+    pub fn new_with_connection_protocol<A: Into<NodeAddr>>(
+        ip_address: A,
+        seed_nodes: Vec<A>,
+        connection_protocol: ConnectionProtocol,
+    ) -> Addr<Cluster> {
+        Self::new_with_transport(ip_address, seed_nodes, connection_protocol, TcpTransport)
+    }
+
+    /// Starts the node of the current actix system that connects to other nodes via `transport`,
+    /// e.g. a [QuicTransport](./struct.QuicTransport.html).
+    pub fn new_with_transport<A: Into<NodeAddr>>(
+        ip_address: A,
+        seed_nodes: Vec<A>,
         connection_protocol: ConnectionProtocol,
+        transport: impl Transport,
     ) -> Addr<Cluster> {
+        Self::from_config(ClusterConfig::new(
+            ip_address.into(),
+            seed_nodes.into_iter().map(Into::into).collect(),
+            connection_protocol,
+            Arc::new(transport),
+        ))
+    }
+
+    /// Starts the node of the current actix system with the settings of `config`,
+    /// see [ClusterConfig](./struct.ClusterConfig.html).
+    pub fn from_config(config: ClusterConfig) -> Addr<Cluster> {
         debug!("Cluster created");
-        Connector::start_service_from(connection_protocol, ip_address, seed_nodes.clone());
+        let handle = ClusterHandle::start(config, AddrResolver::from_registry(), true, None);
+        handle.add_to_registry();
+        handle.cluster()
+    }
 
-        Cluster::start_service_with(move || Cluster {
+    /// Starts the node of the current actix system like [new](#method.new), but binds its address first
+    /// and returns an error if that fails. With port 0, the node advertises the port it got bound to,
+    /// see [ClusterHandle::own_addr](./struct.ClusterHandle.html#method.own_addr).
+    pub async fn bind<A: Into<NodeAddr>>(
+        ip_address: A,
+        seed_nodes: Vec<A>,
+    ) -> IoResult<ClusterHandle> {
+        Self::bind_with_transport(
             ip_address,
-            addrs: seed_nodes.clone(),
-            own_addr: None,
-            nodes: Default::default(),
-        })
+            seed_nodes,
+            ConnectionProtocol::SingleSeed,
+            TcpTransport,
+        )
+        .await
+    }
+
+    pub async fn bind_with_transport<A: Into<NodeAddr>>(
+        ip_address: A,
+        seed_nodes: Vec<A>,
+        connection_protocol: ConnectionProtocol,
+        transport: impl Transport,
+    ) -> IoResult<ClusterHandle> {
+        Self::bind_with_config(ClusterConfig::new(
+            ip_address.into(),
+            seed_nodes.into_iter().map(Into::into).collect(),
+            connection_protocol,
+            Arc::new(transport),
+        ))
+        .await
+    }
+
+    pub async fn bind_with_config(config: ClusterConfig) -> IoResult<ClusterHandle> {
+        let (listener, own_addr) = config.transport().bind(config.own_addr().clone()).await?;
+        debug!("Listening on {}", own_addr);
+        let handle = ClusterHandle::start(
+            config.with_own_addr(own_addr),
+            AddrResolver::from_registry(),
+            true,
+            Some(listener),
+        );
+        handle.add_to_registry();
+        Ok(handle)
+    }
+
+    pub(crate) fn from_handle(
+        handle: ClusterHandle,
+        issue_system_broker: bool,
+        listener: Option<Listener>,
+    ) -> Self {
+        Self {
+            ip_address: handle.own_addr(),
+            handle: Some(handle),
+            issue_system_broker,
+            listener,
+            ..Default::default()
+        }
+    }
+
+    fn cluster_handle(&self) -> ClusterHandle {
+        self.handle.clone().unwrap_or_else(ClusterHandle::current)
     }
 
-    fn bind(addr: String) -> IoResult<Box<TcpListenerStream>> {
-        let addr = net::SocketAddr::from_str(&addr).unwrap();
-        let listener = Box::new(TcpListenerStream::new(
-            block_on(TcpListener::bind(&addr)).unwrap(),
-        ));
+    fn issue_cluster_log(&mut self, log: ClusterLog) {
+        self.subscribers.retain(|subscriber| subscriber.connected());
+        for subscriber in self.subscribers.iter() {
+            subscriber.do_send(log.clone());
+        }
+        if self.issue_system_broker {
+            self.issue_system_async(log);
+        }
+    }
+
+    /// Binds the address of a node started by a synchronous constructor, before its actors start.
+    fn bind_listener(transport: &dyn Transport, addr: NodeAddr) -> IoResult<(Listener, NodeAddr)> {
+        let (listener, addr) = block_on(transport.bind(addr))?;
         debug!("Listening on {}", addr);
-        Ok(listener)
+        Ok((listener, addr))
     }
 
-    fn add_node_from_stream(&mut self, addr: SocketAddr, stream: TcpStream) {
-        let own_ip = self.ip_address;
-        let node = NetworkInterface::from_stream(own_ip, addr, stream).start();
+    /// Tells the connector about the seed nodes of the [SeedProvider](./trait.SeedProvider.html), it chooses which to join.
+    fn discover_seeds(&mut self, ctx: &mut Context<Self>) {
+        let handle = self.cluster_handle();
+        let bind_addr = handle.config().own_addr().clone();
+        handle
+            .config()
+            .seed_provider()
+            .seed_nodes()
+            .into_actor(self)
+            .map(move |res, act, _ctx| match res {
+                Ok(seed_nodes) => {
+                    let seed_nodes: Vec<NodeAddr> = seed_nodes
+                        .into_iter()
+                        .filter(|addr| *addr != act.ip_address && *addr != bind_addr)
+                        .collect();
+                    debug!(target: &act.ip_address.to_string(), "Discovered seed nodes {:?}", seed_nodes);
+                    handle.connector().do_send(SeedsDiscovered(seed_nodes));
+                }
+                Err(err) => warn!("Could not discover seed nodes: {}", err),
+            })
+            .spawn(ctx);
+    }
+
+    fn add_node_from_stream(&mut self, addr: NodeAddr, connection: Connection) {
+        let own_ip = self.ip_address.clone();
+        let node = NetworkInterface::from_stream(own_ip, addr.clone(), connection)
+            .with_delivery_ledger(self.delivery.clone())
+            .with_cluster(self.cluster_handle())
+            .start();
         self.nodes.insert(addr, node);
     }
 
-    fn add_node(&mut self, node_addr: SocketAddr, seed: bool) {
-        let own_ip = self.ip_address;
-        self.nodes
-            .entry(node_addr)
-            .or_insert_with(|| NetworkInterface::new(own_ip, node_addr, seed).start());
+    fn add_node(&mut self, node_addr: NodeAddr, seed: bool) {
+        let own_ip = self.ip_address.clone();
+        let delivery = self.delivery.clone();
+        let handle = self.cluster_handle();
+        self.nodes.entry(node_addr.clone()).or_insert_with(|| {
+            NetworkInterface::new(own_ip, node_addr, seed)
+                .with_delivery_ledger(delivery)
+                .with_cluster(handle)
+                .start()
+        });
     }
 }
 
@@ -146,13 +319,15 @@ impl Cluster {
 
 impl Default for Cluster {
     fn default() -> Self {
-        let ip_addr = "127.0.0.1:8000";
-
         Self {
-            ip_address: SocketAddr::from_str(ip_addr).unwrap(),
-            addrs: vec![],
+            ip_address: ClusterConfig::default().own_addr().clone(),
             own_addr: None,
             nodes: HashMap::new(),
+            delivery: Arc::new(Mutex::new(DeliveryLedger::new())),
+            handle: None,
+            subscribers: vec![],
+            issue_system_broker: true,
+            listener: None,
         }
     }
 }
@@ -161,14 +336,14 @@ impl Supervised for Cluster {}
 impl SystemService for Cluster {}
 impl CustomSystemService for Cluster {}
 
-impl Handler<TcpConnect> for Cluster {
+impl Handler<IncomingConnection> for Cluster {
     type Result = ();
 
-    fn handle(&mut self, msg: TcpConnect, _ctx: &mut Self::Context) -> Self::Result {
-        debug!("Incoming TcpConnect");
-        let stream = msg.0;
+    fn handle(&mut self, msg: IncomingConnection, _ctx: &mut Self::Context) -> Self::Result {
+        debug!("Incoming connection");
+        let connection = msg.0;
         let addr = msg.1;
-        self.add_node_from_stream(addr, stream);
+        self.add_node_from_stream(addr, connection);
     }
 }
 
@@ -180,21 +355,55 @@ impl Handler<ConnectToNode> for Cluster {
     }
 }
 
+impl Handler<ConnectToSeed> for Cluster {
+    type Result = ();
+
+    fn handle(&mut self, msg: ConnectToSeed, _ctx: &mut Self::Context) -> Self::Result {
+        self.add_node(msg.0, true);
+    }
+}
+
 impl Handler<NodeEvent> for Cluster {
     type Result = ();
 
     fn handle(&mut self, msg: NodeEvent, _ctx: &mut Self::Context) -> Self::Result {
+        let handle = self.cluster_handle();
         match &msg {
             NodeEvent::MemberUp(node, _seed) => {
-                self.issue_system_async(ClusterLog::NewMember(node.clone()));
+                self.issue_cluster_log(ClusterLog::NewMember(node.clone()));
             }
             NodeEvent::MemberDown(host) => {
-                self.issue_system_async(ClusterLog::MemberLeft(*host));
+                self.issue_cluster_log(ClusterLog::MemberLeft(host.clone()));
                 self.nodes.remove(host);
+                handle
+                    .addr_resolver()
+                    .do_send(NodeEvent::MemberDown(host.clone()));
             }
         }
 
-        Connector::from_custom_registry().do_send(msg)
+        handle.connector().do_send(msg)
+    }
+}
+
+impl Handler<ConnectionLost> for Cluster {
+    type Result = ();
+
+    fn handle(&mut self, msg: ConnectionLost, ctx: &mut Self::Context) -> Self::Result {
+        self.nodes.retain(|_, node| *node != msg.node);
+        if !msg.connected {
+            return;
+        }
+
+        let handle = self.cluster_handle();
+        let config = handle.config();
+        let grace = (config.connect_timeout() + config.reconnect_delay())
+            * (u32::from(config.reconnect_attempts()) + 1);
+        let addr = msg.addr;
+        ctx.run_later(grace, move |act, ctx| {
+            if !act.nodes.contains_key(&addr) {
+                ctx.notify(NodeEvent::MemberDown(addr));
+            }
+        });
     }
 }
 
@@ -206,7 +415,8 @@ impl Handler<ConnectionApproval> for Cluster {
         msg: ConnectionApproval,
         _ctx: &mut Self::Context,
     ) -> ConnectionApprovalResponse {
-        if self.nodes.contains_key(&msg.addr) {
+        // a seed provider may list the node itself
+        if self.nodes.contains_key(&msg.addr) || msg.addr == self.ip_address {
             ConnectionApprovalResponse::Declined
         } else {
             let node = self
@@ -220,3 +430,27 @@ impl Handler<ConnectionApproval> for Cluster {
         }
     }
 }
+
+impl Handler<ReliableClusterMessage> for Cluster {
+    type Result = ();
+
+    /// Hands a reliable message to the current NetworkInterface of its destination. If there is none,
+    /// the message is retained until the node connects again.
+    fn handle(&mut self, msg: ReliableClusterMessage, _ctx: &mut Self::Context) -> Self::Result {
+        let peer = msg.0.destination.node.addr.clone();
+        match self.nodes.get(&peer) {
+            Some(node) => node.do_send(Queued::new(Queue::NetworkInterface, msg)),
+            None => {
+                self.delivery.lock().prepare(&peer, msg.0);
+            }
+        }
+    }
+}
+
+impl Handler<SubscribeClusterLog> for Cluster {
+    type Result = ();
+
+    fn handle(&mut self, msg: SubscribeClusterLog, _ctx: &mut Self::Context) -> Self::Result {
+        self.subscribers.push(msg.0);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::NodeAddr;
+use futures::future::{ready, LocalBoxFuture};
+use futures::FutureExt;
+use parking_lot::Mutex;
+use std::cmp::Reverse;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::system_conf::read_system_conf;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Tells a node which seed nodes to join. The [Cluster](./struct.Cluster.html) asks for them when it starts
+/// and again whenever it is lonely, e.g. because the seed nodes it knew are unreachable.
+pub trait SeedProvider: Send + Sync + 'static {
+    fn seed_nodes(&self) -> LocalBoxFuture<'static, io::Result<Vec<NodeAddr>>>;
+}
+
+/// Fixed list of seed nodes.
+#[derive(Debug, Clone, Default)]
+pub struct StaticSeeds(Vec<NodeAddr>);
+
+impl StaticSeeds {
+    pub fn new<A: Into<NodeAddr>>(seed_nodes: Vec<A>) -> Self {
+        Self(seed_nodes.into_iter().map(Into::into).collect())
+    }
+}
+
+impl SeedProvider for StaticSeeds {
+    fn seed_nodes(&self) -> LocalBoxFuture<'static, io::Result<Vec<NodeAddr>>> {
+        ready(Ok(self.0.clone())).boxed_local()
+    }
+}
+
+/// Modification time and length of a file
+type FileVersion = (SystemTime, u64);
+
+/// Seed nodes listed in a local file, one address per line; empty lines and lines starting with `#` are skipped.
+/// Like every [SeedProvider](./trait.SeedProvider.html), the file is only consulted when the node starts and while it
+/// has no connections, so changes do not affect a node that is part of a cluster. It is parsed again if its
+/// modification time or length changed.
+pub struct FileSeeds {
+    path: PathBuf,
+    cache: Mutex<Option<(FileVersion, Vec<NodeAddr>)>>,
+}
+
+impl FileSeeds {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            cache: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> io::Result<Vec<NodeAddr>> {
+        let metadata = std::fs::metadata(&self.path)?;
+        let version = (metadata.modified()?, metadata.len());
+        let mut cache = self.cache.lock();
+        if let Some((cached, seed_nodes)) = cache.as_ref() {
+            if *cached == version {
+                return Ok(seed_nodes.clone());
+            }
+        }
+
+        let seed_nodes = std::fs::read_to_string(&self.path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.parse().map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid seed node '{}': {}", line, err),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<NodeAddr>>>()?;
+        *cache = Some((version, seed_nodes.clone()));
+        Ok(seed_nodes)
+    }
+}
+
+impl SeedProvider for FileSeeds {
+    fn seed_nodes(&self) -> LocalBoxFuture<'static, io::Result<Vec<NodeAddr>>> {
+        ready(self.read()).boxed_local()
+    }
+}
+
+/// Seed nodes from the DNS SRV records of `name`, e.g. `_telepathy._tcp.cluster.local`, ordered by priority and weight.
+/// The targets are resolved when connecting to them.
+#[derive(Clone)]
+pub struct DnsSrvSeeds {
+    name: String,
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsSrvSeeds {
+    /// Looks the records up via the name servers of the system configuration.
+    pub fn new<N: Into<String>>(name: N) -> io::Result<Self> {
+        let (config, opts) = read_system_conf()?;
+        Ok(Self::with_resolver_config(name, config, opts))
+    }
+
+    /// Looks the records up via the name server listening on `addr`.
+    pub fn with_name_server<N: Into<String>>(name: N, addr: SocketAddr) -> Self {
+        let name_servers = NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true);
+        let config = ResolverConfig::from_parts(None, vec![], name_servers);
+        Self::with_resolver_config(name, config, ResolverOpts::default())
+    }
+
+    pub fn with_resolver_config<N: Into<String>>(
+        name: N,
+        config: ResolverConfig,
+        opts: ResolverOpts,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            resolver: TokioAsyncResolver::tokio(config, opts),
+        }
+    }
+}
+
+impl SeedProvider for DnsSrvSeeds {
+    fn seed_nodes(&self) -> LocalBoxFuture<'static, io::Result<Vec<NodeAddr>>> {
+        let resolver = self.resolver.clone();
+        let name = self.name.clone();
+        async move {
+            let lookup = resolver.srv_lookup(name).await.map_err(io::Error::other)?;
+            let mut records: Vec<_> = lookup.iter().collect();
+            records.sort_by_key(|srv| (srv.priority(), Reverse(srv.weight())));
+            Ok(records
+                .into_iter()
+                .map(|srv| {
+                    let target = srv.target().to_utf8();
+                    let target = target.trim_end_matches('.');
+                    format!("{}:{}", target, srv.port())
+                        .parse()
+                        .unwrap_or_else(|_| NodeAddr::host(target, srv.port()))
+                })
+                .collect())
+        }
+        .boxed_local()
+    }
+}
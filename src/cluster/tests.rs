@@ -1,293 +1,831 @@
-use crate::test_utils::cluster_listener::TestClusterListener;
+use crate::testkit::{free_local_addrs, ClusterEvents, TestCluster};
 use crate::{
-    Cluster, ClusterListener, ClusterLog, Connector, CustomSystemService, NetworkInterface,
-    NodeResolving,
+    AddrRequest, AddrResolver, Cluster, ClusterConfig, ClusterHandle, ClusterLog, ConfigError,
+    ConnectionProtocol, DnsSrvSeeds, FaultController, FaultyTransport, FileSeeds, MemoryNetwork,
+    NetworkInterface, Node, NodeAddr, NodeResolving, RemoteActor, RemoteMessage, SeedProvider,
+    StaticSeeds, TcpTransport,
 };
 use actix::prelude::*;
-use actix_broker::BrokerSubscribe;
-use actix_rt;
-use log::*;
 use port_scanner::{local_port_available, request_open_port};
-use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, timeout, Duration};
 
-type SocketTestClusterListener = TestClusterListener<Arc<Mutex<Vec<SocketAddr>>>>;
+// Cluster
 
-impl Actor for SocketTestClusterListener {
-    type Context = Context<Self>;
+#[actix_rt::test]
+async fn cluster_binds_port() {
+    let port = request_open_port().unwrap_or(8000);
+    let _listener = Cluster::bind_listener(
+        &TcpTransport,
+        format!("127.0.0.1:{}", port).parse().unwrap(),
+    )
+    .unwrap();
 
-    fn started(&mut self, ctx: &mut Context<Self>) {
-        self.subscribe_system_async::<ClusterLog>(ctx);
+    assert!(!local_port_available(port));
+}
+
+/// Node on the in-memory `network` with short timeouts, so that departures are noticed quickly.
+fn memory_node(
+    network: &MemoryNetwork,
+    controller: &FaultController,
+    port: u16,
+    seed_ports: &[u16],
+    protocol: ConnectionProtocol,
+) -> ClusterHandle {
+    let addr = |port: u16| SocketAddr::from(([127, 0, 0, 1], port));
+    let config = ClusterConfig::builder(addr(port))
+        .connection_protocol(protocol)
+        .seed_nodes(seed_ports.iter().map(|port| addr(*port)).collect())
+        .transport(FaultyTransport::new(network.clone(), controller.clone()))
+        .connect_timeout(Duration::from_millis(100))
+        .reconnect_delay(Duration::from_millis(100))
+        .discovery_interval(Duration::from_millis(200))
+        .build()
+        .unwrap();
+    ClusterHandle::from_config(config)
+}
+
+async fn resolve(cluster: &ClusterHandle, addrs: Vec<NodeAddr>) -> Vec<Addr<NetworkInterface>> {
+    cluster
+        .connector()
+        .send(NodeResolving { addrs })
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+const WAIT: Duration = Duration::from_secs(5);
+
+#[actix_rt::test]
+async fn cluster_adds_node_and_from_stream() {
+    let network = MemoryNetwork::new();
+    let controller = FaultController::new();
+    let seed = memory_node(
+        &network,
+        &controller,
+        23000,
+        &[],
+        ConnectionProtocol::SingleSeed,
+    );
+    let mut seed_events = ClusterEvents::of(&seed);
+    let node = memory_node(
+        &network,
+        &controller,
+        23001,
+        &[23000],
+        ConnectionProtocol::SingleSeed,
+    );
+    let mut node_events = ClusterEvents::of(&node);
+
+    // the seed learns about the node from the incoming stream
+    assert_eq!(
+        seed_events.expect_new_member(WAIT).await.addr,
+        node.own_addr()
+    );
+    assert_eq!(
+        node_events.expect_new_member(WAIT).await.addr,
+        seed.own_addr()
+    );
+}
+
+// Gossip
+
+#[actix_rt::test]
+async fn gossip_adds_member_and_resolves_it() {
+    let network = MemoryNetwork::new();
+    let controller = FaultController::new();
+    let seed = memory_node(
+        &network,
+        &controller,
+        23010,
+        &[],
+        ConnectionProtocol::Gossip,
+    );
+    let mut seed_events = ClusterEvents::of(&seed);
+    let node = memory_node(
+        &network,
+        &controller,
+        23011,
+        &[23010],
+        ConnectionProtocol::Gossip,
+    );
+    let mut node_events = ClusterEvents::of(&node);
+
+    seed_events.expect_new_member(WAIT).await;
+    node_events.expect_new_member(WAIT).await;
+    assert_eq!(resolve(&seed, vec![node.own_addr()]).await.len(), 1);
+    assert_eq!(resolve(&node, vec![seed.own_addr()]).await.len(), 1);
+}
+
+#[actix_rt::test]
+async fn gossip_removes_member() {
+    let network = MemoryNetwork::new();
+    let controller = FaultController::new();
+    let ports = [23020, 23021, 23022];
+    let nodes: Vec<ClusterHandle> = ports
+        .iter()
+        .map(|port| {
+            let seeds: &[u16] = if *port == ports[0] { &[] } else { &ports[..1] };
+            memory_node(
+                &network,
+                &controller,
+                *port,
+                seeds,
+                ConnectionProtocol::Gossip,
+            )
+        })
+        .collect();
+    let mut events: Vec<ClusterEvents> = nodes.iter().map(ClusterEvents::of).collect();
+    for node_events in events.iter_mut() {
+        node_events.expect_new_member(WAIT).await;
+        node_events.expect_new_member(WAIT).await;
     }
+
+    let leaving = nodes[2].own_addr();
+    let remaining = [nodes[0].own_addr(), nodes[1].own_addr()];
+    controller.partition(std::slice::from_ref(&leaving), &remaining);
+    for addr in remaining.iter() {
+        controller.disconnect(leaving.clone(), addr.clone());
+    }
+
+    assert_eq!(events[0].expect_member_left(WAIT).await, leaving);
+    assert_eq!(events[1].expect_member_left(WAIT).await, leaving);
+    assert_eq!(
+        resolve(&nodes[0], vec![remaining[1].clone()]).await.len(),
+        1
+    );
+    assert_eq!(
+        resolve(&nodes[1], vec![remaining[0].clone()]).await.len(),
+        1
+    );
 }
 
-impl Handler<ClusterLog> for SocketTestClusterListener {
-    type Result = ();
+#[actix_rt::test]
+async fn gossip_adds_member_and_introduces_other_members() {
+    let cluster = TestCluster::builder(3)
+        .connection_protocol(ConnectionProtocol::Gossip)
+        .in_memory(MemoryNetwork::new())
+        .start()
+        .await;
 
-    fn handle(&mut self, msg: ClusterLog, _ctx: &mut Context<Self>) -> Self::Result {
-        match msg {
-            ClusterLog::NewMember(node) => {
-                debug!("new member {}", node.socket_addr.to_string());
-                (*(self.content.as_ref().unwrap().lock().unwrap())).push(node.socket_addr);
+    // both nodes only know the seed, which introduces them to each other
+    assert_eq!(cluster.node(1, 2).addr, cluster.addr(2));
+    assert_eq!(cluster.node(2, 1).addr, cluster.addr(1));
+}
+
+#[actix_rt::test]
+async fn cluster_reconnects_to_left_node() {
+    let network = MemoryNetwork::new();
+    let controller = FaultController::new();
+    let seed = memory_node(
+        &network,
+        &controller,
+        23030,
+        &[],
+        ConnectionProtocol::SingleSeed,
+    );
+    let mut seed_events = ClusterEvents::of(&seed);
+    let node = memory_node(
+        &network,
+        &controller,
+        23031,
+        &[23030],
+        ConnectionProtocol::SingleSeed,
+    );
+    let mut node_events = ClusterEvents::of(&node);
+    seed_events.expect_new_member(WAIT).await;
+    node_events.expect_new_member(WAIT).await;
+
+    controller.partition(&[seed.own_addr()], &[node.own_addr()]);
+    controller.disconnect(seed.own_addr(), node.own_addr());
+    assert_eq!(seed_events.expect_member_left(WAIT).await, node.own_addr());
+    assert_eq!(node_events.expect_member_left(WAIT).await, seed.own_addr());
+
+    // the node discovers its seed again once it can be reached, every failed attempt until then
+    // gives the seed up again
+    controller.heal();
+    assert_eq!(
+        seed_events.expect_new_member(WAIT).await.addr,
+        node.own_addr()
+    );
+    let rejoined = timeout(WAIT, async {
+        loop {
+            match node_events.next(WAIT).await {
+                Some(ClusterLog::NewMember(member)) => break member,
+                Some(ClusterLog::MemberLeft(addr)) => assert_eq!(addr, seed.own_addr()),
+                None => panic!("Node did not rejoin its seed"),
             }
-            ClusterLog::MemberLeft(_addr) => {}
         }
-    }
+    })
+    .await
+    .unwrap();
+    assert_eq!(rejoined.addr, seed.own_addr());
 }
 
-struct OwnListenerAskingGossip {
-    pub asking: SocketAddr,
-    pub addrs: Arc<Mutex<Vec<Addr<NetworkInterface>>>>,
+// ClusterHandle
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct Greeting {
+    from: SocketAddr,
 }
-impl ClusterListener for OwnListenerAskingGossip {}
-impl Supervised for OwnListenerAskingGossip {}
 
-impl Actor for OwnListenerAskingGossip {
+#[derive(RemoteActor)]
+#[remote_messages(Greeting)]
+struct GreetingReceiver {
+    cluster: ClusterHandle,
+    received: Arc<Mutex<Vec<SocketAddr>>>,
+}
+
+impl Actor for GreetingReceiver {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Context<Self>) {
-        self.subscribe_system_async::<ClusterLog>(ctx);
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
     }
 }
 
-impl Handler<ClusterLog> for OwnListenerAskingGossip {
+impl Handler<Greeting> for GreetingReceiver {
     type Result = ();
 
-    fn handle(&mut self, msg: ClusterLog, ctx: &mut Context<Self>) -> Self::Result {
-        match msg {
-            ClusterLog::NewMember(_node) => {
-                Connector::from_custom_registry()
-                    .send(NodeResolving {
-                        addrs: vec![self.asking.clone()],
-                    })
-                    .into_actor(self)
-                    .map(
-                        |res: Result<Result<Vec<Addr<NetworkInterface>>, ()>, MailboxError>,
-                         act,
-                         _ctx| match res.unwrap() {
-                            Ok(addrs) => {
-                                (*(act.addrs.lock().unwrap())).push(addrs.get(0).unwrap().clone())
-                            }
-                            Err(_) => (),
-                        },
-                    )
-                    .wait(ctx);
-            }
-            ClusterLog::MemberLeft(addr) => {
-                Connector::from_custom_registry()
-                    .send(NodeResolving { addrs: vec![addr] })
-                    .into_actor(self)
-                    .map(
-                        |res: Result<Result<Vec<Addr<NetworkInterface>>, ()>, MailboxError>,
-                         _act,
-                         _ctx| match res.unwrap() {
-                            Ok(addrs) => assert_eq!(addrs.len(), 0),
-                            Err(_) => (),
-                        },
-                    )
-                    .wait(ctx);
-            }
-        }
+    fn handle(&mut self, msg: Greeting, _ctx: &mut Context<Self>) -> Self::Result {
+        self.received.lock().unwrap().push(msg.from);
     }
 }
 
-// Cluster
+struct MemberCollector(Arc<Mutex<Vec<Node>>>);
 
-#[actix_rt::test]
-async fn cluster_binds_port() {
-    let port = request_open_port().unwrap_or(8000);
-    let _listener = Cluster::bind(format!("127.0.0.1:{}", port));
+impl Actor for MemberCollector {
+    type Context = Context<Self>;
+}
 
-    assert_eq!(local_port_available(port), false);
+impl Handler<ClusterLog> for MemberCollector {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClusterLog, _ctx: &mut Context<Self>) -> Self::Result {
+        if let ClusterLog::NewMember(node) = msg {
+            self.0.lock().unwrap().push(node);
+        }
+    }
 }
 
 #[actix_rt::test]
-async fn cluster_adds_node_and_from_stream() {
-    let _cluster = Cluster::new("127.0.0.1:1992".parse().unwrap(), vec![]);
-    let connections = Arc::new(Mutex::new(vec![]));
-    let own_listener = SocketTestClusterListener::new_with_content(Arc::clone(&connections));
-    let _addr = own_listener.start();
-    let _network_interface = NetworkInterface::new(
-        "127.0.0.1:1993".parse().unwrap(),
-        "127.0.0.1:1992".parse().unwrap(),
-        true,
-    )
+async fn cluster_handles_run_independently_in_one_system() {
+    let addrs = free_local_addrs(2);
+    let gateway = ClusterHandle::new(addrs[0], vec![]);
+    let members = Arc::new(Mutex::new(vec![]));
+    gateway.subscribe(MemberCollector(members.clone()).start().recipient());
+
+    let other = ClusterHandle::new(addrs[1], vec![addrs[0]]);
+    let received = Arc::new(Mutex::new(vec![]));
+    let _receiver = GreetingReceiver {
+        cluster: other.clone(),
+        received: received.clone(),
+    }
     .start();
     sleep(Duration::from_secs(1)).await;
-    assert!(connections
-        .lock()
-        .unwrap()
-        .contains(&("127.0.0.1:1993".parse().unwrap())));
-    assert!(connections
-        .lock()
-        .unwrap()
-        .contains(&("127.0.0.1:1992".parse().unwrap())));
+
+    let node = members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(node.addr, addrs[1]);
+    node.get_remote_addr(GreetingReceiver::ACTOR_ID.to_string())
+        .do_send(Greeting { from: addrs[0] });
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(*received.lock().unwrap(), vec![addrs[0]]);
+
+    // neither handle touches the actors of the current actix system
+    let resolved = AddrResolver::from_registry()
+        .send(AddrRequest::ResolveStr(
+            GreetingReceiver::ACTOR_ID.to_string(),
+        ))
+        .await
+        .unwrap();
+    assert!(resolved.is_err());
 }
 
-// Gossip
+#[actix_rt::test]
+async fn bind_returns_error_if_address_is_in_use() {
+    let addr = free_local_addrs(1)[0];
+    let _occupied = std::net::TcpListener::bind(addr).unwrap();
+
+    let err = ClusterHandle::bind(addr, vec![]).await.err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+}
 
 #[actix_rt::test]
-async fn gossip_adds_member_and_resolves_it() {
-    let local_ip: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
-        .unwrap();
-    let other_ip: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
+async fn node_stops_if_address_is_in_use() {
+    let addr = free_local_addrs(1)[0];
+    let _occupied = std::net::TcpListener::bind(addr).unwrap();
+
+    let cluster = ClusterHandle::new(addr, vec![]);
+    sleep(Duration::from_millis(100)).await;
+    assert!(!cluster.cluster().connected());
+}
+
+#[actix_rt::test]
+async fn new_advertises_ephemeral_port() {
+    let any_port: SocketAddr = "127.0.0.1:0".parse().unwrap();
+    let _cluster = Cluster::new(any_port, vec![]);
+    let own_addr = ClusterHandle::current().own_addr().socket_addr().unwrap();
+    assert_ne!(own_addr.port(), 0);
+
+    let node = ClusterHandle::new(any_port, vec![own_addr]);
+    assert_ne!(node.own_addr().socket_addr().unwrap().port(), 0);
+    let member = ClusterEvents::of(&node)
+        .expect_new_member(Duration::from_secs(1))
+        .await;
+    assert_eq!(member.addr, NodeAddr::from(own_addr));
+}
+
+#[actix_rt::test]
+async fn bind_advertises_ephemeral_port() {
+    let any_port: SocketAddr = "127.0.0.1:0".parse().unwrap();
+    let seed = ClusterHandle::bind(any_port, vec![]).await.unwrap();
+    let seed_addr = seed.own_addr().socket_addr().unwrap();
+    assert_ne!(seed_addr.port(), 0);
+    let members = Arc::new(Mutex::new(vec![]));
+    seed.subscribe(MemberCollector(members.clone()).start().recipient());
+
+    let node = ClusterHandle::bind(any_port, vec![seed_addr])
+        .await
         .unwrap();
-    let _cluster = Cluster::new_with_connection_protocol(
-        local_ip.clone(),
-        vec![],
-        crate::ConnectionProtocol::Gossip,
+    sleep(Duration::from_secs(1)).await;
+
+    let member = members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, node.own_addr());
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn config_from_toml_overrides_defaults() {
+    let config = ClusterConfig::from_toml(
+        r#"
+        own_addr = "127.0.0.1:1992"
+        seed_nodes = ["127.0.0.1:1993", "127.0.0.1:1994"]
+        connection_protocol = "gossip"
+        connect_timeout_ms = 250
+        gossip_fanout = 5
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.own_addr(),
+        &"127.0.0.1:1992".parse::<NodeAddr>().unwrap()
+    );
+    assert_eq!(config.seed_nodes().len(), 2);
+    assert!(matches!(
+        config.connection_protocol(),
+        ConnectionProtocol::Gossip
+    ));
+    assert_eq!(config.connect_timeout(), Duration::from_millis(250));
+    assert_eq!(config.gossip_fanout(), 5);
+    assert_eq!(
+        config.reconnect_attempts(),
+        ClusterConfig::default().reconnect_attempts()
     );
-    let addrs = Arc::new(Mutex::new(vec![]));
-    let _own_listener = OwnListenerAskingGossip {
-        asking: other_ip.clone(),
-        addrs: Arc::clone(&addrs),
+}
+
+#[test]
+fn config_from_env_vars() {
+    let vars = [
+        ("TELEPATHY_OWN_ADDR", "127.0.0.1:1992"),
+        ("TELEPATHY_SEED_NODES", "127.0.0.1:1993, 127.0.0.1:1994"),
+        ("TELEPATHY_CONNECTION_PROTOCOL", "gossip"),
+        ("TELEPATHY_RECONNECT_ATTEMPTS", "5"),
+        ("TELEPATHY_RECONNECT_DELAY_MS", "100"),
+        ("PATH", "/usr/bin"),
+    ]
+    .iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()));
+    let config = ClusterConfig::from_vars(vars).unwrap();
+
+    assert_eq!(config.seed_nodes().len(), 2);
+    assert_eq!(config.reconnect_attempts(), 5);
+    assert_eq!(config.reconnect_delay(), Duration::from_millis(100));
+
+    let invalid = [(
+        "TELEPATHY_OWN_ADDR".to_string(),
+        "not an address".to_string(),
+    )];
+    assert!(matches!(
+        ClusterConfig::from_vars(invalid),
+        Err(ConfigError::Invalid("own_addr", _))
+    ));
+
+    let unknown = [
+        (
+            "TELEPATHY_OWN_ADDR".to_string(),
+            "127.0.0.1:1992".to_string(),
+        ),
+        ("TELEPATHY_FANOUT".to_string(), "3".to_string()),
+    ];
+    assert!(matches!(
+        ClusterConfig::from_vars(unknown),
+        Err(ConfigError::Parse(_))
+    ));
+}
+
+#[test]
+fn config_rejects_invalid_settings() {
+    let own_addr: SocketAddr = "127.0.0.1:1992".parse().unwrap();
+    let seeds: Vec<SocketAddr> = vec!["127.0.0.1:1993".parse().unwrap(), own_addr];
+
+    let zero_timeout = ClusterConfig::builder(own_addr).connect_timeout(Duration::ZERO);
+    assert!(matches!(
+        zero_timeout.build(),
+        Err(ConfigError::Invalid("connect_timeout", _))
+    ));
+    let unspecified: SocketAddr = "0.0.0.0:1992".parse().unwrap();
+    let unreachable = ClusterConfig::builder(unspecified).advertised_addr(unspecified);
+    assert!(matches!(
+        unreachable.build(),
+        Err(ConfigError::Invalid("advertised_addr", _))
+    ));
+    let zero_fanout = ClusterConfig::builder(own_addr).gossip_fanout(0);
+    assert!(matches!(
+        zero_fanout.build(),
+        Err(ConfigError::Invalid("gossip_fanout", _))
+    ));
+    let own_seed = ClusterConfig::builder(own_addr)
+        .connection_protocol(ConnectionProtocol::Gossip)
+        .seed_nodes(seeds.clone());
+    assert!(matches!(
+        own_seed.build(),
+        Err(ConfigError::Invalid("seed_nodes", _))
+    ));
+    let many_seeds = ClusterConfig::builder(own_addr).seed_nodes(vec![seeds[0], seeds[0]]);
+    assert!(matches!(
+        many_seeds.build(),
+        Err(ConfigError::Invalid("seed_nodes", _))
+    ));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn config_from_toml_rejects_invalid_files() {
+    let missing = ClusterConfig::from_toml_file("/nonexistent/telepathy.toml").unwrap_err();
+    let source = std::error::Error::source(&missing)
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .unwrap();
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    assert!(matches!(
+        ClusterConfig::from_toml("seed_nodes = []"),
+        Err(ConfigError::Missing("own_addr"))
+    ));
+    assert!(matches!(
+        ClusterConfig::from_toml("own_addr = \"127.0.0.1:1992\"\nfanout = 3"),
+        Err(ConfigError::Parse(_))
+    ));
+}
+
+struct LeftCollector(Arc<Mutex<Vec<NodeAddr>>>);
+
+impl Actor for LeftCollector {
+    type Context = Context<Self>;
+}
+
+impl Handler<ClusterLog> for LeftCollector {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClusterLog, _ctx: &mut Context<Self>) -> Self::Result {
+        if let ClusterLog::MemberLeft(addr) = msg {
+            self.0.lock().unwrap().push(addr);
+        }
     }
-    .start();
-    let _network_interface = NetworkInterface::new(other_ip, local_ip, true).start();
+}
+
+#[actix_rt::test]
+async fn unreachable_seed_is_given_up_after_configured_attempts() {
+    let addrs = free_local_addrs(2);
+    let config = ClusterConfig::builder(addrs[0])
+        .seed_nodes(vec![addrs[1]])
+        .connect_timeout(Duration::from_millis(100))
+        .reconnect_attempts(1)
+        .reconnect_delay(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let node = ClusterHandle::from_config(config);
+    let left = Arc::new(Mutex::new(vec![]));
+    node.subscribe(LeftCollector(left.clone()).start().recipient());
+
+    // the default of 2 attempts with 1s delay would take longer
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(*left.lock().unwrap(), vec![NodeAddr::from(addrs[1])]);
+}
+
+#[actix_rt::test]
+async fn seed_given_by_host_name_is_identified_by_its_address() {
+    let addrs = free_local_addrs(2);
+    let seed =
+        ClusterHandle::new_with_connection_protocol(addrs[0], vec![], ConnectionProtocol::Gossip);
+    let seed_members = Arc::new(Mutex::new(vec![]));
+    seed.subscribe(MemberCollector(seed_members.clone()).start().recipient());
+
+    let node = ClusterHandle::new_with_connection_protocol(
+        NodeAddr::from(addrs[1]),
+        vec![NodeAddr::host("localhost", addrs[0].port())],
+        ConnectionProtocol::Gossip,
+    );
+    let node_members = Arc::new(Mutex::new(vec![]));
+    node.subscribe(MemberCollector(node_members.clone()).start().recipient());
     sleep(Duration::from_secs(1)).await;
-    assert_eq!(addrs.lock().unwrap().len(), 2);
+
+    let member = node_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(addrs[0]));
+    let member = seed_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(addrs[1]));
 }
 
 #[actix_rt::test]
-async fn gossip_removes_member() {
-    let local_ip: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
+async fn nodes_are_identified_by_advertised_addr() {
+    let ports: Vec<u16> = free_local_addrs(2).iter().map(|addr| addr.port()).collect();
+    let any = |port: u16| SocketAddr::from(([0, 0, 0, 0], port));
+    let loopback = |last: u8, port: u16| SocketAddr::from(([127, 0, 0, last], port));
+
+    let seed_config = ClusterConfig::builder(any(ports[0]))
+        .advertised_addr(loopback(2, ports[0]))
+        .build()
         .unwrap();
-    let other_ip: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
+    let seed = ClusterHandle::bind_with_config(seed_config).await.unwrap();
+    assert_eq!(seed.own_addr(), NodeAddr::from(loopback(2, ports[0])));
+    let seed_members = Arc::new(Mutex::new(vec![]));
+    seed.subscribe(MemberCollector(seed_members.clone()).start().recipient());
+
+    // dials the seed via another address than it advertises
+    let node_config = ClusterConfig::builder(any(ports[1]))
+        .advertised_addr(loopback(3, ports[1]))
+        .seed_nodes(vec![loopback(1, ports[0])])
+        .build()
         .unwrap();
-    let _cluster = Cluster::new_with_connection_protocol(
-        local_ip.clone(),
-        vec![],
-        crate::ConnectionProtocol::Gossip,
-    );
-    let addrs = Arc::new(Mutex::new(vec![]));
-    let _own_listener = OwnListenerAskingGossip {
-        asking: other_ip.clone(),
-        addrs: Arc::clone(&addrs),
-    }
-    .start();
-    let _network_interface = NetworkInterface::new(other_ip, local_ip, true).start();
+    let node = ClusterHandle::bind_with_config(node_config).await.unwrap();
+    let node_members = Arc::new(Mutex::new(vec![]));
+    node.subscribe(MemberCollector(node_members.clone()).start().recipient());
     sleep(Duration::from_secs(1)).await;
-    assert_eq!(addrs.lock().unwrap().len(), 2);
+
+    let member = node_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(loopback(2, ports[0])));
+    let member = seed_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(loopback(3, ports[1])));
 }
 
-struct TestParams {
-    ip: SocketAddr,
-    seeds: Vec<SocketAddr>,
-    start: u64,
-    delay: u64,
-    end: u64,
-    expect: usize,
+fn seed_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("telepathy-{}-{}", name, std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
 }
 
-#[test]
-#[ignore] //github workflows don't get the timing right
-fn gossip_adds_member_and_introduces_other_members() {
-    let ip1: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
-        .unwrap();
-    let ip2: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
+#[actix_rt::test]
+async fn file_seeds_are_read_again_once_changed() {
+    let path = seed_file("changed", "# seeds\n127.0.0.1:1993\n\n");
+    let seeds = FileSeeds::new(&path);
+    assert_eq!(
+        seeds.seed_nodes().await.unwrap(),
+        vec!["127.0.0.1:1993".parse::<NodeAddr>().unwrap()]
+    );
+
+    std::fs::write(&path, "127.0.0.1:1993\nlocalhost:1994\n").unwrap();
+    assert_eq!(
+        seeds.seed_nodes().await.unwrap(),
+        vec![
+            "127.0.0.1:1993".parse::<NodeAddr>().unwrap(),
+            NodeAddr::host("localhost", 1994)
+        ]
+    );
+
+    std::fs::write(&path, "not an address\n").unwrap();
+    let err = seeds.seed_nodes().await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Answers every query with the given SRV records of (priority, weight, port, target).
+async fn stub_dns_server(records: Vec<(u16, u16, u16, &'static str)>) -> SocketAddr {
+    use trust_dns_proto::op::{Message, MessageType};
+    use trust_dns_proto::rr::rdata::SRV;
+    use trust_dns_proto::rr::{Name, RData, Record};
+
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    actix_rt::spawn(async move {
+        let mut buf = [0; 512];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let request = Message::from_vec(&buf[..len]).unwrap();
+            let mut response = Message::new();
+            response
+                .set_id(request.id())
+                .set_message_type(MessageType::Response)
+                .set_op_code(request.op_code())
+                .set_recursion_desired(request.recursion_desired())
+                .set_recursion_available(true)
+                .set_authoritative(true)
+                .add_queries(request.queries().to_vec());
+            let name = request.queries()[0].name().clone();
+            for (priority, weight, port, target) in records.iter() {
+                let target = Name::from_ascii(target).unwrap();
+                let srv = SRV::new(*priority, *weight, *port, target);
+                response.add_answer(Record::from_rdata(name.clone(), 60, RData::SRV(srv)));
+            }
+            socket
+                .send_to(&response.to_vec().unwrap(), from)
+                .await
+                .unwrap();
+        }
+    });
+    addr
+}
+
+#[actix_rt::test]
+async fn dns_srv_seeds_are_ordered_by_priority_and_weight() {
+    let name_server = stub_dns_server(vec![
+        (20, 0, 1995, "node-c.cluster.local."),
+        (10, 5, 1994, "node-b.cluster.local."),
+        (10, 50, 1993, "127.0.0.1."),
+    ])
+    .await;
+    let seeds = DnsSrvSeeds::with_name_server("_telepathy._tcp.cluster.local.", name_server);
+
+    assert_eq!(
+        seeds.seed_nodes().await.unwrap(),
+        vec![
+            "127.0.0.1:1993".parse::<NodeAddr>().unwrap(),
+            NodeAddr::host("node-b.cluster.local", 1994),
+            NodeAddr::host("node-c.cluster.local", 1995),
+        ]
+    );
+}
+
+#[actix_rt::test]
+async fn lonely_node_discovers_seeds_again() {
+    let addrs = free_local_addrs(3);
+    let seed =
+        ClusterHandle::new_with_connection_protocol(addrs[0], vec![], ConnectionProtocol::Gossip);
+    let seed_members = Arc::new(Mutex::new(vec![]));
+    seed.subscribe(MemberCollector(seed_members.clone()).start().recipient());
+
+    // the first listed seed is unreachable
+    let path = seed_file("lonely", &format!("{}\n", addrs[2]));
+    let config = ClusterConfig::builder(addrs[1])
+        .connection_protocol(ConnectionProtocol::Gossip)
+        .seed_provider(FileSeeds::new(&path))
+        .discovery_interval(Duration::from_millis(200))
+        .connect_timeout(Duration::from_millis(100))
+        .reconnect_attempts(0)
+        .build()
         .unwrap();
-    let ip3: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
+    let node = ClusterHandle::from_config(config);
+    let node_members = Arc::new(Mutex::new(vec![]));
+    node.subscribe(MemberCollector(node_members.clone()).start().recipient());
+    sleep(Duration::from_millis(300)).await;
+    assert!(node_members.lock().unwrap().is_empty());
+
+    std::fs::write(&path, format!("# moved\n{}\n", addrs[0])).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    let member = node_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(addrs[0]));
+    let member = seed_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(addrs[1]));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[actix_rt::test]
+async fn single_seed_joins_only_the_first_discovered_seed() {
+    let network = MemoryNetwork::new();
+    let addrs: Vec<SocketAddr> = (0..3)
+        .map(|i| SocketAddr::from(([127, 0, 0, 1], 21000 + i)))
+        .collect();
+    let _first_seed = ClusterHandle::new_with_transport(
+        addrs[0],
+        vec![],
+        ConnectionProtocol::SingleSeed,
+        network.clone(),
+    );
+    let second_seed = ClusterHandle::new_with_transport(
+        addrs[1],
+        vec![],
+        ConnectionProtocol::SingleSeed,
+        network.clone(),
+    );
+    let mut second_seed_events = ClusterEvents::of(&second_seed);
+
+    let config = ClusterConfig::builder(addrs[2])
+        .connection_protocol(ConnectionProtocol::SingleSeed)
+        .seed_provider(StaticSeeds::new(vec![addrs[0], addrs[1]]))
+        .transport(network)
+        .build()
         .unwrap();
+    let node = ClusterHandle::from_config(config);
+    let mut events = ClusterEvents::of(&node);
 
-    let arr = [
-        TestParams {
-            ip: ip1.clone(),
-            seeds: vec![],
-            start: 0,
-            delay: 0,
-            end: 2000,
-            expect: 0,
-        },
-        TestParams {
-            ip: ip2.clone(),
-            seeds: vec![ip1.clone()],
-            start: 1000,
-            delay: 1000,
-            end: 0,
-            expect: 2,
-        },
-        TestParams {
-            ip: ip3.clone(),
-            seeds: vec![ip1.clone()],
-            start: 1000,
-            delay: 1000,
-            end: 0,
-            expect: 2,
-        },
-    ];
-    arr.par_iter()
-        .for_each(|p| build_cluster(p.ip, p.seeds.clone(), p.start, p.delay, p.end, p.expect));
-}
-
-#[actix_rt::main]
-async fn build_cluster(
-    own_ip: SocketAddr,
-    other_ip: Vec<SocketAddr>,
-    start: u64,
-    delay: u64,
-    end: u64,
-    expect: usize,
-) {
-    sleep(Duration::from_millis(start)).await;
-    let _cluster = Cluster::new(own_ip, other_ip);
-    let addrs = Arc::new(Mutex::new(vec![]));
-    let _cluster_listener = SocketTestClusterListener::new_with_content(Arc::clone(&addrs)).start();
-    sleep(Duration::from_millis(delay)).await;
-    assert_eq!((*(addrs.lock().unwrap())).len(), expect);
-    sleep(Duration::from_millis(end)).await;
+    let member = events.expect_new_member(Duration::from_secs(1)).await;
+    assert_eq!(member.addr, NodeAddr::from(addrs[0]));
+    assert!(events.next(Duration::from_millis(200)).await.is_none());
+    assert!(second_seed_events
+        .next(Duration::from_millis(10))
+        .await
+        .is_none());
 }
 
+#[cfg(feature = "toml")]
 #[test]
-#[ignore] //github workflows don't get the timing right
-fn cluster_reconnects_to_left_node() {
-    env_logger::init();
-    let ip1: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
-        .unwrap();
-    let ip2: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
-        .parse()
-        .unwrap();
+fn config_reads_seed_provider() {
+    let config = ClusterConfig::from_toml(
+        r#"
+        own_addr = "127.0.0.1:1992"
+        seed_file = "/etc/telepathy/seeds"
+        discovery_interval_ms = 100
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.discovery_interval(), Duration::from_millis(100));
 
-    let variables = vec![
-        (ip1, vec![], false),
-        (ip2, vec![ip1], false),
-        (ip2, vec![ip1], true),
-    ];
-    let results: Vec<bool> = variables
-        .par_iter()
-        .map(|(own_ip, seed_node, delay)| {
-            build_cluster_and_maybe_leave(own_ip.clone(), seed_node.clone(), *delay)
-        })
-        .collect();
+    assert!(matches!(
+        ClusterConfig::from_toml(
+            "own_addr = \"127.0.0.1:1992\"\nseed_file = \"seeds\"\nseed_srv = \"_telepathy._tcp\""
+        ),
+        Err(ConfigError::Invalid("seed_srv", _))
+    ));
+    assert!(matches!(
+        ClusterConfig::from_toml(
+            "own_addr = \"127.0.0.1:1992\"\nseed_nodes = [\"127.0.0.1:1993\"]\nseed_file = \"seeds\""
+        ),
+        Err(ConfigError::Invalid("seed_nodes", _))
+    ));
+}
 
-    assert_eq!(results, vec![true, true, true]);
+fn free_ipv6_loopback_addrs(n: usize) -> Vec<SocketAddr> {
+    let listeners: Vec<std::net::TcpListener> = (0..n)
+        .map(|_| std::net::TcpListener::bind("[::1]:0").expect("No free IPv6 port"))
+        .collect();
+    listeners
+        .iter()
+        .map(|listener| listener.local_addr().unwrap())
+        .collect()
 }
 
-#[actix_rt::main]
-async fn build_cluster_and_maybe_leave(
-    own_ip: SocketAddr,
-    seed_nodes: Vec<SocketAddr>,
-    delay: bool,
-) -> bool {
-    if delay {
-        sleep(Duration::from_secs(2)).await;
-    }
-    let _cluster = Cluster::new(own_ip, seed_nodes.clone());
-    sleep(Duration::from_secs(1)).await;
-    if seed_nodes.len() > 0 && !delay {
-        System::current().stop();
-        return true;
+#[actix_rt::test]
+async fn cluster_forms_over_ipv6_loopback() {
+    let addrs = free_ipv6_loopback_addrs(3);
+    let members: Vec<Arc<Mutex<Vec<Node>>>> =
+        (0..3).map(|_| Arc::new(Mutex::new(vec![]))).collect();
+    let mut nodes = vec![];
+    for (i, addr) in addrs.iter().enumerate() {
+        let seed_nodes = if i == 0 { vec![] } else { vec![addrs[0]] };
+        let config = ClusterConfig::builder(*addr)
+            .connection_protocol(ConnectionProtocol::Gossip)
+            .seed_nodes(seed_nodes)
+            .build()
+            .unwrap();
+        let node = ClusterHandle::bind_with_config(config).await.unwrap();
+        node.subscribe(MemberCollector(members[i].clone()).start().recipient());
+        nodes.push(node);
     }
     sleep(Duration::from_secs(2)).await;
 
-    true
+    for (i, members) in members.iter().enumerate() {
+        let mut member_addrs: Vec<NodeAddr> = members
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|node| node.addr.clone())
+            .collect();
+        member_addrs.sort();
+        let mut expected: Vec<NodeAddr> = addrs
+            .iter()
+            .filter(|addr| **addr != addrs[i])
+            .map(|addr| NodeAddr::from(*addr))
+            .collect();
+        expected.sort();
+        assert_eq!(member_addrs, expected);
+    }
+}
+
+#[actix_rt::test]
+async fn dual_stack_listener_accepts_ipv4_peers() {
+    let ports: Vec<u16> = free_local_addrs(2).iter().map(|addr| addr.port()).collect();
+    let seed_addr = SocketAddr::from(([127, 0, 0, 1], ports[0]));
+    let seed_config = ClusterConfig::builder(SocketAddr::from((
+        std::net::Ipv6Addr::UNSPECIFIED,
+        ports[0],
+    )))
+    .advertised_addr(seed_addr)
+    .build()
+    .unwrap();
+    let seed = ClusterHandle::bind_with_config(seed_config).await.unwrap();
+    let seed_members = Arc::new(Mutex::new(vec![]));
+    seed.subscribe(MemberCollector(seed_members.clone()).start().recipient());
+
+    let node_addr = SocketAddr::from(([127, 0, 0, 1], ports[1]));
+    let node = ClusterHandle::new(node_addr, vec![seed_addr]);
+    let node_members = Arc::new(Mutex::new(vec![]));
+    node.subscribe(MemberCollector(node_members.clone()).start().recipient());
+    sleep(Duration::from_secs(1)).await;
+
+    let member = node_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(seed_addr));
+    let member = seed_members.lock().unwrap().first().cloned().unwrap();
+    assert_eq!(member.addr, NodeAddr::from(node_addr));
 }
@@ -1,4 +1,5 @@
-use crate::remote::RemoteWrapper;
+use crate::network::Delivery;
+use crate::remote::{NodeAddr, RemoteWrapper};
 use actix::prelude::*;
 use byteorder::{ByteOrder, NetworkEndian};
 use bytes::{Buf, BufMut, BytesMut};
@@ -12,12 +13,16 @@ const ENDIAN_LENGTH: usize = 4;
 
 #[derive(Message, Deserialize, Serialize, Debug)]
 #[rtype(result = "()")]
+#[allow(clippy::large_enum_variant)]
 pub enum ClusterMessage {
-    /// bool = is_seed?
-    Request(u16, bool),
-    Response,
+    /// (listening address of the requesting node, is_seed?)
+    Request(NodeAddr, bool),
+    /// (listening address of the accepting node)
+    Response(NodeAddr),
     Message(RemoteWrapper),
     Decline,
+    /// Acknowledges a message sent with at-least-once delivery
+    Ack(Delivery),
 }
 
 impl ClusterMessage {
@@ -47,6 +52,11 @@ impl ConnectCodec {
     pub fn new() -> ConnectCodec {
         ConnectCodec { prefix: false }
     }
+
+    /// Codec for a stream of messages next to the connection that carried the handshake.
+    pub fn without_handshake() -> ConnectCodec {
+        ConnectCodec { prefix: true }
+    }
 }
 
 impl Decoder for ConnectCodec {
@@ -62,7 +72,7 @@ impl Decoder for ConnectCodec {
                 let _s = src.split_to(11);
                 self.prefix = true;
             } else {
-                return Err(io::Error::new(io::ErrorKind::Other, "Prefix mismatch"));
+                return Err(io::Error::other("Prefix mismatch"));
             }
         }
 
@@ -103,7 +113,7 @@ impl Encoder<ClusterMessage> for ConnectCodec {
     fn encode(&mut self, item: ClusterMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match &item {
             ClusterMessage::Request(_, _) => dst.extend_from_slice(PREFIX),
-            ClusterMessage::Response => dst.extend_from_slice(PREFIX),
+            ClusterMessage::Response(_) => dst.extend_from_slice(PREFIX),
             ClusterMessage::Message(_) => {
                 let (buffer, header) = item.split();
                 let buffer_ref: &[u8] = buffer.as_ref();
@@ -182,20 +182,50 @@
 #[cfg(feature = "derive")]
 pub use actix_telepathy_derive::*;
 
+// lets the paths generated by the derive macros resolve within this crate
+extern crate self as actix_telepathy;
+
+/// Used by the code the derive macros generate, so that it does not depend on the imports of the user's module.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::remote::message::identifiers_equal;
+    pub use crate::remote::type_name::IdentifierBuf;
+    pub use log::warn;
+    pub use serde::{de::DeserializeOwned, Serialize};
+    pub use tokio::sync::oneshot;
+
+    #[deprecated(
+        note = "the `custom_serializer` of telepathy.yaml is deprecated, use the `serializer` attribute or a `default-serializer-*` feature"
+    )]
+    pub const TELEPATHY_YAML: () = ();
+}
+
 mod cluster;
 mod codec;
 mod network;
+#[cfg(feature = "raft")]
+mod raft;
 mod remote;
 mod serialization;
-#[cfg(test)]
-pub(crate) mod test_utils;
+mod telemetry;
+#[cfg(any(test, feature = "testkit"))]
+#[cfg_attr(not(feature = "testkit"), allow(dead_code, unused_imports))]
+mod testkit;
+mod transport;
 mod utils;
 
 pub use crate::cluster::*;
 pub use crate::codec::ClusterMessage;
 pub use crate::network::*;
+#[cfg(feature = "raft")]
+pub use crate::raft::*;
 pub use crate::remote::*;
 pub use crate::serialization::*;
+#[cfg(feature = "metrics")]
+pub use crate::telemetry::*;
+#[cfg(feature = "testkit")]
+pub use crate::testkit::*;
+pub use crate::transport::*;
 pub use crate::utils::*;
 
 pub mod prelude {
@@ -204,8 +234,11 @@ pub mod prelude {
 
     pub use crate::cluster::{Cluster, ClusterListener, ClusterLog, NodeResolving};
     pub use crate::network::NetworkInterface;
-    pub use crate::remote::{AnyAddr, RemoteActor, RemoteAddr, RemoteMessage, RemoteWrapper};
+    pub use crate::remote::{
+        AnyAddr, NodeAddr, RemoteActor, RemoteAddr, RemoteMessage, RemoteReply, RemoteWrapper,
+        TraceContext,
+    };
     pub use crate::serialization::{
-        CustomSerialization, CustomSerializationError, DefaultSerialization,
+        CustomSerialization, CustomSerializationError, DefaultSerialization, MessageSerialization,
     };
 }
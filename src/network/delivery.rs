@@ -0,0 +1,194 @@
+use crate::remote::{NodeAddr, RemoteWrapper};
+use crate::telemetry::record_dropped;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Unacknowledged messages retained per peer; beyond, the oldest ones are given up
+const MAX_UNACKED: usize = 10_000;
+/// How long an unacknowledged message is retained, e.g. for a peer that does not come back
+const UNACKED_TTL: Duration = Duration::from_secs(300);
+
+/// Sequence number of a reliably sent message. `session` identifies the sending cluster instance,
+/// so that a restarted node does not get its messages discarded as duplicates.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Delivery {
+    pub session: Uuid,
+    pub sequence: u64,
+    /// The lowest sequence number the sender still retains; earlier messages were acked or given up
+    pub retained_from: u64,
+}
+
+/// Sequence numbers already received from one sender session.
+struct ReceiveWindow {
+    /// All sequence numbers up to this one have been received or given up by the sender
+    contiguous: u64,
+    received: BTreeSet<u64>,
+    last_received: Instant,
+}
+
+impl ReceiveWindow {
+    fn new() -> Self {
+        Self {
+            contiguous: 0,
+            received: BTreeSet::new(),
+            last_received: Instant::now(),
+        }
+    }
+
+    /// Returns `false` if `delivery` has been received before.
+    fn receive(&mut self, delivery: Delivery) -> bool {
+        self.last_received = Instant::now();
+        // the gaps before `retained_from` are never filled, the sender does not send them anymore
+        self.advance(delivery.retained_from.saturating_sub(1));
+        if delivery.sequence <= self.contiguous || !self.received.insert(delivery.sequence) {
+            return false;
+        }
+        self.advance(self.contiguous);
+        true
+    }
+
+    fn advance(&mut self, received_up_to: u64) {
+        if received_up_to > self.contiguous {
+            self.contiguous = received_up_to;
+            self.received = self.received.split_off(&(received_up_to + 1));
+        }
+        while self.received.remove(&(self.contiguous + 1)) {
+            self.contiguous += 1;
+        }
+    }
+}
+
+#[derive(Default)]
+struct Outgoing {
+    last_sequence: u64,
+    unacked: BTreeMap<u64, (Instant, RemoteWrapper)>,
+}
+
+/// Bookkeeping for at-least-once delivery, shared by all NetworkInterfaces of a cluster so that it
+/// survives reconnects.
+///
+/// Outgoing messages are numbered per peer and kept until the peer acknowledges them, at most
+/// `max_unacked` per peer and for `ttl`. Incoming messages are deduplicated per sender session, which
+/// is forgotten once it has not sent anything for `ttl`; the sender does not redeliver after that.
+pub(crate) struct DeliveryLedger {
+    session: Uuid,
+    outgoing: HashMap<NodeAddr, Outgoing>,
+    incoming: HashMap<Uuid, ReceiveWindow>,
+    max_unacked: usize,
+    ttl: Duration,
+}
+
+impl DeliveryLedger {
+    pub fn new() -> Self {
+        Self::with_limits(MAX_UNACKED, UNACKED_TTL)
+    }
+
+    pub fn with_limits(max_unacked: usize, ttl: Duration) -> Self {
+        Self {
+            session: Uuid::new_v4(),
+            outgoing: HashMap::new(),
+            incoming: HashMap::new(),
+            max_unacked,
+            ttl,
+        }
+    }
+
+    /// Assigns the next sequence number for `peer` and retains the message until it is acked.
+    pub fn prepare(&mut self, peer: &NodeAddr, mut wrapper: RemoteWrapper) -> RemoteWrapper {
+        let outgoing = self.outgoing.entry(peer.clone()).or_default();
+        outgoing.last_sequence += 1;
+        wrapper.delivery = Some(Delivery {
+            session: self.session,
+            sequence: outgoing.last_sequence,
+            retained_from: outgoing.last_sequence,
+        });
+        outgoing
+            .unacked
+            .insert(outgoing.last_sequence, (Instant::now(), wrapper.clone()));
+        while outgoing.unacked.len() > self.max_unacked {
+            if let Some((_, (_, dropped))) = outgoing.unacked.pop_first() {
+                give_up(peer, &dropped, "too many messages are unacknowledged");
+            }
+        }
+        outgoing.with_retained_from(&wrapper)
+    }
+
+    pub fn ack(&mut self, peer: &NodeAddr, delivery: Delivery) {
+        if delivery.session != self.session {
+            return;
+        }
+        if let Some(outgoing) = self.outgoing.get_mut(peer) {
+            outgoing.unacked.remove(&delivery.sequence);
+        }
+    }
+
+    /// Messages to `peer` that have not been acked yet, in sending order.
+    pub fn unacked(&self, peer: &NodeAddr) -> Vec<RemoteWrapper> {
+        self.outgoing
+            .get(peer)
+            .map(|outgoing| {
+                outgoing
+                    .unacked
+                    .values()
+                    .map(|(_, wrapper)| outgoing.with_retained_from(wrapper))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gives up messages that have not been acked within the ttl and forgets sender sessions that
+    /// have been quiet as long. The sequence numbers of the peers are kept, so that the peers do not
+    /// discard later messages as duplicates.
+    pub fn prune(&mut self) {
+        let ttl = self.ttl;
+        self.incoming
+            .retain(|_, window| window.last_received.elapsed() < ttl);
+        for (peer, outgoing) in self.outgoing.iter_mut() {
+            outgoing.unacked.retain(|_, (sent, wrapper)| {
+                let expired = sent.elapsed() >= ttl;
+                if expired {
+                    give_up(peer, wrapper, "it was not acknowledged in time");
+                }
+                !expired
+            });
+        }
+    }
+
+    /// Returns `false` if the message has been received before and must not be delivered again.
+    pub fn receive(&mut self, delivery: Delivery) -> bool {
+        self.incoming
+            .entry(delivery.session)
+            .or_insert_with(ReceiveWindow::new)
+            .receive(delivery)
+    }
+
+    /// Received sequence numbers of `session` that are kept because an earlier one is missing.
+    #[cfg(test)]
+    pub(crate) fn out_of_order(&self, session: &Uuid) -> Option<usize> {
+        self.incoming
+            .get(session)
+            .map(|window| window.received.len())
+    }
+}
+
+impl Outgoing {
+    /// `wrapper` announcing the lowest sequence number that is currently retained.
+    fn with_retained_from(&self, wrapper: &RemoteWrapper) -> RemoteWrapper {
+        let mut wrapper = wrapper.clone();
+        if let (Some(delivery), Some(first)) = (&mut wrapper.delivery, self.unacked.keys().next()) {
+            delivery.retained_from = *first;
+        }
+        wrapper
+    }
+}
+
+fn give_up(peer: &NodeAddr, wrapper: &RemoteWrapper, reason: &str) {
+    warn!(
+        "Reliable message '{}' to {} is dropped because {}",
+        wrapper.identifier, peer, reason
+    );
+    record_dropped(&wrapper.identifier);
+}
@@ -1,34 +1,50 @@
+mod delivery;
 mod resolver;
+#[cfg(test)]
+mod tests;
 mod writer;
 
+pub use self::delivery::Delivery;
+pub(crate) use self::delivery::DeliveryLedger;
+#[cfg(unix)]
+pub(crate) use self::resolver::ConnectUnix;
+pub(crate) use self::resolver::{Connect, Resolver, DEFAULT_CONNECT_TIMEOUT};
+
 use actix::prelude::*;
 use log::*;
+use parking_lot::Mutex;
 use std::io::Error;
-use std::net::SocketAddr;
-use tokio::net::TcpStream;
+use std::sync::Arc;
 
-use crate::cluster::{Cluster, NodeEvent};
+use crate::cluster::{ClusterHandle, ConnectionLost, NodeEvent, SeedResolved};
 use crate::codec::{ClusterMessage, ConnectCodec};
-use crate::network::resolver::{Connect, Resolver};
 use crate::network::writer::Writer;
-use crate::remote::{AddrRepresentation, AddrResolver, RemoteWrapper};
-use crate::Node;
-use crate::{ConnectionApproval, ConnectionApprovalResponse, Connector, CustomSystemService};
+use crate::remote::{AddrRepresentation, RemoteWrapper, Unwatch, Watch};
+use crate::telemetry::{
+    record_frame_received, record_frame_sent, record_handshake, record_reconnect, Backlog, Queue,
+};
+use crate::transport::Connection;
+use crate::{ConnectionApproval, ConnectionApprovalResponse};
+use crate::{Node, NodeAddr};
 use actix::io::WriteHandler;
 use std::fmt;
-use std::thread::sleep;
-use tokio::time::Duration;
 use tokio_util::codec::FramedRead;
 
 pub struct NetworkInterface {
-    own_ip: SocketAddr,
-    pub addr: SocketAddr,
-    stream: Vec<TcpStream>,
+    own_ip: NodeAddr,
+    pub addr: NodeAddr,
+    connection: Option<Connection>,
+    /// Address the connection was established with, if the transport knows it
+    peer_addr: Option<NodeAddr>,
     connected: bool,
     own_addr: Option<Addr<NetworkInterface>>,
-    counter: i8,
+    counter: u8,
     seed: bool,
+    /// Whether this side dialed the connection and is therefore the one to reconnect
+    dialing: bool,
     writer: Option<Addr<Writer>>,
+    delivery: Arc<Mutex<DeliveryLedger>>,
+    cluster: Option<ClusterHandle>,
 }
 
 impl Actor for NetworkInterface {
@@ -38,22 +54,39 @@ impl Actor for NetworkInterface {
         debug!(target: &self.own_ip.to_string(), "NetworkInterface started! {}", self.addr);
         self.own_addr = Some(ctx.address());
         self.counter = 0;
-        if self.stream.is_empty() {
-            self.connect_to_stream(ctx);
-        } else {
-            self.frame_stream(ctx);
+        match self.connection.take() {
+            Some(connection) => self.frame_stream(connection, None, ctx),
+            None => self.connect_to_stream(ctx),
         }
     }
 
     fn stopping(&mut self, ctx: &mut Context<Self>) -> Running {
         warn!(target: &self.own_ip.to_string(), "NetworkInterface stopping! {}, counter: {}", self.addr, self.counter);
-        if self.counter < 2 {
-            self.stream = vec![];
+        // actix stops remaining actors when the runtime is dropped, there is nothing to reconnect to then
+        let runtime_alive = tokio::runtime::Handle::try_current().is_ok();
+        let reconnect_attempts = self.cluster().config().reconnect_attempts();
+        if self.dialing && self.counter < reconnect_attempts && runtime_alive {
+            record_reconnect(&self.addr);
+            self.connected = false;
             self.connect_to_stream(ctx);
             return Running::Continue;
         }
 
-        Cluster::from_custom_registry().do_send(NodeEvent::MemberDown(self.addr));
+        // the dialing side reconnects, the peer is only down if it does not in time
+        if !self.dialing {
+            if let Some(node) = self.own_addr.clone() {
+                self.cluster().cluster().do_send(ConnectionLost {
+                    node,
+                    addr: self.addr.clone(),
+                    connected: self.connected,
+                });
+            }
+            return Running::Stop;
+        }
+
+        self.cluster()
+            .cluster()
+            .do_send(NodeEvent::MemberDown(self.addr.clone()));
         Running::Stop
     }
 
@@ -63,122 +96,214 @@ impl Actor for NetworkInterface {
 }
 
 impl NetworkInterface {
-    pub fn new(own_ip: SocketAddr, addr: SocketAddr, seed: bool) -> NetworkInterface {
+    pub fn new<A: Into<NodeAddr>>(own_ip: A, addr: A, seed: bool) -> NetworkInterface {
         NetworkInterface {
-            own_ip,
-            addr,
-            stream: vec![],
+            own_ip: own_ip.into(),
+            addr: addr.into(),
+            connection: None,
+            peer_addr: None,
             connected: false,
             own_addr: None,
             counter: 0,
             seed,
+            dialing: true,
             writer: None,
+            delivery: Arc::new(Mutex::new(DeliveryLedger::new())),
+            cluster: None,
         }
     }
 
-    pub fn from_stream(
-        own_ip: SocketAddr,
-        addr: SocketAddr,
-        stream: TcpStream,
+    /// Shares the ledger for at-least-once delivery with other NetworkInterfaces of the cluster.
+    pub(crate) fn with_delivery_ledger(mut self, delivery: Arc<Mutex<DeliveryLedger>>) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// Binds the NetworkInterface to `cluster` instead of the cluster of the current actix system.
+    pub fn with_cluster(mut self, cluster: ClusterHandle) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    fn cluster(&self) -> ClusterHandle {
+        self.cluster.clone().unwrap_or_else(ClusterHandle::current)
+    }
+
+    pub fn from_stream<A: Into<NodeAddr>>(
+        own_ip: A,
+        addr: A,
+        stream: impl Into<Connection>,
     ) -> NetworkInterface {
         let mut ni = Self::new(own_ip, addr, false);
-        ni.stream.push(stream);
+        ni.connection = Some(stream.into());
+        ni.dialing = false;
         ni
     }
 
-    fn frame_stream(&mut self, ctx: &mut Context<Self>) {
-        let stream = self.stream.pop().unwrap();
-        let (r, w) = stream.into_split();
+    /// Reads and writes the frames of `connection`, starting with `handshake` if this side connected.
+    fn frame_stream(
+        &mut self,
+        mut connection: Connection,
+        handshake: Option<ClusterMessage>,
+        ctx: &mut Context<Self>,
+    ) {
+        self.peer_addr = connection.peer_addr().cloned();
+        let messages = connection.take_message_stream();
+        let (r, w) = connection.into_split();
 
-        let framed = actix::io::FramedWrite::new(w, ConnectCodec::new(), ctx);
-        self.writer = Some(Writer::new(framed).start());
+        // configure write side of the connection
+        let mut framed = actix::io::FramedWrite::new(w, ConnectCodec::new(), ctx);
+        if let Some(handshake) = handshake {
+            framed.write(handshake);
+        }
+        let mut writer = Writer::new(framed);
         ctx.add_stream(FramedRead::new(r, ConnectCodec::new()));
+
+        if let Some(messages) = messages {
+            let (r, w) = messages.into_split();
+            let framed = actix::io::FramedWrite::new(w, ConnectCodec::without_handshake(), ctx);
+            writer = writer.with_message_stream(framed);
+            ctx.add_stream(FramedRead::new(r, ConnectCodec::without_handshake()));
+        }
+        self.writer = Some(writer.start());
     }
 
     fn connect_to_stream(&mut self, ctx: &mut Context<Self>) {
-        let addr = self.addr.clone().to_string();
-
-        Resolver::from_registry()
-            .send(Connect::host(addr))
+        let cluster = self.cluster();
+        let config = cluster.config();
+        let reconnect_delay = config.reconnect_delay();
+        config
+            .transport()
+            .connect_timeout(
+                self.own_ip.clone(),
+                self.addr.clone(),
+                config.connect_timeout(),
+            )
             .into_actor(self)
-            .map(|res, act, ctx| match res {
-                Ok(stream) => {
-                    if let Ok(stream) = stream {
-                        debug!(
-                            "Connected to network node: {}",
-                            act.addr.clone().to_string()
-                        );
-
-                        let (r, w) = stream.into_split();
-
-                        // configure write side of the connection
-                        let mut framed = actix::io::FramedWrite::new(w, ConnectCodec::new(), ctx);
-                        let reply_port = act.own_ip.port();
-                        framed.write(ClusterMessage::Request(reply_port, act.seed));
-                        act.writer = Some(Writer::new(framed).start());
-
-                        ctx.add_stream(FramedRead::new(r, ConnectCodec::new()));
-                    } else {
-                        debug!("Connection refused! Trying to reconnect!");
-                        act.counter += 1;
-                        sleep(Duration::from_secs(1));
-                        ctx.stop();
-                    }
+            .map(move |res, act, ctx| match res {
+                Ok(connection) => {
+                    debug!("Connected to network node: {}", act.addr);
+                    let request = ClusterMessage::Request(act.own_ip.clone(), act.seed);
+                    act.frame_stream(connection, Some(request), ctx);
                 }
                 Err(err) => {
-                    error!("{} | {}", err.to_string(), act.addr.to_string());
-                    act.counter += 1;
-                    sleep(Duration::from_secs(1));
-                    ctx.stop();
+                    debug!("Connection refused ({})! Trying to reconnect!", err);
+                    act.counter = act.counter.saturating_add(1);
+                    ctx.run_later(reconnect_delay, |_act, ctx| ctx.stop());
+                }
+            })
+            .wait(ctx);
+    }
+
+    /// The accepting node is identified by the address it advertises, like the other nodes know it,
+    /// not by the address it was dialed with, e.g. the host name of a seed node.
+    fn accepted_by(&mut self, advertised: NodeAddr, ctx: &mut Context<Self>) {
+        let dialed = self.addr.clone();
+        let addr = match &self.peer_addr {
+            Some(peer_addr) => peer_addr.listening_addr(advertised),
+            None => dialed.listening_addr(advertised),
+        };
+        if addr == dialed {
+            return self.finish_connecting(false);
+        }
+        debug!(target: &self.own_ip.to_string(), "Node {} advertises {}", dialed, addr);
+
+        self.cluster()
+            .cluster()
+            .send(ConnectionApproval {
+                addr: addr.clone(),
+                send_addr: dialed.clone(),
+            })
+            .into_actor(self)
+            .map(move |res, act, ctx| match res {
+                Ok(ConnectionApprovalResponse::Approved) => {
+                    act.addr = addr.clone();
+                    act.cluster()
+                        .connector()
+                        .do_send(SeedResolved { seed: dialed, addr });
+                    act.finish_connecting(false)
+                }
+                Ok(ConnectionApprovalResponse::Declined) => {
+                    record_handshake(&act.addr, false);
+                    ctx.stop()
                 }
+                Err(_) => {}
             })
             .wait(ctx);
     }
 
     fn finish_connecting(&mut self, self_is_seed: bool) {
         self.connected = true;
+        self.counter = 0;
+        record_handshake(&self.addr, true);
+        let unacked = self.delivery.lock().unacked(&self.addr);
+        for wrapper in unacked {
+            self.transmit_message(ClusterMessage::Message(wrapper));
+        }
 
         match self.own_addr.clone() {
             Some(addr) => {
-                debug!(target: &self.own_ip.to_string(), "finish connecting to {}", self.addr.to_string());
-                let node = Node::new(self.addr, Some(addr));
-                Cluster::from_custom_registry().do_send(NodeEvent::MemberUp(node, self_is_seed));
+                debug!(target: &self.own_ip.to_string(), "finish connecting to {}", self.addr);
+                let cluster = self.cluster();
+                let node = Node::new(self.addr.clone(), Some(addr)).with_cluster(cluster.clone());
+                cluster
+                    .cluster()
+                    .do_send(NodeEvent::MemberUp(node, self_is_seed));
             }
             None => error!("NetworkInterface might not have been started already!"),
         };
     }
 
     fn transmit_message(&mut self, msg: ClusterMessage) {
-        self.writer.as_ref().unwrap().do_send(msg);
+        record_frame_sent(&self.addr, message_info(&msg));
+        self.writer
+            .as_ref()
+            .unwrap()
+            .do_send(Queued::new(Queue::Writer, msg));
     }
 
     fn received_message(&mut self, mut msg: RemoteWrapper) {
         msg.source = self.own_addr.clone();
+        let _stage = msg.enter_receive_stage("network_interface");
+        if let Some(delivery) = msg.delivery {
+            self.transmit_message(ClusterMessage::Ack(delivery));
+            if !self.delivery.lock().receive(delivery) {
+                debug!(
+                    "Dropping duplicate of reliable message '{}'",
+                    msg.identifier
+                );
+                return;
+            }
+        }
         match msg.destination.id {
             AddrRepresentation::NetworkInterface => {
                 panic!("NetworkInterface does not interact as RemoteActor")
             }
-            AddrRepresentation::Connector => Connector::from_custom_registry().do_send(msg),
-            AddrRepresentation::Key(_) => AddrResolver::from_registry().do_send(msg),
+            AddrRepresentation::Connector => self.cluster().connector().do_send(msg),
+            AddrRepresentation::AddrResolver | AddrRepresentation::Key(_) => {
+                self.cluster().addr_resolver().do_send(msg)
+            }
         }
     }
 
-    fn set_reply_port(&mut self, port: u16, ctx: &mut Context<Self>, seed: bool) {
-        let send_addr = self.addr;
-        self.addr.set_port(port);
-        let addr = self.addr;
+    fn set_reply_addr(&mut self, advertised: NodeAddr, ctx: &mut Context<Self>, seed: bool) {
+        let send_addr = self.addr.clone();
+        self.addr = send_addr.listening_addr(advertised);
+        let addr = self.addr.clone();
 
-        Cluster::from_custom_registry()
+        self.cluster()
+            .cluster()
             .send(ConnectionApproval { addr, send_addr })
             .into_actor(self)
             .map(move |res, act, ctx| {
                 if let Ok(message_response) = res {
                     match message_response {
                         ConnectionApprovalResponse::Approved => {
-                            act.transmit_message(ClusterMessage::Response);
+                            act.transmit_message(ClusterMessage::Response(act.own_ip.clone()));
                             act.finish_connecting(seed)
                         }
                         ConnectionApprovalResponse::Declined => {
+                            record_handshake(&act.addr, false);
                             act.transmit_message(ClusterMessage::Decline);
                             ctx.stop()
                         }
@@ -189,17 +314,39 @@ impl NetworkInterface {
     }
 }
 
+/// Identifier and payload size of remote messages for metrics.
+fn message_info(msg: &ClusterMessage) -> Option<(&str, usize)> {
+    match msg {
+        ClusterMessage::Message(wrapper) => {
+            Some((wrapper.identifier.as_str(), wrapper.message_buffer.len()))
+        }
+        _ => None,
+    }
+}
+
 impl StreamHandler<Result<ClusterMessage, Error>> for NetworkInterface {
     fn handle(&mut self, item: Result<ClusterMessage, Error>, ctx: &mut Context<Self>) {
         match item {
-            Ok(msg) => match msg {
-                ClusterMessage::Request(reply_port, seed) => {
-                    self.set_reply_port(reply_port, ctx, seed)
+            Ok(msg) => {
+                record_frame_received(&self.addr, message_info(&msg));
+                match msg {
+                    ClusterMessage::Request(reply_addr, seed) => {
+                        self.set_reply_addr(reply_addr, ctx, seed)
+                    }
+                    ClusterMessage::Response(advertised) => self.accepted_by(advertised, ctx),
+                    ClusterMessage::Message(remote_message) => {
+                        self.received_message(remote_message)
+                    }
+                    ClusterMessage::Ack(delivery) => self.delivery.lock().ack(&self.addr, delivery),
+                    ClusterMessage::Decline => {
+                        // the peer may not have noticed yet that the previous connection ended
+                        record_handshake(&self.addr, false);
+                        self.counter = self.counter.saturating_add(1);
+                        let reconnect_delay = self.cluster().config().reconnect_delay();
+                        ctx.run_later(reconnect_delay, |_act, ctx| ctx.stop());
+                    }
                 }
-                ClusterMessage::Response => self.finish_connecting(false),
-                ClusterMessage::Message(remote_message) => self.received_message(remote_message),
-                ClusterMessage::Decline => ctx.stop(),
-            },
+            }
             Err(err) => warn!("{}", err),
         }
     }
@@ -213,15 +360,90 @@ impl Handler<ClusterMessage> for NetworkInterface {
     }
 }
 
+/// A message that is counted in the backlog of its mailbox until it is handled.
+pub(crate) struct Queued<M> {
+    message: M,
+    _backlog: Backlog,
+}
+
+impl<M> Queued<M> {
+    pub(crate) fn new(queue: Queue, message: M) -> Self {
+        Self {
+            message,
+            _backlog: Backlog::new(queue),
+        }
+    }
+}
+
+impl<M: Message> Message for Queued<M> {
+    type Result = M::Result;
+}
+
+macro_rules! handle_queued {
+    ($($msg:ty),*) => {
+        $(
+            impl Handler<Queued<$msg>> for NetworkInterface {
+                type Result = ();
+
+                fn handle(&mut self, msg: Queued<$msg>, ctx: &mut Self::Context) -> Self::Result {
+                    <Self as Handler<$msg>>::handle(self, msg.message, ctx)
+                }
+            }
+        )*
+    };
+}
+
+handle_queued!(ClusterMessage, ReliableClusterMessage, Watch, Unwatch);
+
 #[derive(Message)]
 #[rtype(result = "Result<(), MailboxError>")]
-pub struct WrappedClusterMessage(pub(crate) ClusterMessage);
+pub struct WrappedClusterMessage(pub(crate) Queued<ClusterMessage>);
 
 impl Handler<WrappedClusterMessage> for NetworkInterface {
     type Result = ResponseFuture<Result<(), MailboxError>>;
 
     fn handle(&mut self, msg: WrappedClusterMessage, _ctx: &mut Self::Context) -> Self::Result {
-        Box::pin(self.writer.as_ref().unwrap().send(msg.0))
+        let msg = msg.0.message;
+        record_frame_sent(&self.addr, message_info(&msg));
+        Box::pin(
+            self.writer
+                .as_ref()
+                .unwrap()
+                .send(Queued::new(Queue::Writer, msg)),
+        )
+    }
+}
+
+/// Sends a message with at-least-once delivery, see [RemoteAddr::do_send_reliable](./struct.RemoteAddr.html#method.do_send_reliable).
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ReliableClusterMessage(pub(crate) RemoteWrapper);
+
+impl Handler<ReliableClusterMessage> for NetworkInterface {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReliableClusterMessage, _ctx: &mut Self::Context) -> Self::Result {
+        let wrapper = self.delivery.lock().prepare(&self.addr, msg.0);
+        if self.connected {
+            self.transmit_message(ClusterMessage::Message(wrapper));
+        }
+    }
+}
+
+impl Handler<Watch> for NetworkInterface {
+    type Result = ();
+
+    /// Watches are kept by the AddrResolver of the cluster this NetworkInterface belongs to.
+    fn handle(&mut self, msg: Watch, _ctx: &mut Self::Context) -> Self::Result {
+        self.cluster().addr_resolver().do_send(msg);
+    }
+}
+
+impl Handler<Unwatch> for NetworkInterface {
+    type Result = ();
+
+    fn handle(&mut self, msg: Unwatch, _ctx: &mut Self::Context) -> Self::Result {
+        self.cluster().addr_resolver().do_send(msg);
     }
 }
 
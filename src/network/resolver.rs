@@ -3,7 +3,9 @@
 use std::collections::VecDeque;
 use std::future::Future;
 use std::io;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::task::{self, Poll};
 use std::time::Duration;
@@ -11,15 +13,21 @@ use std::time::Duration;
 use derive_more::Display;
 use log::warn;
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 
 use actix::prelude::*;
 use futures::prelude::future::Either;
 use tokio::time::{sleep, Sleep};
-use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::config::{LookupIpStrategy, ResolverConfig, ResolverOpts};
 use trust_dns_resolver::error::ResolveError;
 use trust_dns_resolver::lookup_ip::LookupIp;
+use trust_dns_resolver::system_conf::read_system_conf;
 use trust_dns_resolver::TokioAsyncResolver as AsyncResolver;
 
+/// Timeout of connection attempts unless the ClusterConfig sets another one
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Eq, PartialEq, Debug)]
 pub struct Resolve {
     pub name: String,
@@ -42,9 +50,14 @@ impl Connect {
         Connect {
             name: host.as_ref().to_owned(),
             port: None,
-            timeout: Duration::from_secs(1),
+            timeout: DEFAULT_CONNECT_TIMEOUT,
         }
     }
+
+    pub fn timeout(mut self, timeout: Duration) -> Connect {
+        self.timeout = timeout;
+        self
+    }
 }
 
 impl Message for Connect {
@@ -58,6 +71,34 @@ impl Message for ConnectAddr {
     type Result = Result<TcpStream, ResolverError>;
 }
 
+/// Connects to a node listening on a Unix domain socket; there is nothing to resolve.
+#[cfg(unix)]
+#[derive(Eq, PartialEq, Debug)]
+pub struct ConnectUnix {
+    pub path: PathBuf,
+    pub timeout: Duration,
+}
+
+#[cfg(unix)]
+impl ConnectUnix {
+    pub fn path<P: Into<PathBuf>>(path: P) -> ConnectUnix {
+        ConnectUnix {
+            path: path.into(),
+            timeout: DEFAULT_CONNECT_TIMEOUT,
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> ConnectUnix {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[cfg(unix)]
+impl Message for ConnectUnix {
+    type Result = Result<UnixStream, ResolverError>;
+}
+
 #[derive(Debug, Display)]
 pub enum ResolverError {
     /// Failed to resolve the hostname
@@ -100,16 +141,16 @@ impl Actor for Resolver {
                         }
                         Box::pin(
                             async {
-                                match AsyncResolver::tokio_from_system_conf() {
-                                    Ok(resolver) => resolver,
+                                let (config, mut opts) = match read_system_conf() {
+                                    Ok(conf) => conf,
                                     Err(err) => {
                                         warn!("Can not create system dns resolver: {}", err);
-                                        AsyncResolver::tokio(
-                                            ResolverConfig::default(),
-                                            ResolverOpts::default(),
-                                        )
+                                        (ResolverConfig::default(), ResolverOpts::default())
                                     }
-                                }
+                                };
+                                // all A and AAAA records of a seed node are tried
+                                opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+                                AsyncResolver::tokio(config, opts)
                             }
                             .into_actor(this),
                         )
@@ -172,6 +213,21 @@ impl Handler<ConnectAddr> for Resolver {
     }
 }
 
+#[cfg(unix)]
+impl Handler<ConnectUnix> for Resolver {
+    type Result = ResponseFuture<Result<UnixStream, ResolverError>>;
+
+    fn handle(&mut self, msg: ConnectUnix, _: &mut Self::Context) -> Self::Result {
+        Box::pin(async move {
+            match tokio::time::timeout(msg.timeout, UnixStream::connect(msg.path)).await {
+                Ok(Ok(stream)) => Ok(stream),
+                Ok(Err(err)) => Err(ResolverError::IoError(err)),
+                Err(_) => Err(ResolverError::Timeout),
+            }
+        })
+    }
+}
+
 type LookupIpFuture = Pin<Box<dyn Future<Output = Result<LookupIp, ResolveError>>>>;
 
 /// A resolver future.
@@ -186,43 +242,44 @@ struct ResolveFut {
 impl ResolveFut {
     pub fn new<S: AsRef<str>>(addr: S, port: u16, resolver: &AsyncResolver) -> ResolveFut {
         // try to parse as a regular SocketAddr first
-        if let Ok(addr) = addr.as_ref().parse() {
-            let mut addrs = VecDeque::new();
-            addrs.push_back(addr);
-
-            ResolveFut {
-                port,
-                lookup: None,
-                addrs: Some(addrs),
-                error: None,
-                error2: None,
-            }
-        } else {
-            // we need to do dns resolution
-            match ResolveFut::parse(addr.as_ref(), port) {
-                Ok((host, port)) => ResolveFut {
-                    port,
-                    lookup: {
-                        // Clone data so it can be moved to async block
-                        let resolver_clone = resolver.clone();
-                        let host = host.to_string();
-                        Some(Box::pin(async move {
-                            let resolver = resolver_clone;
-                            resolver.lookup_ip(host).await
-                        }))
-                    },
-                    addrs: None,
-                    error: None,
-                    error2: None,
-                },
-                Err(err) => ResolveFut {
+        let parsed = ResolveFut::parse(addr.as_ref(), port)
+            .map(|(host, port)| (host, port, ResolveFut::ip_literal(host, port)));
+        match parsed {
+            Ok((_, port, Some(addr))) => {
+                let mut addrs = VecDeque::new();
+                addrs.push_back(addr);
+
+                ResolveFut {
                     port,
                     lookup: None,
-                    addrs: None,
-                    error: Some(err),
+                    addrs: Some(addrs),
+                    error: None,
                     error2: None,
-                },
+                }
             }
+            // we need to do dns resolution
+            Ok((host, port, None)) => ResolveFut {
+                port,
+                lookup: {
+                    // Clone data so it can be moved to async block
+                    let resolver_clone = resolver.clone();
+                    let host = host.to_string();
+                    Some(Box::pin(async move {
+                        let resolver = resolver_clone;
+                        resolver.lookup_ip(host).await
+                    }))
+                },
+                addrs: None,
+                error: None,
+                error2: None,
+            },
+            Err(err) => ResolveFut {
+                port,
+                lookup: None,
+                addrs: None,
+                error: Some(err),
+                error2: None,
+            },
         }
     }
 
@@ -236,23 +293,41 @@ impl ResolveFut {
         }
     }
 
+    /// Splits `addr` into host and port, e.g. `localhost:8000`, `[::1]:8000` or `[fe80::1%2]`.
+    /// Without a port, `port` is taken.
     fn parse(addr: &str, port: u16) -> Result<(&str, u16), ResolverError> {
-        macro_rules! try_opt {
-            ($e:expr, $msg:expr) => {
-                match $e {
-                    Some(r) => r,
-                    None => return Err(ResolverError::InvalidInput($msg)),
-                }
-            };
+        fn parse_port(port: &str) -> Result<u16, ResolverError> {
+            port.parse()
+                .map_err(|_| ResolverError::InvalidInput("invalid port"))
         }
 
-        // split the string by ':' and convert the second part to u16
-        let mut parts_iter = addr.splitn(2, ':');
-        let host = try_opt!(parts_iter.next(), "invalid socket address");
-        let port_str = parts_iter.next().unwrap_or("");
-        let port: u16 = port_str.parse().unwrap_or(port);
+        if let Some(bracketed) = addr.strip_prefix('[') {
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or(ResolverError::InvalidInput("missing ']' in IPv6 address"))?;
+            return match rest.strip_prefix(':') {
+                Some(port) => Ok((host, parse_port(port)?)),
+                None if rest.is_empty() => Ok((host, port)),
+                None => Err(ResolverError::InvalidInput("invalid socket address")),
+            };
+        }
+        match addr.rsplit_once(':') {
+            // an IPv6 address without brackets has no port
+            Some((host, _)) if host.contains(':') => Ok((addr, port)),
+            Some((host, port)) => Ok((host, parse_port(port)?)),
+            None => Ok((addr, port)),
+        }
+    }
 
-        Ok((host, port))
+    /// IP addresses need no lookup; IPv6 addresses may carry a numeric scope id, e.g. `fe80::1%2`.
+    fn ip_literal(host: &str, port: u16) -> Option<SocketAddr> {
+        if host.contains(':') {
+            format!("[{}]:{}", host, port).parse().ok()
+        } else {
+            host.parse::<IpAddr>()
+                .ok()
+                .map(|ip| SocketAddr::new(ip, port))
+        }
     }
 }
 
@@ -314,24 +389,26 @@ impl Future for HasSleep {
     }
 }
 
-/// A TCP stream connector.
+/// A TCP stream connector. It tries the addresses one after another, each for up to `timeout`.
 #[allow(clippy::type_complexity)]
 pub struct TcpConnector {
     addrs: VecDeque<SocketAddr>,
-    timeout: HasSleep,
+    timeout: Duration,
+    attempt_timeout: HasSleep,
     stream: Option<Pin<Box<dyn Future<Output = Result<TcpStream, io::Error>>>>>,
 }
 
 impl TcpConnector {
     pub fn new(addrs: VecDeque<SocketAddr>) -> TcpConnector {
-        TcpConnector::with_timeout(addrs, Duration::from_secs(1))
+        TcpConnector::with_timeout(addrs, DEFAULT_CONNECT_TIMEOUT)
     }
 
     pub fn with_timeout(addrs: VecDeque<SocketAddr>, timeout: Duration) -> TcpConnector {
         TcpConnector {
             addrs,
             stream: None,
-            timeout: HasSleep::new(timeout),
+            timeout,
+            attempt_timeout: HasSleep::new(timeout),
         }
     }
 }
@@ -347,17 +424,19 @@ impl<A: Actor> ActorFuture<A> for TcpConnector {
     ) -> Poll<Self::Output> {
         let this = self.get_mut();
 
-        // timeout
-        if Pin::new(&mut this.timeout).poll(cx).is_ready() {
-            return Poll::Ready(Err(ResolverError::Timeout));
-        }
-
-        // connect
         loop {
             if let Some(ref mut fut) = this.stream {
                 match Pin::new(fut).poll(cx) {
                     Poll::Ready(Ok(sock)) => return Poll::Ready(Ok(sock)),
-                    Poll::Pending => return Poll::Pending,
+                    Poll::Pending => {
+                        // an address that does not answer must not keep the others from being tried
+                        if Pin::new(&mut this.attempt_timeout).poll(cx).is_pending() {
+                            return Poll::Pending;
+                        }
+                        if this.addrs.is_empty() {
+                            return Poll::Ready(Err(ResolverError::Timeout));
+                        }
+                    }
                     Poll::Ready(Err(err)) => {
                         if this.addrs.is_empty() {
                             return Poll::Ready(Err(ResolverError::IoError(err)));
@@ -368,6 +447,7 @@ impl<A: Actor> ActorFuture<A> for TcpConnector {
             // try to connect
             let addr = this.addrs.pop_front().unwrap();
             this.stream = Some(Box::pin(TcpStream::connect(addr)));
+            this.attempt_timeout = HasSleep::new(this.timeout);
         }
     }
 }
@@ -0,0 +1,311 @@
+use crate::codec::ConnectCodec;
+use crate::network::resolver::{Resolve, Resolver, ResolverError, TcpConnector};
+use crate::network::{Delivery, DeliveryLedger};
+use crate::prelude::*;
+use crate::testkit::TestCluster;
+use crate::{ClusterMessage, FaultController, LinkFaults, MemoryNetwork, NodeAddr};
+use actix::prelude::*;
+use bytes::BytesMut;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tokio::time::Duration;
+use tokio_util::codec::{Decoder, Encoder};
+use uuid::Uuid;
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, PartialEq)]
+struct ReliableTestMessage {
+    value: u32,
+}
+
+fn test_wrapper(peer: &NodeAddr, value: u32) -> RemoteWrapper {
+    RemoteWrapper::new(
+        RemoteAddr::new_from_id(peer.clone(), "receiver"),
+        ReliableTestMessage { value },
+        None,
+    )
+}
+
+#[test]
+fn ledger_retains_messages_until_acked() {
+    let peer: NodeAddr = "127.0.0.1:1992".parse().unwrap();
+    let other_peer: NodeAddr = "unix:/tmp/telepathy-1993.sock".parse().unwrap();
+    let mut ledger = DeliveryLedger::new();
+
+    let first = ledger.prepare(&peer, test_wrapper(&peer, 1));
+    let second = ledger.prepare(&peer, test_wrapper(&peer, 2));
+    let other = ledger.prepare(&other_peer, test_wrapper(&other_peer, 3));
+    assert_eq!(first.delivery.unwrap().sequence, 1);
+    assert_eq!(second.delivery.unwrap().sequence, 2);
+    assert_eq!(other.delivery.unwrap().sequence, 1);
+
+    ledger.ack(
+        &peer,
+        Delivery {
+            session: Uuid::new_v4(),
+            sequence: 1,
+            retained_from: 1,
+        },
+    );
+    assert_eq!(ledger.unacked(&peer).len(), 2);
+
+    ledger.ack(&peer, first.delivery.unwrap());
+    let unacked = ledger.unacked(&peer);
+    assert_eq!(unacked.len(), 1);
+    assert_eq!(unacked[0].delivery.unwrap().sequence, 2);
+    assert_eq!(unacked[0].delivery.unwrap().retained_from, 2);
+    assert_eq!(ledger.unacked(&other_peer).len(), 1);
+}
+
+#[test]
+fn ledger_gives_up_oldest_messages_beyond_limit() {
+    let peer: NodeAddr = "127.0.0.1:1992".parse().unwrap();
+    let mut ledger = DeliveryLedger::with_limits(2, Duration::from_secs(60));
+
+    for value in 1..=3 {
+        ledger.prepare(&peer, test_wrapper(&peer, value));
+    }
+    let sequences: Vec<u64> = ledger
+        .unacked(&peer)
+        .iter()
+        .map(|wrapper| wrapper.delivery.unwrap().sequence)
+        .collect();
+    assert_eq!(sequences, vec![2, 3]);
+}
+
+#[test]
+fn ledger_gives_up_expired_messages() {
+    let peer: NodeAddr = "127.0.0.1:1992".parse().unwrap();
+    let mut ledger = DeliveryLedger::with_limits(10, Duration::ZERO);
+
+    ledger.prepare(&peer, test_wrapper(&peer, 1));
+    ledger.prune();
+    assert!(ledger.unacked(&peer).is_empty());
+
+    // the peer must not take later messages for duplicates
+    let next = ledger.prepare(&peer, test_wrapper(&peer, 2));
+    assert_eq!(next.delivery.unwrap().sequence, 2);
+}
+
+#[test]
+fn ledger_discards_duplicates() {
+    let mut ledger = DeliveryLedger::new();
+    let session = Uuid::new_v4();
+    let delivery = |sequence| Delivery {
+        session,
+        sequence,
+        retained_from: 1,
+    };
+
+    assert!(ledger.receive(delivery(2)));
+    assert!(!ledger.receive(delivery(2)));
+    assert!(ledger.receive(delivery(1)));
+    assert!(!ledger.receive(delivery(1)));
+    assert!(ledger.receive(delivery(3)));
+    assert!(!ledger.receive(delivery(2)));
+    assert!(ledger.receive(Delivery {
+        session: Uuid::new_v4(),
+        sequence: 1,
+        retained_from: 1,
+    }));
+}
+
+#[test]
+fn receive_window_compacts_sequences_given_up_by_sender() {
+    let peer: NodeAddr = "127.0.0.1:1992".parse().unwrap();
+    let mut sender = DeliveryLedger::with_limits(2, Duration::from_secs(60));
+    let mut receiver = DeliveryLedger::with_limits(10, Duration::ZERO);
+
+    let lost = sender.prepare(&peer, test_wrapper(&peer, 1));
+    let session = lost.delivery.unwrap().session;
+    let second = sender.prepare(&peer, test_wrapper(&peer, 2));
+    assert!(receiver.receive(second.delivery.unwrap()));
+    assert_eq!(receiver.out_of_order(&session), Some(1));
+
+    // the sender gives the first message up, the next one announces it
+    let third = sender.prepare(&peer, test_wrapper(&peer, 3));
+    assert_eq!(third.delivery.unwrap().retained_from, 2);
+    assert!(receiver.receive(third.delivery.unwrap()));
+    assert_eq!(receiver.out_of_order(&session), Some(0));
+    assert!(!receiver.receive(lost.delivery.unwrap()));
+
+    // quiet sessions are forgotten
+    receiver.prune();
+    assert_eq!(receiver.out_of_order(&session), None);
+}
+
+#[test]
+fn codec_transmits_delivery_and_ack() {
+    let peer: NodeAddr = "127.0.0.1:1992".parse().unwrap();
+    let delivery = Delivery {
+        session: Uuid::new_v4(),
+        sequence: 7,
+        retained_from: 5,
+    };
+    let mut wrapper = test_wrapper(&peer, 1);
+    wrapper.delivery = Some(delivery);
+
+    let mut codec = ConnectCodec::new();
+    let mut buffer = BytesMut::new();
+    codec
+        .encode(ClusterMessage::Request(peer.clone(), false), &mut buffer)
+        .unwrap();
+    codec
+        .encode(ClusterMessage::Message(wrapper), &mut buffer)
+        .unwrap();
+    codec
+        .encode(ClusterMessage::Ack(delivery), &mut buffer)
+        .unwrap();
+
+    match codec.decode(&mut buffer).unwrap() {
+        Some(ClusterMessage::Request(addr, false)) => assert_eq!(addr, peer),
+        _ => panic!("Expected ClusterMessage::Request"),
+    }
+    match codec.decode(&mut buffer).unwrap() {
+        Some(ClusterMessage::Message(received)) => {
+            assert_eq!(received.delivery, Some(delivery))
+        }
+        _ => panic!("Expected ClusterMessage::Message"),
+    }
+    match codec.decode(&mut buffer).unwrap() {
+        Some(ClusterMessage::Ack(acked)) => assert_eq!(acked, delivery),
+        _ => panic!("Expected ClusterMessage::Ack"),
+    }
+}
+
+#[actix_rt::test]
+async fn reliable_messages_are_redelivered_after_reconnect_once() {
+    let controller = FaultController::new();
+    let cluster = TestCluster::builder(2)
+        .in_memory(MemoryNetwork::new())
+        .faults(controller.clone())
+        .start()
+        .await;
+    let mut probe = cluster.probe(1, "receiver");
+    let receiver = cluster.remote_addr(0, 1, probe.id());
+    let wait = Duration::from_secs(1);
+    let quiet = Duration::from_millis(200);
+
+    controller.set_link(
+        cluster.addr(0),
+        cluster.addr(1),
+        LinkFaults {
+            duplicate: 1.0,
+            ..Default::default()
+        },
+    );
+    receiver.do_send_reliable(ReliableTestMessage { value: 1 });
+    assert_eq!(
+        probe.expect_message::<ReliableTestMessage>(wait).await,
+        ReliableTestMessage { value: 1 }
+    );
+    probe.expect_no_message(quiet).await;
+
+    controller.set_link(
+        cluster.addr(0),
+        cluster.addr(1),
+        LinkFaults {
+            drop: 1.0,
+            ..Default::default()
+        },
+    );
+    receiver.do_send_reliable(ReliableTestMessage { value: 2 });
+    receiver.do_send_reliable(ReliableTestMessage { value: 3 });
+    probe.expect_no_message(quiet).await;
+
+    controller.reset();
+    controller.disconnect(cluster.addr(0), cluster.addr(1));
+    for value in [2, 3] {
+        assert_eq!(
+            probe.expect_message::<ReliableTestMessage>(wait).await,
+            ReliableTestMessage { value }
+        );
+    }
+    probe.expect_no_message(quiet).await;
+}
+
+async fn resolve(name: &str, port: u16) -> Result<Vec<SocketAddr>, ResolverError> {
+    Resolver::from_registry()
+        .send(Resolve {
+            name: name.to_string(),
+            port: Some(port),
+        })
+        .await
+        .unwrap()
+        .map(Vec::from)
+}
+
+#[actix_rt::test]
+async fn resolver_parses_ipv6_literals() {
+    let loopback: SocketAddr = "[::1]:1992".parse().unwrap();
+    assert_eq!(resolve("[::1]:1992", 8000).await.unwrap(), vec![loopback]);
+    assert_eq!(resolve("[::1]", 1992).await.unwrap(), vec![loopback]);
+    assert_eq!(resolve("::1", 1992).await.unwrap(), vec![loopback]);
+    assert_eq!(
+        resolve("127.0.0.1", 1992).await.unwrap(),
+        vec!["127.0.0.1:1992".parse().unwrap()]
+    );
+
+    let link_local = resolve("[fe80::1%2]:1992", 8000).await.unwrap();
+    assert_eq!(link_local[0].to_string(), "[fe80::1%2]:1992");
+
+    assert!(matches!(
+        resolve("[::1:1992", 8000).await,
+        Err(ResolverError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        resolve("[::1]:port", 8000).await,
+        Err(ResolverError::InvalidInput(_))
+    ));
+}
+
+/// Connects via a [TcpConnector] to the given addresses.
+struct Dialer;
+
+impl Actor for Dialer {
+    type Context = Context<Self>;
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<tokio::net::TcpStream, ResolverError>")]
+struct Dial(Vec<SocketAddr>, Duration);
+
+impl Handler<Dial> for Dialer {
+    type Result = ResponseActFuture<Self, Result<tokio::net::TcpStream, ResolverError>>;
+
+    fn handle(&mut self, msg: Dial, _ctx: &mut Self::Context) -> Self::Result {
+        Box::pin(TcpConnector::with_timeout(msg.0.into(), msg.1))
+    }
+}
+
+#[actix_rt::test]
+async fn connector_tries_next_address_if_one_does_not_answer() {
+    use socket2::{Domain, Socket, Type};
+
+    // a listener with a full backlog does not answer further connection attempts
+    let blackhole = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+    blackhole
+        .bind(&SocketAddr::from(([127, 0, 0, 1], 0)).into())
+        .unwrap();
+    blackhole.listen(0).unwrap();
+    let blackhole_addr = blackhole.local_addr().unwrap().as_socket().unwrap();
+    let _queued = std::net::TcpStream::connect(blackhole_addr).unwrap();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let listener_addr = listener.local_addr().unwrap();
+
+    let dialer = Dialer.start();
+    let stream = dialer
+        .send(Dial(
+            vec![blackhole_addr, listener_addr],
+            Duration::from_millis(200),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), listener_addr);
+
+    let unanswered = dialer
+        .send(Dial(vec![blackhole_addr], Duration::from_millis(200)))
+        .await
+        .unwrap();
+    assert!(matches!(unanswered, Err(ResolverError::Timeout)));
+}
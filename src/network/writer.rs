@@ -1,23 +1,41 @@
 use crate::codec::ConnectCodec;
-use crate::ClusterMessage;
+use crate::network::Queued;
+use crate::transport::ConnectionWriter;
+use crate::{AddrRepresentation, ClusterMessage};
 use actix::io::{FramedWrite, WriteHandler};
 use actix::prelude::*;
 use std::io::Error;
-use tokio::net::tcp::OwnedWriteHalf;
 
 pub struct Writer {
-    framed: Vec<FramedWrite<ClusterMessage, OwnedWriteHalf, ConnectCodec>>,
+    framed: Vec<FramedWrite<ClusterMessage, ConnectionWriter, ConnectCodec>>,
 }
 
 impl Writer {
-    pub fn new(framed: FramedWrite<ClusterMessage, OwnedWriteHalf, ConnectCodec>) -> Self {
+    pub fn new(framed: FramedWrite<ClusterMessage, ConnectionWriter, ConnectCodec>) -> Self {
         Self {
             framed: vec![framed],
         }
     }
 
+    /// Messages to actors are written to the message stream, if the connection has one.
+    pub fn with_message_stream(
+        mut self,
+        framed: FramedWrite<ClusterMessage, ConnectionWriter, ConnectCodec>,
+    ) -> Self {
+        self.framed.push(framed);
+        self
+    }
+
     fn transmit_message(&mut self, msg: ClusterMessage) {
-        self.framed[0].write(msg);
+        let framed = match &msg {
+            ClusterMessage::Message(wrapper)
+                if matches!(wrapper.destination.id, AddrRepresentation::Key(_)) =>
+            {
+                self.framed.last_mut()
+            }
+            _ => self.framed.first_mut(),
+        };
+        framed.unwrap().write(msg);
     }
 }
 
@@ -25,11 +43,11 @@ impl Actor for Writer {
     type Context = Context<Self>;
 }
 
-impl Handler<ClusterMessage> for Writer {
+impl Handler<Queued<ClusterMessage>> for Writer {
     type Result = ();
 
-    fn handle(&mut self, msg: ClusterMessage, _ctx: &mut Self::Context) -> Self::Result {
-        self.transmit_message(msg);
+    fn handle(&mut self, msg: Queued<ClusterMessage>, _ctx: &mut Self::Context) -> Self::Result {
+        self.transmit_message(msg.message);
     }
 }
 
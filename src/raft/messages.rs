@@ -0,0 +1,72 @@
+use crate::raft::storage::LogEntry;
+use crate::raft::{RaftError, StateMachine};
+use crate::{DefaultSerialization, NodeAddr, RemoteAddr, RemoteMessage};
+use actix::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, Clone)]
+#[serializer(DefaultSerialization)]
+#[with_source(source)]
+pub struct RequestVote {
+    pub term: u64,
+    pub candidate: NodeAddr,
+    pub last_log_index: u64,
+    pub last_log_term: u64,
+    pub source: RemoteAddr,
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, Clone)]
+#[serializer(DefaultSerialization)]
+#[with_source(source)]
+pub struct RequestVoteResponse {
+    pub term: u64,
+    pub from: NodeAddr,
+    pub vote_granted: bool,
+    pub source: RemoteAddr,
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, Clone)]
+#[serializer(DefaultSerialization)]
+#[with_source(source)]
+pub struct AppendEntries {
+    pub term: u64,
+    pub leader: NodeAddr,
+    pub prev_log_index: u64,
+    pub prev_log_term: u64,
+    pub entries: Vec<LogEntry>,
+    pub leader_commit: u64,
+    pub source: RemoteAddr,
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, Clone)]
+#[serializer(DefaultSerialization)]
+#[with_source(source)]
+pub struct AppendEntriesResponse {
+    pub term: u64,
+    pub from: NodeAddr,
+    pub success: bool,
+    /// On success the index of the last replicated entry, otherwise the last index of the follower's log
+    pub match_index: u64,
+    pub source: RemoteAddr,
+}
+
+/// Local request to append a command to the replicated log. Resolves once the command has been
+/// committed and applied to the leader's state machine.
+pub struct Propose<S: StateMachine> {
+    pub command: S::Command,
+    phantom: PhantomData<fn() -> S>,
+}
+
+impl<S: StateMachine> Propose<S> {
+    pub fn new(command: S::Command) -> Self {
+        Self {
+            command,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<S: StateMachine> Message for Propose<S> {
+    type Result = Result<S::Output, RaftError>;
+}
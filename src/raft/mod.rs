@@ -0,0 +1,668 @@
+//! Strongly consistent replicated state machines based on the Raft consensus algorithm.
+//!
+//! Every member listed in the [RaftConfig](./struct.RaftConfig.html) runs a [Raft](./struct.Raft.html)
+//! actor with the same [StateMachine](./trait.StateMachine.html). Commands proposed to the leader are
+//! appended to a log that is persisted in a local directory, replicated via `RemoteMessage`s and
+//! applied in the same order on every member once a majority has stored them.
+
+mod messages;
+mod storage;
+#[cfg(test)]
+mod tests;
+
+pub use self::messages::{
+    AppendEntries, AppendEntriesResponse, Propose, RequestVote, RequestVoteResponse,
+};
+pub use self::storage::LogEntry;
+
+use crate::raft::storage::{HardState, RaftStorage};
+use crate::{
+    ClusterHandle, ClusterListener, ClusterLog, CustomSerialization, DefaultSerialization,
+};
+use crate::{NodeAddr, RemoteActor, RemoteAddr};
+use actix::prelude::*;
+use actix_broker::BrokerSubscribe;
+use derive_more::Display;
+use log::*;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const MAX_ENTRIES_PER_MESSAGE: usize = 64;
+
+/// The deterministic application state that is replicated by [Raft](./struct.Raft.html).
+///
+/// `apply` is called exactly once for every committed command and in the same order on every member.
+pub trait StateMachine: Unpin + 'static {
+    type Command: Serialize + DeserializeOwned + Send + 'static;
+    type Output: Send + 'static;
+
+    fn apply(&mut self, command: Self::Command) -> Self::Output;
+}
+
+#[derive(Debug, Display)]
+pub enum RaftError {
+    /// The node is not the leader; contains the current leader if known
+    #[display(fmt = "Node is not the leader (leader: {:?})", _0)]
+    NotLeader(Option<NodeAddr>),
+
+    /// The node lost its leadership before the command was committed
+    #[display(fmt = "Leadership was lost before the command was committed")]
+    LeadershipLost,
+
+    /// The command could not be (de)serialized
+    #[display(fmt = "Command could not be (de)serialized")]
+    Serialization,
+
+    /// Writing to the log directory failed
+    #[display(fmt = "{}", _0)]
+    Storage(io::Error),
+
+    /// The Raft actor is not running anymore
+    #[display(fmt = "Raft actor stopped")]
+    Stopped,
+}
+
+#[derive(Debug, Clone)]
+pub struct RaftConfig {
+    /// Address of this node in the cluster
+    pub own_addr: NodeAddr,
+    /// Addresses of all voting members, including this node
+    pub members: Vec<NodeAddr>,
+    /// Directory the log and the hard state are persisted in
+    pub log_dir: PathBuf,
+    pub election_timeout_min: Duration,
+    pub election_timeout_max: Duration,
+    pub heartbeat_interval: Duration,
+}
+
+impl RaftConfig {
+    pub fn new<A: Into<NodeAddr>, P: Into<PathBuf>>(
+        own_addr: A,
+        members: Vec<A>,
+        log_dir: P,
+    ) -> Self {
+        Self {
+            own_addr: own_addr.into(),
+            members: members.into_iter().map(Into::into).collect(),
+            log_dir: log_dir.into(),
+            election_timeout_min: Duration::from_millis(300),
+            election_timeout_max: Duration::from_millis(600),
+            heartbeat_interval: Duration::from_millis(100),
+        }
+    }
+
+    fn quorum(&self) -> usize {
+        let mut members: HashSet<NodeAddr> = self.members.iter().cloned().collect();
+        members.insert(self.own_addr.clone());
+        members.len() / 2 + 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RaftRole {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+type PendingProposal<S> = (
+    u64,
+    oneshot::Sender<Result<<S as StateMachine>::Output, RaftError>>,
+);
+
+/// Actor running one member of a Raft group.
+///
+/// Peers are learned from [ClusterLog](./enum.ClusterLog.html) events and from incoming Raft messages,
+/// therefore the actor should be started together with the [Cluster](./struct.Cluster.html).
+#[derive(RemoteActor)]
+#[remote_actor(id = "Raft")]
+#[remote_messages(RequestVote, RequestVoteResponse, AppendEntries, AppendEntriesResponse)]
+pub struct Raft<S: StateMachine> {
+    config: RaftConfig,
+    state_machine: S,
+    storage: RaftStorage,
+    role: RaftRole,
+    leader: Option<NodeAddr>,
+    commit_index: u64,
+    last_applied: u64,
+    peers: HashMap<NodeAddr, RemoteAddr>,
+    next_index: HashMap<NodeAddr, u64>,
+    match_index: HashMap<NodeAddr, u64>,
+    votes: HashSet<NodeAddr>,
+    election_timer: Option<SpawnHandle>,
+    pending: HashMap<u64, PendingProposal<S>>,
+    cluster: Option<ClusterHandle>,
+}
+
+impl<S: StateMachine> Raft<S> {
+    /// Opens (or creates) the log in `config.log_dir`.
+    pub fn new(config: RaftConfig, state_machine: S) -> io::Result<Self> {
+        let storage = RaftStorage::open(&config.log_dir)?;
+        Ok(Self {
+            config,
+            state_machine,
+            storage,
+            role: RaftRole::Follower,
+            leader: None,
+            commit_index: 0,
+            last_applied: 0,
+            peers: HashMap::new(),
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            votes: HashSet::new(),
+            election_timer: None,
+            pending: HashMap::new(),
+            cluster: None,
+        })
+    }
+
+    fn current_term(&self) -> u64 {
+        self.storage.hard_state().current_term
+    }
+
+    fn own_remote_addr(&self) -> RemoteAddr {
+        RemoteAddr::new_from_id(self.config.own_addr.clone(), Self::ACTOR_ID)
+    }
+
+    fn other_members(&self) -> Vec<NodeAddr> {
+        self.config
+            .members
+            .iter()
+            .filter(|member| **member != self.config.own_addr)
+            .cloned()
+            .collect()
+    }
+
+    fn learn_peer(&mut self, addr: &NodeAddr, source: RemoteAddr) {
+        if self.config.members.contains(addr) && source.node.network_interface.is_some() {
+            self.peers.insert(addr.clone(), source);
+        }
+    }
+
+    /// Persists term and vote before they take effect; on failure neither is adopted.
+    fn save_hard_state(
+        &mut self,
+        current_term: u64,
+        voted_for: Option<NodeAddr>,
+    ) -> io::Result<()> {
+        self.storage
+            .save_hard_state(HardState {
+                current_term,
+                voted_for,
+            })
+            .map_err(|err| {
+                error!("Raft could not persist its hard state: {}", err);
+                err
+            })
+    }
+
+    fn reset_election_timer(&mut self, ctx: &mut Context<Self>) {
+        if let Some(handle) = self.election_timer.take() {
+            ctx.cancel_future(handle);
+        }
+        let min = self.config.election_timeout_min.as_millis() as u64;
+        let max = (self.config.election_timeout_max.as_millis() as u64).max(min + 1);
+        let timeout = Duration::from_millis(rand::thread_rng().gen_range(min..max));
+        self.election_timer = Some(ctx.run_later(timeout, |act, ctx| {
+            act.election_timer = None;
+            act.start_election(ctx);
+        }));
+    }
+
+    /// Steps down in any case, but only follows `leader` in `term` if the term could be persisted.
+    fn become_follower(&mut self, term: u64, leader: Option<NodeAddr>) -> io::Result<()> {
+        let persisted = if term > self.current_term() {
+            self.save_hard_state(term, None)
+        } else {
+            Ok(())
+        };
+        if self.role == RaftRole::Leader {
+            for (_, (_, sender)) in self.pending.drain() {
+                let _ = sender.send(Err(RaftError::LeadershipLost));
+            }
+        }
+        self.role = RaftRole::Follower;
+        self.leader = if persisted.is_ok() { leader } else { None };
+        persisted
+    }
+
+    fn start_election(&mut self, ctx: &mut Context<Self>) {
+        if self.role == RaftRole::Leader {
+            return;
+        }
+
+        let term = self.current_term() + 1;
+        if self
+            .save_hard_state(term, Some(self.config.own_addr.clone()))
+            .is_err()
+        {
+            self.reset_election_timer(ctx);
+            return;
+        }
+        self.role = RaftRole::Candidate;
+        self.leader = None;
+        self.votes = HashSet::from([self.config.own_addr.clone()]);
+        debug!(target: &self.config.own_addr.to_string(), "Raft starts election for term {}", term);
+
+        let request = RequestVote {
+            term,
+            candidate: self.config.own_addr.clone(),
+            last_log_index: self.storage.last_index(),
+            last_log_term: self.storage.last_term(),
+            source: self.own_remote_addr(),
+        };
+        for member in self.other_members() {
+            if let Some(peer) = self.peers.get(&member) {
+                peer.do_send(request.clone());
+            }
+        }
+
+        self.reset_election_timer(ctx);
+        self.check_election_won(ctx);
+    }
+
+    fn check_election_won(&mut self, ctx: &mut Context<Self>) {
+        if self.role == RaftRole::Candidate && self.votes.len() >= self.config.quorum() {
+            self.become_leader(ctx);
+        }
+    }
+
+    fn become_leader(&mut self, ctx: &mut Context<Self>) {
+        debug!(target: &self.config.own_addr.to_string(), "Raft became leader for term {}", self.current_term());
+        if let Some(handle) = self.election_timer.take() {
+            ctx.cancel_future(handle);
+        }
+        self.role = RaftRole::Leader;
+        self.leader = Some(self.config.own_addr.clone());
+
+        let next_index = self.storage.last_index() + 1;
+        self.next_index.clear();
+        self.match_index.clear();
+        for member in self.other_members() {
+            self.next_index.insert(member.clone(), next_index);
+            self.match_index.insert(member, 0);
+        }
+
+        // entries of previous terms can only be committed with an entry of the current term
+        let noop = LogEntry {
+            term: self.current_term(),
+            command: None,
+        };
+        if let Err(err) = self.storage.append(&[noop]) {
+            // without an entry of its term, the leader could not commit anything
+            error!("Raft could not append to its log, stepping down: {}", err);
+            let _ = self.become_follower(self.current_term(), None);
+            self.reset_election_timer(ctx);
+            return;
+        }
+        self.broadcast_append_entries();
+        self.advance_commit_index();
+    }
+
+    fn broadcast_append_entries(&mut self) {
+        for member in self.other_members() {
+            self.send_append_entries(&member);
+        }
+    }
+
+    fn send_append_entries(&mut self, member: &NodeAddr) {
+        let peer = match self.peers.get(member) {
+            Some(peer) => peer,
+            None => return,
+        };
+        let next_index = *self
+            .next_index
+            .get(member)
+            .unwrap_or(&(self.storage.last_index() + 1));
+        let prev_log_index = next_index - 1;
+
+        peer.do_send(AppendEntries {
+            term: self.current_term(),
+            leader: self.config.own_addr.clone(),
+            prev_log_index,
+            prev_log_term: self.storage.term_at(prev_log_index).unwrap_or(0),
+            entries: self
+                .storage
+                .entries_from(next_index, MAX_ENTRIES_PER_MESSAGE),
+            leader_commit: self.commit_index,
+            source: self.own_remote_addr(),
+        });
+    }
+
+    fn advance_commit_index(&mut self) {
+        let mut match_indices: Vec<u64> = self.match_index.values().cloned().collect();
+        match_indices.push(self.storage.last_index());
+        match_indices.sort_unstable_by(|a, b| b.cmp(a));
+
+        let quorum_index = match_indices[self.config.quorum() - 1];
+        if quorum_index > self.commit_index
+            && self.storage.term_at(quorum_index) == Some(self.current_term())
+        {
+            self.commit_index = quorum_index;
+            self.apply_committed();
+        }
+    }
+
+    fn apply_committed(&mut self) {
+        while self.last_applied < self.commit_index {
+            let index = self.last_applied + 1;
+            let entry = match self.storage.entry(index) {
+                Some(entry) => entry.clone(),
+                None => break,
+            };
+            self.last_applied = index;
+
+            let serializer = DefaultSerialization {};
+            let result = match entry.command {
+                Some(command) => match serializer.deserialize::<S::Command>(&command) {
+                    Ok(command) => Ok(self.state_machine.apply(command)),
+                    Err(_) => {
+                        error!("Raft could not deserialize committed command {}", index);
+                        Err(RaftError::Serialization)
+                    }
+                },
+                None => continue,
+            };
+
+            if let Some((term, sender)) = self.pending.remove(&index) {
+                if term == entry.term {
+                    let _ = sender.send(result);
+                } else {
+                    let _ = sender.send(Err(RaftError::LeadershipLost));
+                }
+            }
+        }
+    }
+
+    fn is_up_to_date(&self, last_log_index: u64, last_log_term: u64) -> bool {
+        last_log_term > self.storage.last_term()
+            || (last_log_term == self.storage.last_term()
+                && last_log_index >= self.storage.last_index())
+    }
+
+    fn append_from_leader(&mut self, msg: &AppendEntries) -> io::Result<u64> {
+        let mut index = msg.prev_log_index;
+        for (offset, entry) in msg.entries.iter().enumerate() {
+            index += 1;
+            match self.storage.term_at(index) {
+                Some(term) if term == entry.term => continue,
+                Some(_) => {
+                    self.storage.truncate(index)?;
+                    self.storage.append(&msg.entries[offset..])?;
+                    break;
+                }
+                None => {
+                    self.storage.append(&msg.entries[offset..])?;
+                    break;
+                }
+            }
+        }
+        Ok(msg.prev_log_index + msg.entries.len() as u64)
+    }
+}
+
+impl<S: StateMachine> Actor for Raft<S> {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        match self.cluster.clone() {
+            Some(cluster) => {
+                self.register_with(ctx.address().recipient(), &cluster);
+                cluster.subscribe(ctx.address().recipient());
+            }
+            None => {
+                self.register(ctx.address().recipient());
+                self.subscribe_system_async::<ClusterLog>(ctx);
+            }
+        }
+        ctx.run_interval(self.config.heartbeat_interval, |act, _ctx| {
+            if act.role == RaftRole::Leader {
+                act.broadcast_append_entries();
+            }
+        });
+        self.reset_election_timer(ctx);
+        debug!("{} actor started", Self::ACTOR_ID);
+    }
+}
+
+impl<S: StateMachine> ClusterListener for Raft<S> {}
+
+impl<S: StateMachine> Handler<ClusterLog> for Raft<S> {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClusterLog, _ctx: &mut Self::Context) -> Self::Result {
+        match msg {
+            ClusterLog::NewMember(node) => {
+                if self.config.members.contains(&node.addr) {
+                    let remote_addr = node.get_remote_addr(Self::ACTOR_ID.to_string());
+                    self.peers.insert(node.addr, remote_addr);
+                }
+            }
+            ClusterLog::MemberLeft(addr) => {
+                self.peers.remove(&addr);
+            }
+        }
+    }
+}
+
+impl<S: StateMachine> Handler<RequestVote> for Raft<S> {
+    type Result = ();
+
+    // `Option::is_none_or` would require Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    fn handle(&mut self, msg: RequestVote, ctx: &mut Self::Context) -> Self::Result {
+        self.learn_peer(&msg.candidate, msg.source.clone());
+        if msg.term > self.current_term() {
+            // without the new term, the term check below rejects the vote
+            let _ = self.become_follower(msg.term, None);
+        }
+
+        let voted_for = self.storage.hard_state().voted_for.clone();
+        let vote_granted = msg.term == self.current_term()
+            && voted_for.map_or(true, |voted_for| voted_for == msg.candidate)
+            && self.is_up_to_date(msg.last_log_index, msg.last_log_term)
+            && self
+                .save_hard_state(msg.term, Some(msg.candidate.clone()))
+                .is_ok();
+
+        if vote_granted {
+            self.reset_election_timer(ctx);
+        }
+
+        if let Some(peer) = self.peers.get(&msg.candidate) {
+            peer.do_send(RequestVoteResponse {
+                term: self.current_term(),
+                from: self.config.own_addr.clone(),
+                vote_granted,
+                source: self.own_remote_addr(),
+            });
+        }
+    }
+}
+
+impl<S: StateMachine> Handler<RequestVoteResponse> for Raft<S> {
+    type Result = ();
+
+    fn handle(&mut self, msg: RequestVoteResponse, ctx: &mut Self::Context) -> Self::Result {
+        self.learn_peer(&msg.from, msg.source.clone());
+        if msg.term > self.current_term() {
+            let _ = self.become_follower(msg.term, None);
+            self.reset_election_timer(ctx);
+            return;
+        }
+
+        if msg.vote_granted
+            && msg.term == self.current_term()
+            && self.config.members.contains(&msg.from)
+        {
+            self.votes.insert(msg.from);
+            self.check_election_won(ctx);
+        }
+    }
+}
+
+impl<S: StateMachine> Handler<AppendEntries> for Raft<S> {
+    type Result = ();
+
+    fn handle(&mut self, msg: AppendEntries, ctx: &mut Self::Context) -> Self::Result {
+        self.learn_peer(&msg.leader, msg.source.clone());
+
+        let success = if msg.term < self.current_term()
+            || self
+                .become_follower(msg.term, Some(msg.leader.clone()))
+                .is_err()
+        {
+            false
+        } else {
+            self.reset_election_timer(ctx);
+            self.storage.term_at(msg.prev_log_index) == Some(msg.prev_log_term)
+        };
+
+        let match_index = if success {
+            match self.append_from_leader(&msg) {
+                Ok(match_index) => {
+                    let commit_index = msg.leader_commit.min(match_index);
+                    if commit_index > self.commit_index {
+                        self.commit_index = commit_index;
+                        self.apply_committed();
+                    }
+                    Some(match_index)
+                }
+                Err(err) => {
+                    error!("Raft could not append to its log: {}", err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(peer) = self.peers.get(&msg.leader) {
+            peer.do_send(AppendEntriesResponse {
+                term: self.current_term(),
+                from: self.config.own_addr.clone(),
+                success: match_index.is_some(),
+                match_index: match_index.unwrap_or_else(|| self.storage.last_index()),
+                source: self.own_remote_addr(),
+            });
+        }
+    }
+}
+
+impl<S: StateMachine> Handler<AppendEntriesResponse> for Raft<S> {
+    type Result = ();
+
+    fn handle(&mut self, msg: AppendEntriesResponse, ctx: &mut Self::Context) -> Self::Result {
+        self.learn_peer(&msg.from, msg.source.clone());
+        if msg.term > self.current_term() {
+            let _ = self.become_follower(msg.term, None);
+            self.reset_election_timer(ctx);
+            return;
+        }
+        if self.role != RaftRole::Leader || msg.term != self.current_term() {
+            return;
+        }
+
+        if msg.success {
+            let match_index = self.match_index.entry(msg.from.clone()).or_insert(0);
+            *match_index = (*match_index).max(msg.match_index);
+            let next_index = *match_index + 1;
+            self.next_index.insert(msg.from.clone(), next_index);
+            self.advance_commit_index();
+
+            if next_index <= self.storage.last_index() {
+                self.send_append_entries(&msg.from);
+            }
+        } else {
+            let next_index = self.next_index.entry(msg.from.clone()).or_insert(1);
+            *next_index = (*next_index - 1).min(msg.match_index + 1).max(1);
+            self.send_append_entries(&msg.from);
+        }
+    }
+}
+
+impl<S: StateMachine> Handler<Propose<S>> for Raft<S> {
+    type Result = ResponseFuture<Result<S::Output, RaftError>>;
+
+    fn handle(&mut self, msg: Propose<S>, _ctx: &mut Self::Context) -> Self::Result {
+        if self.role != RaftRole::Leader {
+            let leader = self.leader.clone();
+            return Box::pin(async move { Err(RaftError::NotLeader(leader)) });
+        }
+
+        let serializer = DefaultSerialization {};
+        let command = match serializer.serialize(&msg.command) {
+            Ok(command) => command,
+            Err(_) => return Box::pin(async { Err(RaftError::Serialization) }),
+        };
+        let term = self.current_term();
+        if let Err(err) = self.storage.append(&[LogEntry {
+            term,
+            command: Some(command),
+        }]) {
+            return Box::pin(async move { Err(RaftError::Storage(err)) });
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .insert(self.storage.last_index(), (term, sender));
+        self.broadcast_append_entries();
+        self.advance_commit_index();
+
+        Box::pin(async move { receiver.await.unwrap_or(Err(RaftError::Stopped)) })
+    }
+}
+
+/// Handle to a started [Raft](./struct.Raft.html) actor.
+pub struct RaftHandle<S: StateMachine> {
+    addr: Addr<Raft<S>>,
+}
+
+impl<S: StateMachine> RaftHandle<S> {
+    /// Opens the log in `config.log_dir` and starts the Raft actor.
+    pub fn start(config: RaftConfig, state_machine: S) -> io::Result<Self> {
+        Ok(Self {
+            addr: Raft::new(config, state_machine)?.start(),
+        })
+    }
+
+    /// Like [start](#method.start), but the actor is a member of the node of `cluster`.
+    pub fn start_with(
+        cluster: &ClusterHandle,
+        config: RaftConfig,
+        state_machine: S,
+    ) -> io::Result<Self> {
+        let mut raft = Raft::new(config, state_machine)?;
+        raft.cluster = Some(cluster.clone());
+        Ok(Self { addr: raft.start() })
+    }
+
+    pub fn addr(&self) -> &Addr<Raft<S>> {
+        &self.addr
+    }
+
+    /// Replicates `command` and resolves with the output of the state machine once the command is
+    /// committed. Fails with `RaftError::NotLeader` if this node is not the leader.
+    pub fn propose(
+        &self,
+        command: S::Command,
+    ) -> impl Future<Output = Result<S::Output, RaftError>> {
+        let request = self.addr.send(Propose::new(command));
+        async move { request.await.unwrap_or(Err(RaftError::Stopped)) }
+    }
+}
+
+impl<S: StateMachine> Clone for RaftHandle<S> {
+    fn clone(&self) -> Self {
+        Self {
+            addr: self.addr.clone(),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::NodeAddr;
+use byteorder::{ByteOrder, NetworkEndian};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const LOG_FILE: &str = "raft.log";
+const STATE_FILE: &str = "raft.state";
+const STATE_TMP_FILE: &str = "raft.state.tmp";
+const LENGTH_SIZE: usize = 4;
+
+/// A single entry of the replicated log. `command` is `None` for the no-op entry
+/// a leader appends when it takes over a term.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub term: u64,
+    pub command: Option<Vec<u8>>,
+}
+
+/// State that has to survive restarts before a node answers any RPC.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct HardState {
+    pub current_term: u64,
+    pub voted_for: Option<NodeAddr>,
+}
+
+/// Persists the Raft log and the hard state to a local directory.
+///
+/// The log is an append-only file of length-prefixed flexbuffers records. A torn record at the end
+/// of the file (e.g. after a crash during a write) is cut off when the storage is opened.
+pub(crate) struct RaftStorage {
+    dir: PathBuf,
+    log_file: File,
+    entries: Vec<LogEntry>,
+    offsets: Vec<u64>,
+    end_offset: u64,
+    hard_state: HardState,
+}
+
+impl RaftStorage {
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let hard_state = match fs::read(dir.join(STATE_FILE)) {
+            Ok(bytes) => flexbuffers::from_slice(&bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HardState::default(),
+            Err(err) => return Err(err),
+        };
+
+        let mut log_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOG_FILE))?;
+        let mut buffer = vec![];
+        log_file.read_to_end(&mut buffer)?;
+
+        let mut entries = vec![];
+        let mut offsets = vec![];
+        let mut position = 0;
+        while position + LENGTH_SIZE <= buffer.len() {
+            let size = NetworkEndian::read_u32(&buffer[position..]) as usize;
+            let start = position + LENGTH_SIZE;
+            if start + size > buffer.len() {
+                break;
+            }
+            match flexbuffers::from_slice::<LogEntry>(&buffer[start..start + size]) {
+                Ok(entry) => {
+                    offsets.push(position as u64);
+                    entries.push(entry);
+                    position = start + size;
+                }
+                Err(_) => break,
+            }
+        }
+
+        let end_offset = position as u64;
+        if end_offset < buffer.len() as u64 {
+            log_file.set_len(end_offset)?;
+            log_file.sync_data()?;
+        }
+        log_file.seek(SeekFrom::Start(end_offset))?;
+
+        Ok(Self {
+            dir,
+            log_file,
+            entries,
+            offsets,
+            end_offset,
+            hard_state,
+        })
+    }
+
+    pub fn hard_state(&self) -> &HardState {
+        &self.hard_state
+    }
+
+    pub fn save_hard_state(&mut self, hard_state: HardState) -> io::Result<()> {
+        let bytes = flexbuffers::to_vec(&hard_state)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let tmp_path = self.dir.join(STATE_TMP_FILE);
+        let mut tmp_file = File::create(&tmp_path)?;
+        tmp_file.write_all(&bytes)?;
+        tmp_file.sync_all()?;
+        fs::rename(tmp_path, self.dir.join(STATE_FILE))?;
+        self.hard_state = hard_state;
+        Ok(())
+    }
+
+    /// Index of the last entry, 0 if the log is empty. Log indices start at 1.
+    pub fn last_index(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    pub fn last_term(&self) -> u64 {
+        self.entries.last().map(|entry| entry.term).unwrap_or(0)
+    }
+
+    /// Term of the entry at `index`; index 0 always has term 0.
+    pub fn term_at(&self, index: u64) -> Option<u64> {
+        if index == 0 {
+            Some(0)
+        } else {
+            self.entry(index).map(|entry| entry.term)
+        }
+    }
+
+    pub fn entry(&self, index: u64) -> Option<&LogEntry> {
+        if index == 0 {
+            None
+        } else {
+            self.entries.get((index - 1) as usize)
+        }
+    }
+
+    /// Up to `max` entries beginning at `index`.
+    pub fn entries_from(&self, index: u64, max: usize) -> Vec<LogEntry> {
+        let start = (index.max(1) - 1) as usize;
+        self.entries.iter().skip(start).take(max).cloned().collect()
+    }
+
+    pub fn append(&mut self, entries: &[LogEntry]) -> io::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffer = vec![];
+        let mut offsets = vec![];
+        for entry in entries {
+            let bytes = flexbuffers::to_vec(entry)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            offsets.push(self.end_offset + buffer.len() as u64);
+            let mut length = [0; LENGTH_SIZE];
+            NetworkEndian::write_u32(&mut length, bytes.len() as u32);
+            buffer.extend_from_slice(&length);
+            buffer.extend_from_slice(&bytes);
+        }
+
+        if let Err(err) = self
+            .log_file
+            .write_all(&buffer)
+            .and_then(|_| self.log_file.sync_data())
+        {
+            // torn bytes would end the log on reopen and drop the entries appended after them
+            self.log_file.set_len(self.end_offset)?;
+            self.log_file.seek(SeekFrom::Start(self.end_offset))?;
+            return Err(err);
+        }
+        self.end_offset += buffer.len() as u64;
+        self.offsets.extend(offsets);
+        self.entries.extend_from_slice(entries);
+        Ok(())
+    }
+
+    /// Removes the entry at `index` and all entries after it.
+    pub fn truncate(&mut self, index: u64) -> io::Result<()> {
+        if index == 0 || index > self.last_index() {
+            return Ok(());
+        }
+
+        let position = (index - 1) as usize;
+        let offset = self.offsets[position];
+        self.log_file.set_len(offset)?;
+        self.log_file.sync_data()?;
+        self.log_file.seek(SeekFrom::Start(offset))?;
+        self.end_offset = offset;
+        self.offsets.truncate(position);
+        self.entries.truncate(position);
+        Ok(())
+    }
+}
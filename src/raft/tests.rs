@@ -0,0 +1,294 @@
+use crate::raft::storage::{HardState, LogEntry, RaftStorage};
+use crate::raft::RaftRole;
+use crate::testkit::TestCluster;
+use crate::{ClusterLog, MemoryNetwork, NodeAddr, RemoteAddr};
+use crate::{
+    Raft, RaftConfig, RaftError, RaftHandle, RequestVote, RequestVoteResponse, StateMachine,
+};
+use actix::{Context, Handler, System};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::time::{sleep, Duration, Instant};
+use uuid::Uuid;
+
+#[derive(Default)]
+struct Counter {
+    value: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+enum CounterCommand {
+    Add(i64),
+    Get,
+}
+
+impl StateMachine for Counter {
+    type Command = CounterCommand;
+    type Output = i64;
+
+    fn apply(&mut self, command: Self::Command) -> Self::Output {
+        if let CounterCommand::Add(value) = command {
+            self.value += value;
+        }
+        self.value
+    }
+}
+
+fn temp_log_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("telepathy-raft-{}", Uuid::new_v4()))
+}
+
+fn entry(term: u64, command: u8) -> LogEntry {
+    LogEntry {
+        term,
+        command: Some(vec![command]),
+    }
+}
+
+async fn propose_until_leader(
+    raft: &RaftHandle<Counter>,
+    command: fn() -> CounterCommand,
+) -> Result<i64, RaftError> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match raft.propose(command()).await {
+            Err(RaftError::NotLeader(_)) if Instant::now() < deadline => {
+                sleep(Duration::from_millis(50)).await
+            }
+            result => return result,
+        }
+    }
+}
+
+#[test]
+fn storage_persists_entries_and_hard_state() {
+    let dir = temp_log_dir();
+    let hard_state = HardState {
+        current_term: 3,
+        voted_for: Some("127.0.0.1:1992".parse().unwrap()),
+    };
+
+    {
+        let mut storage = RaftStorage::open(&dir).unwrap();
+        storage
+            .append(&[entry(1, 1), entry(1, 2), entry(3, 3)])
+            .unwrap();
+        storage.save_hard_state(hard_state.clone()).unwrap();
+    }
+
+    let storage = RaftStorage::open(&dir).unwrap();
+    assert_eq!(storage.hard_state(), &hard_state);
+    assert_eq!(storage.last_index(), 3);
+    assert_eq!(storage.last_term(), 3);
+    assert_eq!(storage.entry(2), Some(&entry(1, 2)));
+    assert_eq!(storage.entries_from(2, 10), vec![entry(1, 2), entry(3, 3)]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn storage_truncates_conflicting_entries() {
+    let dir = temp_log_dir();
+
+    {
+        let mut storage = RaftStorage::open(&dir).unwrap();
+        storage
+            .append(&[entry(1, 1), entry(1, 2), entry(1, 3)])
+            .unwrap();
+        storage.truncate(2).unwrap();
+        storage.append(&[entry(2, 4)]).unwrap();
+    }
+
+    let storage = RaftStorage::open(&dir).unwrap();
+    assert_eq!(storage.entries_from(1, 10), vec![entry(1, 1), entry(2, 4)]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn storage_drops_torn_record() {
+    let dir = temp_log_dir();
+
+    {
+        let mut storage = RaftStorage::open(&dir).unwrap();
+        storage.append(&[entry(1, 1), entry(1, 2)]).unwrap();
+    }
+    let log_path = dir.join("raft.log");
+    let mut bytes = std::fs::read(&log_path).unwrap();
+    bytes.truncate(bytes.len() - 2);
+    std::fs::write(&log_path, bytes).unwrap();
+
+    let mut storage = RaftStorage::open(&dir).unwrap();
+    assert_eq!(storage.entries_from(1, 10), vec![entry(1, 1)]);
+    storage.append(&[entry(2, 3)]).unwrap();
+
+    let storage = RaftStorage::open(&dir).unwrap();
+    assert_eq!(storage.entries_from(1, 10), vec![entry(1, 1), entry(2, 3)]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[actix_rt::test]
+async fn single_member_commits_proposals() {
+    let dir = temp_log_dir();
+    let own_addr: SocketAddr = "127.0.0.1:1992".parse().unwrap();
+    let raft = RaftHandle::start(
+        RaftConfig::new(own_addr, vec![own_addr], &dir),
+        Counter::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        propose_until_leader(&raft, || CounterCommand::Add(2))
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(raft.propose(CounterCommand::Add(5)).await.unwrap(), 7);
+    assert_eq!(raft.propose(CounterCommand::Get).await.unwrap(), 7);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn single_member_replays_log_after_restart() {
+    let dir = temp_log_dir();
+    let own_addr: SocketAddr = "127.0.0.1:1992".parse().unwrap();
+
+    for expected in [5, 10] {
+        let config = RaftConfig::new(own_addr, vec![own_addr], &dir);
+        let value = System::new().block_on(async move {
+            let raft = RaftHandle::start(config, Counter::default()).unwrap();
+            propose_until_leader(&raft, || CounterCommand::Add(5))
+                .await
+                .unwrap()
+        });
+        assert_eq!(value, expected);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[actix_rt::test]
+async fn members_replicate_and_agree_on_leader() {
+    let cluster = TestCluster::builder(3)
+        .in_memory(MemoryNetwork::new())
+        .start()
+        .await;
+    let dirs: Vec<PathBuf> = (0..3).map(|_| temp_log_dir()).collect();
+    let rafts: Vec<RaftHandle<Counter>> = (0..3)
+        .map(|i| {
+            let config = RaftConfig::new(cluster.addr(i), cluster.addrs().to_vec(), &dirs[i]);
+            RaftHandle::start_with(cluster.handle(i), config, Counter::default()).unwrap()
+        })
+        .collect();
+    for (i, raft) in rafts.iter().enumerate() {
+        for j in (0..3).filter(|j| *j != i) {
+            raft.addr()
+                .do_send(ClusterLog::NewMember(cluster.node(i, j)));
+        }
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let leader = loop {
+        let mut leader = None;
+        for (i, raft) in rafts.iter().enumerate() {
+            if let Ok(value) = raft.propose(CounterCommand::Add(1)).await {
+                assert_eq!(value, 1);
+                leader = Some(i);
+                break;
+            }
+        }
+        match leader {
+            Some(leader) => break leader,
+            None if Instant::now() < deadline => sleep(Duration::from_millis(50)).await,
+            None => panic!("no leader was elected"),
+        }
+    };
+
+    assert_eq!(
+        rafts[leader].propose(CounterCommand::Add(1)).await.unwrap(),
+        2
+    );
+    assert_eq!(
+        rafts[leader].propose(CounterCommand::Add(1)).await.unwrap(),
+        3
+    );
+    for (i, raft) in rafts.iter().enumerate().filter(|(i, _)| *i != leader) {
+        match raft.propose(CounterCommand::Get).await {
+            Err(RaftError::NotLeader(Some(addr))) => assert_eq!(addr, cluster.addr(leader)),
+            result => panic!(
+                "member {} did not redirect to the leader: {:?}",
+                i,
+                result.err()
+            ),
+        }
+    }
+    for dir in dirs {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[actix_rt::test]
+async fn vote_is_only_granted_if_it_is_persisted() {
+    let own_addr: NodeAddr = "127.0.0.1:1992".parse::<SocketAddr>().unwrap().into();
+    let candidate: NodeAddr = "127.0.0.1:1993".parse::<SocketAddr>().unwrap().into();
+    let request_vote = |term| RequestVote {
+        term,
+        candidate: candidate.clone(),
+        last_log_index: 0,
+        last_log_term: 0,
+        source: RemoteAddr::new_from_id(candidate.clone(), "Raft"),
+    };
+    let dir = temp_log_dir();
+    let config = RaftConfig::new(own_addr.clone(), vec![own_addr, candidate.clone()], &dir);
+    let mut raft = Raft::new(config, Counter::default()).unwrap();
+    let mut ctx = Context::new();
+
+    raft.handle(request_vote(1), &mut ctx);
+    assert_eq!(raft.current_term(), 1);
+    assert_eq!(raft.storage.hard_state().voted_for, Some(candidate.clone()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    raft.handle(request_vote(2), &mut ctx);
+    assert_eq!(raft.current_term(), 1);
+    assert_eq!(raft.storage.hard_state().voted_for, Some(candidate));
+}
+
+#[actix_rt::test]
+async fn votes_of_non_members_are_ignored() {
+    let addr = |port: u16| -> NodeAddr { SocketAddr::from(([127, 0, 0, 1], port)).into() };
+    let members = vec![addr(1992), addr(1993), addr(1994)];
+    let dir = temp_log_dir();
+    let config = RaftConfig::new(members[0].clone(), members.clone(), &dir);
+    let mut raft = Raft::new(config, Counter::default()).unwrap();
+    let mut ctx = Context::new();
+    raft.start_election(&mut ctx);
+    let vote = |from: NodeAddr| RequestVoteResponse {
+        term: 1,
+        from: from.clone(),
+        vote_granted: true,
+        source: RemoteAddr::new_from_id(from, "Raft"),
+    };
+
+    raft.handle(vote(addr(1995)), &mut ctx);
+    assert_eq!(raft.role, RaftRole::Candidate);
+    raft.handle(vote(members[1].clone()), &mut ctx);
+    assert_eq!(raft.role, RaftRole::Leader);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[actix_rt::test]
+async fn missing_committed_entries_are_not_marked_applied() {
+    let own_addr: NodeAddr = "127.0.0.1:1992".parse::<SocketAddr>().unwrap().into();
+    let dir = temp_log_dir();
+    let config = RaftConfig::new(own_addr.clone(), vec![own_addr], &dir);
+    let mut raft = Raft::new(config, Counter::default()).unwrap();
+    raft.storage
+        .append(&[LogEntry {
+            term: 1,
+            command: None,
+        }])
+        .unwrap();
+
+    raft.commit_index = 2;
+    raft.apply_committed();
+    assert_eq!(raft.last_applied, 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,4 +1,4 @@
-use crate::{AddrRequest, AddrResolver, RemoteWrapper};
+use crate::{AddrRequest, AddrResolver, ClusterHandle, RemoteWrapper};
 use actix::{Actor, Handler, Recipient, SystemService};
 
 pub trait RemoteActor
@@ -11,4 +11,24 @@ where
         AddrResolver::from_registry()
             .do_send(AddrRequest::Register(rec, Self::ACTOR_ID.to_string()));
     }
+
+    /// Removes the actor from the [AddrResolver](./struct.AddrResolver.html); remote watchers receive
+    /// [Terminated](./struct.Terminated.html).
+    fn deregister(&mut self, rec: Recipient<RemoteWrapper>) {
+        AddrResolver::from_registry().do_send(AddrRequest::Deregister(rec));
+    }
+
+    /// Registers the actor at the [AddrResolver](./struct.AddrResolver.html) of `cluster`, so it
+    /// receives messages that arrive at that cluster node.
+    fn register_with(&mut self, rec: Recipient<RemoteWrapper>, cluster: &ClusterHandle) {
+        cluster
+            .addr_resolver()
+            .do_send(AddrRequest::Register(rec, Self::ACTOR_ID.to_string()));
+    }
+
+    fn deregister_with(&mut self, rec: Recipient<RemoteWrapper>, cluster: &ClusterHandle) {
+        cluster
+            .addr_resolver()
+            .do_send(AddrRequest::Deregister(rec));
+    }
 }
@@ -1,21 +1,37 @@
 use std::hash::{Hash, Hasher};
-use std::net::SocketAddr;
 use std::str::FromStr;
 
 use actix::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::codec::ClusterMessage;
-use crate::remote::{AddrRepresentation, RemoteMessage, RemoteWrapper};
-use crate::{NetworkInterface, WrappedClusterMessage};
+use crate::network::Queued;
+use crate::remote::{
+    AddrRepresentation, RemoteMessage, RemoteReply, RemoteWrapper, Terminated, Unwatch, Watch,
+};
+use crate::telemetry::Queue;
+use crate::{
+    ClusterHandle, CustomSerialization, NetworkInterface, ReliableClusterMessage,
+    WrappedClusterMessage,
+};
 use actix::dev::ToEnvelope;
+use futures::channel::oneshot;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::time::Duration;
+use uuid::Uuid;
 
-pub use self::node::Node;
+use self::reply::AwaitReply;
+pub use self::reply::{RemoteSendError, ReplyTo, DEFAULT_REPLY_TIMEOUT};
+
+pub use self::node::{Node, NodeAddr};
 
 pub mod node;
+pub mod reply;
 pub mod resolver;
 #[cfg(test)]
 mod tests;
+pub mod watch;
 
 /// Similar to actix::prelude::Addr but supports communication to remote actors on other nodes.
 #[derive(Deserialize, Serialize, Debug)]
@@ -29,30 +45,30 @@ impl RemoteAddr {
         RemoteAddr { node, id }
     }
 
-    pub fn new_from_id(socket_addr: SocketAddr, id: &str) -> Self {
+    pub fn new_from_id<A: Into<NodeAddr>>(addr: A, id: &str) -> Self {
         RemoteAddr {
-            node: Node::new(socket_addr, None),
+            node: Node::new(addr, None),
             id: AddrRepresentation::from_str(id).unwrap(),
         }
     }
 
-    pub fn new_from_key(
-        socket_addr: SocketAddr,
+    pub fn new_from_key<A: Into<NodeAddr>>(
+        addr: A,
         network_interface: Addr<NetworkInterface>,
         id: &str,
     ) -> Self {
         RemoteAddr {
-            node: Node::new(socket_addr, Some(network_interface)),
+            node: Node::new(addr, Some(network_interface)),
             id: AddrRepresentation::from_str(id).unwrap(),
         }
     }
 
-    pub fn new_connector(
-        socket_addr: SocketAddr,
+    pub fn new_connector<A: Into<NodeAddr>>(
+        addr: A,
         network_interface: Option<Addr<NetworkInterface>>,
     ) -> Self {
         RemoteAddr::new(
-            Node::new(socket_addr, network_interface),
+            Node::new(addr, network_interface),
             AddrRepresentation::Connector,
         )
     }
@@ -70,11 +86,56 @@ impl RemoteAddr {
             .network_interface
             .as_ref()
             .expect("Network interface must be set!")
-            .do_send(ClusterMessage::Message(RemoteWrapper::new(
-                self.clone(),
-                msg,
-                None,
-            )));
+            .do_send(Queued::new(
+                Queue::NetworkInterface,
+                ClusterMessage::Message(RemoteWrapper::new(self.clone(), msg, None)),
+            ));
+    }
+
+    /// Sends the message with at-least-once delivery: the message is retained until the receiving
+    /// node acknowledges it and is redelivered after a reconnect. The receiving node discards
+    /// duplicates, so the handler sees the message once.
+    pub fn do_send_reliable<T: RemoteMessage + Serialize>(&self, msg: T) {
+        let msg = ReliableClusterMessage(RemoteWrapper::new(self.clone(), msg, None));
+        match &self.node.network_interface {
+            Some(network_interface) if network_interface.connected() => {
+                network_interface.do_send(Queued::new(Queue::NetworkInterface, msg))
+            }
+            _ => match &self.node.cluster {
+                Some(cluster) => cluster.cluster().do_send(msg),
+                None => ClusterHandle::current().cluster().do_send(msg),
+            },
+        }
+    }
+
+    /// Delivers [Terminated](./struct.Terminated.html) to `watcher` once the remote actor stops,
+    /// deregisters or its node goes down.
+    pub fn watch(&self, watcher: Recipient<Terminated>) {
+        match &self.node.network_interface {
+            Some(network_interface) if network_interface.connected() => {
+                network_interface.do_send(Queued::new(
+                    Queue::NetworkInterface,
+                    Watch {
+                        watched: self.clone(),
+                        watcher,
+                    },
+                ))
+            }
+            _ => watcher.do_send(Terminated(self.clone())),
+        }
+    }
+
+    /// Stops delivering [Terminated](./struct.Terminated.html) to `watcher`, see [watch](#method.watch).
+    pub fn unwatch(&self, watcher: Recipient<Terminated>) {
+        if let Some(network_interface) = &self.node.network_interface {
+            network_interface.do_send(Queued::new(
+                Queue::NetworkInterface,
+                Unwatch {
+                    watched: self.clone(),
+                    watcher,
+                },
+            ))
+        }
     }
 
     pub fn try_send<T: RemoteMessage + Serialize>(
@@ -84,10 +145,59 @@ impl RemoteAddr {
         unimplemented!("So far, it is not possible to use this method!")
     }
 
-    pub fn send<T: RemoteMessage + Serialize>(&self, _msg: T) {
-        unimplemented!(
-            "So far, it is not possible to receive responses from remote destinations as futures!"
-        )
+    /// Sends the message and waits for the result of the remote handler, which needs
+    /// `#[remote_message(reply)]` to reply.
+    /// The result is serialized with the message's serializer; it fails after [DEFAULT_REPLY_TIMEOUT](./constant.DEFAULT_REPLY_TIMEOUT.html).
+    pub fn send<T>(&self, msg: T) -> impl Future<Output = Result<T::Result, RemoteSendError>>
+    where
+        T: RemoteReply,
+        T::Result: DeserializeOwned,
+    {
+        self.send_timeout(msg, DEFAULT_REPLY_TIMEOUT)
+    }
+
+    pub fn send_timeout<T>(
+        &self,
+        msg: T,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<T::Result, RemoteSendError>>
+    where
+        T: RemoteReply,
+        T::Result: DeserializeOwned,
+    {
+        let conversation_id = Uuid::new_v4();
+        let connection = match &self.node.network_interface {
+            Some(network_interface) if network_interface.connected() => {
+                let resolver = match &self.node.cluster {
+                    Some(cluster) => cluster.addr_resolver(),
+                    None => ClusterHandle::current().addr_resolver(),
+                };
+                Ok((network_interface.clone(), resolver))
+            }
+            _ => Err(RemoteSendError::NotConnected),
+        };
+        let wrapper = RemoteWrapper::new(self.clone(), msg, Some(conversation_id));
+        async move {
+            let (network_interface, resolver) = connection?;
+            let (sender, receiver) = oneshot::channel();
+            resolver
+                .send(AwaitReply {
+                    conversation_id,
+                    sender,
+                })
+                .await
+                .map_err(|_| RemoteSendError::NotConnected)?;
+            network_interface.do_send(Queued::new(
+                Queue::NetworkInterface,
+                ClusterMessage::Message(wrapper),
+            ));
+            match tokio::time::timeout(timeout, receiver).await {
+                Ok(Ok(result)) => T::generate_serializer()
+                    .deserialize(&result)
+                    .map_err(|_| RemoteSendError::Deserialization),
+                _ => Err(RemoteSendError::Timeout),
+            }
+        }
     }
 
     pub fn wait_send<T: RemoteMessage + Serialize>(
@@ -98,8 +208,9 @@ impl RemoteAddr {
             .network_interface
             .as_ref()
             .expect("Network interface must be set!")
-            .send(WrappedClusterMessage(ClusterMessage::Message(
-                RemoteWrapper::new(self.clone(), msg, None),
+            .send(WrappedClusterMessage(Queued::new(
+                Queue::NetworkInterface,
+                ClusterMessage::Message(RemoteWrapper::new(self.clone(), msg, None)),
             )))
     }
 }
@@ -1,27 +1,181 @@
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
-use std::net::SocketAddr;
+use std::net::{AddrParseError, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use actix::Addr;
 use serde::{Deserialize, Serialize};
 
-use crate::{AddrRepresentation, NetworkInterface, RemoteAddr};
+use crate::{AddrRepresentation, ClusterHandle, NetworkInterface, RemoteAddr};
+
+const UNIX_PREFIX: &str = "unix:";
+
+/// Address a node listens on and is identified by in the cluster.
+///
+/// Nodes on the same host can listen on a Unix domain socket instead of a TCP port.
+/// As string, Unix socket paths are prefixed with `unix:`, e.g. `unix:/tmp/node.sock`.
+///
+/// Seed nodes can also be given by host name and port, e.g. `seed:1992`. The name is resolved on each
+/// attempt to connect and the node is identified by the address it advertises once connected.
+///
+/// IPv6 addresses are written in brackets, e.g. `[::1]:1992`, and may carry a numeric scope id, e.g. `[fe80::1%2]:1992`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeAddr {
+    Tcp(#[serde(with = "socket_addr")] SocketAddr),
+    Unix(PathBuf),
+    /// (host name, port)
+    Host(String, u16),
+}
+
+impl NodeAddr {
+    pub fn unix<P: Into<PathBuf>>(path: P) -> Self {
+        NodeAddr::Unix(path.into())
+    }
+
+    pub fn host<H: Into<String>>(host: H, port: u16) -> Self {
+        NodeAddr::Host(host.into(), port)
+    }
+
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self {
+            NodeAddr::Tcp(addr) => Some(*addr),
+            _ => None,
+        }
+    }
+
+    pub fn unix_path(&self) -> Option<&Path> {
+        match self {
+            NodeAddr::Unix(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Whether the address is a host name that still needs to be resolved.
+    pub fn is_host(&self) -> bool {
+        matches!(self, NodeAddr::Host(_, _))
+    }
+
+    /// Address a peer that connected from `self` is identified by, given the address it advertised in the handshake.
+    /// The advertised address is trusted, unless the peer is bound to an unspecified IP like `0.0.0.0` without
+    /// advertising another address; then only its port is taken over.
+    pub(crate) fn listening_addr(&self, advertised: NodeAddr) -> NodeAddr {
+        match (self, advertised) {
+            (NodeAddr::Tcp(source), NodeAddr::Tcp(advertised))
+                if advertised.ip().is_unspecified() =>
+            {
+                // keeps the scope id of a link-local IPv6 source
+                let mut addr = *source;
+                addr.set_port(advertised.port());
+                NodeAddr::Tcp(addr)
+            }
+            (_, advertised) => advertised,
+        }
+    }
+}
+
+/// Socket addresses are serialized as strings, since the compact form of serde drops the scope id of IPv6 addresses.
+mod socket_addr {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::net::SocketAddr;
+
+    pub fn serialize<S: Serializer>(addr: &SocketAddr, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(addr)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SocketAddr, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl Display for NodeAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeAddr::Tcp(addr) => write!(f, "{}", addr),
+            NodeAddr::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
+            NodeAddr::Host(host, port) => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+impl FromStr for NodeAddr {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(UNIX_PREFIX) {
+            Some(path) => Ok(NodeAddr::unix(path)),
+            None => s.parse().map(NodeAddr::Tcp).or_else(|err| {
+                match s.rsplit_once(':').map(|(host, port)| (host, port.parse())) {
+                    Some((host, Ok(port))) if is_host_name(host) => Ok(NodeAddr::host(host, port)),
+                    _ => Err(err),
+                }
+            }),
+        }
+    }
+}
+
+/// Letters, digits, hyphens, underscores and dots with at least one letter, so that malformed IP addresses are rejected.
+fn is_host_name(host: &str) -> bool {
+    host.chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && host.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+impl From<SocketAddr> for NodeAddr {
+    fn from(addr: SocketAddr) -> Self {
+        NodeAddr::Tcp(addr)
+    }
+}
+
+impl From<PathBuf> for NodeAddr {
+    fn from(path: PathBuf) -> Self {
+        NodeAddr::Unix(path)
+    }
+}
+
+impl From<&Path> for NodeAddr {
+    fn from(path: &Path) -> Self {
+        NodeAddr::unix(path)
+    }
+}
+
+impl PartialEq<SocketAddr> for NodeAddr {
+    fn eq(&self, other: &SocketAddr) -> bool {
+        self.socket_addr().as_ref() == Some(other)
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Node {
-    pub socket_addr: SocketAddr,
+    pub addr: NodeAddr,
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
     pub network_interface: Option<Addr<NetworkInterface>>,
+    /// Cluster the node is connected to, if it is not the one of the current actix system
+    #[serde(skip)]
+    pub(crate) cluster: Option<ClusterHandle>,
 }
 
 impl Node {
-    pub fn new(socket_addr: SocketAddr, network_interface: Option<Addr<NetworkInterface>>) -> Self {
+    pub fn new<A: Into<NodeAddr>>(
+        addr: A,
+        network_interface: Option<Addr<NetworkInterface>>,
+    ) -> Self {
         Self {
-            socket_addr,
+            addr: addr.into(),
             network_interface,
+            cluster: None,
         }
     }
 
+    pub(crate) fn with_cluster(mut self, cluster: ClusterHandle) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
     pub fn get_remote_addr(&self, id: String) -> RemoteAddr {
         RemoteAddr {
             node: self.clone(),
@@ -33,21 +187,26 @@ impl Node {
 impl Default for Node {
     fn default() -> Self {
         Node {
-            socket_addr: "0.1.2.3:8080".parse().unwrap(),
+            addr: NodeAddr::Tcp("0.1.2.3:8080".parse().unwrap()),
             network_interface: None,
+            cluster: None,
         }
     }
 }
 
 impl Clone for Node {
     fn clone(&self) -> Self {
-        Node::new(self.socket_addr, self.network_interface.clone())
+        Self {
+            addr: self.addr.clone(),
+            network_interface: self.network_interface.clone(),
+            cluster: self.cluster.clone(),
+        }
     }
 }
 
 impl PartialEq for Node {
     fn eq(&self, other: &Self) -> bool {
-        self.socket_addr.eq(&other.socket_addr)
+        self.addr.eq(&other.addr)
     }
 }
 
@@ -55,6 +214,6 @@ impl Eq for Node {}
 
 impl Hash for Node {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.socket_addr.hash(state);
+        self.addr.hash(state);
     }
 }
@@ -0,0 +1,81 @@
+use crate::remote::AddrRepresentation;
+use crate::{DefaultSerialization, NetworkInterface, Node, RemoteAddr, RemoteMessage};
+use actix::prelude::*;
+use derive_more::Display;
+use futures::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Time [RemoteAddr::send](./struct.RemoteAddr.html#method.send) waits for a reply
+pub const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum RemoteSendError {
+    /// The remote address has no network interface to its node
+    #[display(fmt = "The remote node is not connected")]
+    NotConnected,
+
+    /// No reply arrived in time, e.g. because the actor is not registered or its node is down
+    #[display(fmt = "No reply arrived in time")]
+    Timeout,
+
+    #[display(fmt = "Could not deserialize the reply")]
+    Deserialization,
+}
+
+/// Local request to the [AddrResolver](./struct.AddrResolver.html) to forward the reply of a conversation.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct AwaitReply {
+    pub conversation_id: Uuid,
+    pub sender: oneshot::Sender<Vec<u8>>,
+}
+
+/// Sent back to the asking node's AddrResolver with the serialized result of the handler.
+#[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+#[serializer(DefaultSerialization)]
+pub(crate) struct Reply {
+    pub conversation_id: Uuid,
+    pub result: Vec<u8>,
+}
+
+/// The node to answer a message sent with [RemoteAddr::send](./struct.RemoteAddr.html#method.send),
+/// see [RemoteWrapper::reply_to](./struct.RemoteWrapper.html#method.reply_to).
+pub struct ReplyTo {
+    conversation_id: Uuid,
+    resolver: RemoteAddr,
+}
+
+impl ReplyTo {
+    pub(crate) fn new(
+        conversation_id: Uuid,
+        node: Node,
+        network_interface: Addr<NetworkInterface>,
+    ) -> Self {
+        // the asking node routes by AddrRepresentation only, so the node's address is not used
+        let resolver = RemoteAddr::new(
+            Node::new(node.addr, Some(network_interface)),
+            AddrRepresentation::AddrResolver,
+        );
+        Self {
+            conversation_id,
+            resolver,
+        }
+    }
+
+    /// Sends the result of handling `M` back; messages that cannot serialize their result are not answered.
+    pub fn reply<M: RemoteMessage>(self, result: &M::Result) {
+        if let Some(result) = M::serialize_result(result) {
+            match &self.resolver.node.network_interface {
+                Some(network_interface) if network_interface.connected() => {
+                    self.resolver.do_send(Reply {
+                        conversation_id: self.conversation_id,
+                        result,
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -1,28 +1,44 @@
+use crate::remote::addr::reply::{AwaitReply, Reply};
+use crate::remote::addr::watch::{
+    Terminated, TerminatedNotice, Unwatch, UnwatchRequest, Watch, WatchRequest,
+};
 use crate::remote::RemoteWrapper;
+use crate::telemetry::record_dropped;
+use crate::{
+    CustomSerialization, DefaultSerialization, NetworkInterface, Node, NodeEvent, RemoteAddr,
+    RemoteMessage,
+};
 use actix::prelude::*;
+use futures::channel::oneshot;
 use log::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::time::Duration;
+use uuid::Uuid;
 
 const NETWORKINTERFACE: &str = "networkinterface";
 const GOSSIP: &str = "gossip";
+const ADDRRESOLVER: &str = "addrresolver";
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum AddrRepresentation {
     NetworkInterface,
     Connector,
+    AddrResolver,
     Key(String),
 }
 
-impl ToString for AddrRepresentation {
-    fn to_string(&self) -> String {
+impl Display for AddrRepresentation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            AddrRepresentation::NetworkInterface => String::from(NETWORKINTERFACE),
-            AddrRepresentation::Connector => String::from(GOSSIP),
-            AddrRepresentation::Key(id) => id.clone(),
+            AddrRepresentation::NetworkInterface => write!(f, "{}", NETWORKINTERFACE),
+            AddrRepresentation::Connector => write!(f, "{}", GOSSIP),
+            AddrRepresentation::AddrResolver => write!(f, "{}", ADDRRESOLVER),
+            AddrRepresentation::Key(id) => write!(f, "{}", id),
         }
     }
 }
@@ -34,6 +50,7 @@ impl FromStr for AddrRepresentation {
         Ok(match s {
             NETWORKINTERFACE => AddrRepresentation::NetworkInterface,
             GOSSIP => AddrRepresentation::Connector,
+            ADDRRESOLVER => AddrRepresentation::AddrResolver,
             _ => AddrRepresentation::Key(String::from(s)),
         })
     }
@@ -74,10 +91,12 @@ pub enum AddrRequest {
     Register(Recipient<RemoteWrapper>, String),
     ResolveStr(String),
     ResolveRec(Recipient<RemoteWrapper>),
+    Deregister(Recipient<RemoteWrapper>),
 }
 
 pub enum AddrResponse {
     Register,
+    Deregister,
     ResolveStr(Recipient<RemoteWrapper>),
     ResolveRec(String),
 }
@@ -86,6 +105,12 @@ pub enum AddrResponse {
 pub struct AddrResolver {
     str2rec: HashMap<String, Recipient<RemoteWrapper>>,
     rec2str: HashMap<Recipient<RemoteWrapper>, String>,
+    /// Remote watchers of local actors by `ACTOR_ID`
+    watchers: HashMap<String, Vec<(Addr<NetworkInterface>, RemoteAddr)>>,
+    /// Local watchers of remote actors
+    watching: HashMap<RemoteAddr, Vec<Recipient<Terminated>>>,
+    /// Local senders waiting for the reply of a conversation
+    replies: HashMap<Uuid, oneshot::Sender<Vec<u8>>>,
 }
 
 pub struct NotAvailableError {}
@@ -135,25 +160,169 @@ impl AddrResolver {
     ) -> Result<&Recipient<RemoteWrapper>, NotAvailableError> {
         self.resolve_str(addr_representation.to_string())
     }
+
+    fn handle_system_message(&mut self, msg: RemoteWrapper) {
+        let serializer = DefaultSerialization {};
+        if msg.identifier == WatchRequest::IDENTIFIER {
+            match (
+                serializer.deserialize::<WatchRequest>(&msg.message_buffer),
+                msg.source,
+            ) {
+                (Ok(request), Some(source)) => self.add_watcher(request.watched, source),
+                _ => warn!("Could not handle WatchRequest"),
+            }
+        } else if msg.identifier == UnwatchRequest::IDENTIFIER {
+            match (
+                serializer.deserialize::<UnwatchRequest>(&msg.message_buffer),
+                msg.source,
+            ) {
+                (Ok(request), Some(source)) => self.remove_watcher(&request.watched, &source),
+                _ => warn!("Could not handle UnwatchRequest"),
+            }
+        } else if msg.identifier == TerminatedNotice::IDENTIFIER {
+            match serializer.deserialize::<TerminatedNotice>(&msg.message_buffer) {
+                Ok(notice) => self.terminated(&notice.watched),
+                Err(_) => warn!("Could not handle TerminatedNotice"),
+            }
+        } else if msg.identifier == Reply::IDENTIFIER {
+            match serializer.deserialize::<Reply>(&msg.message_buffer) {
+                Ok(reply) => match self.replies.remove(&reply.conversation_id) {
+                    Some(sender) => {
+                        let _ = sender.send(reply.result);
+                    }
+                    None => debug!("Reply {} arrived too late", reply.conversation_id),
+                },
+                Err(_) => warn!("Could not handle Reply"),
+            }
+        } else {
+            warn!("Unknown system message '{}' is abandoned.", msg.identifier);
+        }
+    }
+
+    fn add_watcher(&mut self, watched: RemoteAddr, network_interface: Addr<NetworkInterface>) {
+        let id = watched.id.to_string();
+        match self.str2rec.get(&id) {
+            Some(rec) if rec.connected() => self
+                .watchers
+                .entry(id)
+                .or_default()
+                .push((network_interface, watched)),
+            _ => Self::notify_watcher(network_interface, watched),
+        }
+    }
+
+    fn remove_watcher(&mut self, watched: &RemoteAddr, network_interface: &Addr<NetworkInterface>) {
+        let id = watched.id.to_string();
+        if let Some(watchers) = self.watchers.get_mut(&id) {
+            watchers.retain(|(watcher, remote_addr)| {
+                watcher != network_interface || remote_addr != watched
+            });
+            if watchers.is_empty() {
+                self.watchers.remove(&id);
+            }
+        }
+    }
+
+    fn notify_watcher(network_interface: Addr<NetworkInterface>, watched: RemoteAddr) {
+        if network_interface.connected() {
+            // the watching node routes by AddrRepresentation only, so the node's address is not used
+            let destination = RemoteAddr::new(
+                Node::new(watched.node.addr.clone(), Some(network_interface)),
+                AddrRepresentation::AddrResolver,
+            );
+            destination.do_send(TerminatedNotice { watched });
+        }
+    }
+
+    /// Removes the local actor and informs its remote watchers.
+    fn deregister(&mut self, id: &str) {
+        if let Some(rec) = self.str2rec.remove(id) {
+            self.rec2str.remove(&rec);
+            debug!("Actor '{}' deregistered", id);
+        }
+        for (network_interface, watched) in self.watchers.remove(id).unwrap_or_default() {
+            Self::notify_watcher(network_interface, watched);
+        }
+    }
+
+    // Option::is_none_or would require Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    fn check_watched(&mut self) {
+        let stopped: Vec<String> = self
+            .watchers
+            .keys()
+            .filter(|id| self.str2rec.get(*id).map_or(true, |rec| !rec.connected()))
+            .cloned()
+            .collect();
+        for id in stopped {
+            self.deregister(&id);
+        }
+        self.prune_watchers();
+    }
+
+    /// Drops remote watchers whose node has left and local watchers that stopped.
+    fn prune_watchers(&mut self) {
+        self.watchers.retain(|_, watchers| {
+            watchers.retain(|(network_interface, _)| network_interface.connected());
+            !watchers.is_empty()
+        });
+        let unwatched: Vec<RemoteAddr> = self
+            .watching
+            .iter_mut()
+            .filter_map(|(watched, watchers)| {
+                watchers.retain(|watcher| watcher.connected());
+                watchers.is_empty().then(|| watched.clone())
+            })
+            .collect();
+        for watched in unwatched {
+            self.unwatch_remote(watched);
+        }
+    }
+
+    /// Informs the watched actor's node that this node no longer watches it.
+    fn unwatch_remote(&mut self, watched: RemoteAddr) {
+        self.watching.remove(&watched);
+        let resolver = RemoteAddr::new(watched.node.clone(), AddrRepresentation::AddrResolver);
+        resolver.do_send(UnwatchRequest { watched });
+    }
+
+    /// Informs the local watchers of a remote actor that it is gone.
+    fn terminated(&mut self, watched: &RemoteAddr) {
+        if let Some((watched, watchers)) = self.watching.remove_entry(watched) {
+            for watcher in watchers {
+                watcher.do_send(Terminated(watched.clone()));
+            }
+        }
+    }
 }
 
 impl Actor for AddrResolver {
     type Context = Context<Self>;
 
-    fn started(&mut self, _ctx: &mut Context<Self>) {
+    fn started(&mut self, ctx: &mut Context<Self>) {
         debug!("AddressResolver actor started");
+        ctx.run_interval(WATCH_INTERVAL, |act, _ctx| {
+            act.check_watched();
+            // senders that timed out do not wait any longer
+            act.replies.retain(|_, sender| !sender.is_canceled());
+        });
     }
 }
 
 impl Handler<RemoteWrapper> for AddrResolver {
     type Result = ();
 
-    fn handle(&mut self, msg: RemoteWrapper, _ctx: &mut Context<Self>) -> Self::Result {
-        if let Ok(recipient) = self.resolve_rec_from_addr_representation(msg.destination.id.clone())
+    fn handle(&mut self, mut msg: RemoteWrapper, _ctx: &mut Context<Self>) -> Self::Result {
+        let _stage = msg.enter_receive_stage("addr_resolver");
+        if let AddrRepresentation::AddrResolver = msg.destination.id {
+            self.handle_system_message(msg);
+        } else if let Ok(recipient) =
+            self.resolve_rec_from_addr_representation(msg.destination.id.clone())
         {
             recipient.do_send(msg);
         } else {
             warn!("Could not resolve Recipient '{}' for RemoteMessage. Is this receiver a RemoteActor? Message is abandoned.", msg.identifier);
+            record_dropped(&msg.identifier);
         }
     }
 }
@@ -164,7 +333,7 @@ impl Handler<AddrRequest> for AddrResolver {
     fn handle(&mut self, msg: AddrRequest, _ctx: &mut Context<Self>) -> Self::Result {
         match msg {
             AddrRequest::Register(rec, identifier) => {
-                let is_new = self.rec2str.get(&rec).is_none();
+                let is_new = !self.rec2str.contains_key(&rec);
 
                 if is_new {
                     self.str2rec.insert(identifier.clone(), rec.clone());
@@ -190,9 +359,97 @@ impl Handler<AddrRequest> for AddrResolver {
                     Err(_) => Err(()),
                 }
             }
+            AddrRequest::Deregister(rec) => match self.rec2str.get(&rec).cloned() {
+                Some(id) => {
+                    self.deregister(&id);
+                    Ok(AddrResponse::Deregister)
+                }
+                None => Err(()),
+            },
+        }
+    }
+}
+
+impl Handler<AwaitReply> for AddrResolver {
+    type Result = ();
+
+    fn handle(&mut self, msg: AwaitReply, _ctx: &mut Context<Self>) -> Self::Result {
+        self.replies.insert(msg.conversation_id, msg.sender);
+    }
+}
+
+impl Handler<Watch> for AddrResolver {
+    type Result = ();
+
+    fn handle(&mut self, msg: Watch, _ctx: &mut Context<Self>) -> Self::Result {
+        let network_interface = msg.watched.node.network_interface.clone();
+        match network_interface {
+            Some(network_interface) if network_interface.connected() => {
+                let watchers = self.watching.entry(msg.watched.clone()).or_default();
+                watchers.push(msg.watcher);
+                if watchers.len() == 1 {
+                    let resolver =
+                        RemoteAddr::new(msg.watched.node.clone(), AddrRepresentation::AddrResolver);
+                    resolver.do_send(WatchRequest {
+                        watched: msg.watched,
+                    });
+                }
+            }
+            _ => msg.watcher.do_send(Terminated(msg.watched)),
         }
     }
 }
 
+impl Handler<Unwatch> for AddrResolver {
+    type Result = ();
+
+    fn handle(&mut self, msg: Unwatch, _ctx: &mut Context<Self>) -> Self::Result {
+        if let Some(watchers) = self.watching.get_mut(&msg.watched) {
+            watchers.retain(|watcher| *watcher != msg.watcher);
+            if watchers.is_empty() {
+                self.unwatch_remote(msg.watched);
+            }
+        }
+    }
+}
+
+impl Handler<NodeEvent> for AddrResolver {
+    type Result = ();
+
+    fn handle(&mut self, msg: NodeEvent, _ctx: &mut Context<Self>) -> Self::Result {
+        if let NodeEvent::MemberDown(addr) = msg {
+            let watched: Vec<RemoteAddr> = self
+                .watching
+                .keys()
+                .filter(|watched| watched.node.addr == addr)
+                .cloned()
+                .collect();
+            for watched in watched {
+                self.terminated(&watched);
+            }
+            // the watchers on that node are gone with its NetworkInterface
+            self.prune_watchers();
+        }
+    }
+}
+
+/// Number of remote watchers and of local watchers the AddrResolver keeps.
+#[cfg(test)]
+#[derive(Message)]
+#[rtype(result = "(usize, usize)")]
+pub(crate) struct WatchCounts;
+
+#[cfg(test)]
+impl Handler<WatchCounts> for AddrResolver {
+    type Result = MessageResult<WatchCounts>;
+
+    fn handle(&mut self, _msg: WatchCounts, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult((
+            self.watchers.values().map(Vec::len).sum(),
+            self.watching.values().map(Vec::len).sum(),
+        ))
+    }
+}
+
 impl Supervised for AddrResolver {}
 impl SystemService for AddrResolver {}
@@ -1,5 +1,8 @@
-use crate::{prelude::*, Node};
-use crate::{AddrRepresentation, AddrRequest, AddrResolver, AddrResponse};
+use crate::remote::addr::resolver::WatchCounts;
+use crate::testkit::{ClusterEvents, TestCluster};
+use crate::{prelude::*, ClusterHandle, Node, RemoteSendError};
+use crate::{AddrRepresentation, AddrRequest, AddrResolver, AddrResponse, Terminated};
+use crate::{FaultController, MemoryNetwork};
 use actix::prelude::*;
 use actix_broker::BrokerSubscribe;
 use actix_telepathy_derive::{RemoteActor, RemoteMessage};
@@ -10,9 +13,11 @@ use rayon::iter::ParallelIterator;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{sleep, timeout};
 
 #[derive(RemoteMessage, Serialize, Deserialize)]
 #[with_source(source)]
@@ -69,7 +74,7 @@ async fn addr_resolver_registers_and_resolves_addr() {
     });
     sleep(Duration::from_secs(1)).await;
     assert_eq!(
-        (*(identifiers.lock().unwrap())).get(0).unwrap(),
+        (*(identifiers.lock().unwrap())).first().unwrap(),
         &identifier
     );
 }
@@ -117,18 +122,18 @@ fn remote_addr_ignores_hash() {
 
     let arr = [
         TestParams {
-            ip: ip1.clone(),
+            ip: ip1,
             seeds: vec![],
             last: false,
         },
         TestParams {
-            ip: ip2.clone(),
-            seeds: vec![ip1.clone()],
+            ip: ip2,
+            seeds: vec![ip1],
             last: false,
         },
         TestParams {
-            ip: ip3.clone(),
-            seeds: vec![ip1.clone()],
+            ip: ip3,
+            seeds: vec![ip1],
             last: true,
         },
     ];
@@ -174,22 +179,20 @@ impl Actor for OwnListenerGossipIntroduction {
 impl Handler<ClusterLog> for OwnListenerGossipIntroduction {
     type Result = ();
 
+    #[allow(clippy::mutable_key_type)]
     fn handle(&mut self, msg: ClusterLog, _ctx: &mut Context<Self>) -> Self::Result {
-        match msg {
-            ClusterLog::NewMember(node) => {
-                self.addrs.push(node);
-                if self.addrs.len() >= 2 {
-                    let node = self.addrs.get(0).unwrap().clone();
-                    let mut node2 = node.clone();
-                    node2.network_interface = None;
-                    //let remote_addr3 = self.addrs.get(1).unwrap().clone();
-
-                    let mut map = HashMap::<Node, usize>::new();
-                    map.insert(node, 0);
-                    (*(self.returned.lock().unwrap())) = map.remove(&node2);
-                }
+        if let ClusterLog::NewMember(node) = msg {
+            self.addrs.push(node);
+            if self.addrs.len() >= 2 {
+                let node = self.addrs.first().unwrap().clone();
+                let mut node2 = node.clone();
+                node2.network_interface = None;
+                //let remote_addr3 = self.addrs.get(1).unwrap().clone();
+
+                let mut map = HashMap::<Node, usize>::new();
+                map.insert(node, 0);
+                (*(self.returned.lock().unwrap())) = map.remove(&node2);
             }
-            _ => (),
         }
     }
 }
@@ -214,12 +217,9 @@ impl Handler<ClusterLog> for OwnListenerGossipIntroduction2 {
     type Result = ();
 
     fn handle(&mut self, msg: ClusterLog, _ctx: &mut Context<Self>) -> Self::Result {
-        match msg {
-            ClusterLog::NewMember(node) => {
-                let remote_addr = node.get_remote_addr(TestActor2::ACTOR_ID.to_string());
-                (*(self.returned.lock().unwrap())) = Some(remote_addr);
-            }
-            _ => (),
+        if let ClusterLog::NewMember(node) = msg {
+            let remote_addr = node.get_remote_addr(TestActor2::ACTOR_ID.to_string());
+            (*(self.returned.lock().unwrap())) = Some(remote_addr);
         }
     }
 }
@@ -263,12 +263,12 @@ fn remote_addr_filled_in_with_source() {
 
     let arr = [
         TestParams2 {
-            ip: ip1.clone(),
+            ip: ip1,
             seeds: vec![],
         },
         TestParams2 {
-            ip: ip2.clone(),
-            seeds: vec![ip1.clone()],
+            ip: ip2,
+            seeds: vec![ip1],
         },
     ];
     arr.par_iter()
@@ -289,10 +289,13 @@ async fn build_cluster_2(own_ip: SocketAddr, other_ip: Vec<SocketAddr>) {
     }
     .start();
     sleep(Duration::from_millis(200)).await;
-    let guard = returned.lock().unwrap();
-    let remote_addr = guard.as_ref().expect("Something should be returned");
+    let remote_addr = returned
+        .lock()
+        .unwrap()
+        .clone()
+        .expect("Something should be returned");
     let mut own_remote_addr = remote_addr.clone();
-    own_remote_addr.node.socket_addr = own_ip;
+    own_remote_addr.node.addr = own_ip.into();
     remote_addr.do_send(TestMessage {
         source: own_remote_addr,
     });
@@ -302,8 +305,8 @@ async fn build_cluster_2(own_ip: SocketAddr, other_ip: Vec<SocketAddr>) {
             .as_ref()
             .unwrap()
             .node
-            .socket_addr,
-        remote_addr.node.socket_addr
+            .addr,
+        remote_addr.node.addr
     );
 }
 
@@ -316,7 +319,7 @@ async fn addr_resolver_does_not_panic_wrong_id() {
     let listening_ip: SocketAddr = format!("127.0.0.1:{}", request_open_port().unwrap_or(8000))
         .parse()
         .unwrap();
-    let _cluster = Cluster::new(listening_ip.clone(), vec![]);
+    let _cluster = Cluster::new(listening_ip, vec![]);
     let fake_addr = RemoteAddr::new(
         Node::new(listening_ip, None),
         AddrRepresentation::Key("test".to_string()),
@@ -335,3 +338,330 @@ async fn addr_resolver_does_not_panic_wrong_id() {
         assert_eq!(warnings_count, 1);
     });
 }
+
+// DeathWatch
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct StopWorker {}
+
+#[derive(RemoteActor)]
+#[remote_messages(StopWorker)]
+struct DeathWatchWorker {
+    cluster: ClusterHandle,
+}
+
+impl Actor for DeathWatchWorker {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+impl Handler<StopWorker> for DeathWatchWorker {
+    type Result = ();
+
+    fn handle(&mut self, _msg: StopWorker, ctx: &mut Context<Self>) -> Self::Result {
+        ctx.stop();
+    }
+}
+
+#[derive(RemoteActor)]
+#[remote_messages(StopWorker)]
+struct DeathWatchSurvivor {
+    cluster: ClusterHandle,
+}
+
+impl Actor for DeathWatchSurvivor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+impl Handler<StopWorker> for DeathWatchSurvivor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: StopWorker, ctx: &mut Context<Self>) -> Self::Result {
+        let cluster = self.cluster.clone();
+        self.deregister_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+struct DeathWatcher {
+    terminated: UnboundedSender<String>,
+}
+
+impl Actor for DeathWatcher {
+    type Context = Context<Self>;
+}
+
+impl Handler<Terminated> for DeathWatcher {
+    type Result = ();
+
+    fn handle(&mut self, msg: Terminated, _ctx: &mut Context<Self>) -> Self::Result {
+        let _ = self.terminated.send(msg.0.id.to_string());
+    }
+}
+
+impl Handler<StopWorker> for DeathWatcher {
+    type Result = ();
+
+    fn handle(&mut self, _msg: StopWorker, ctx: &mut Context<Self>) -> Self::Result {
+        ctx.stop();
+    }
+}
+
+async fn expect_terminated(terminated: &mut UnboundedReceiver<String>, id: &str) {
+    let received = timeout(Duration::from_secs(5), terminated.recv())
+        .await
+        .unwrap_or_else(|_| panic!("'{}' did not terminate", id));
+    assert_eq!(received.as_deref(), Some(id));
+}
+
+#[test]
+fn addr_representation_parses_addr_resolver() {
+    assert_eq!(
+        AddrRepresentation::from_str("addrresolver").unwrap(),
+        AddrRepresentation::AddrResolver
+    );
+    assert_eq!(AddrRepresentation::AddrResolver.to_string(), "addrresolver");
+}
+
+#[test]
+fn node_addr_parses_tcp_and_unix() {
+    let tcp = NodeAddr::from_str("127.0.0.1:1992").unwrap();
+    assert_eq!(tcp, "127.0.0.1:1992".parse::<SocketAddr>().unwrap());
+    assert_eq!(tcp.to_string(), "127.0.0.1:1992");
+
+    let unix = NodeAddr::from_str("unix:/tmp/telepathy.sock").unwrap();
+    assert_eq!(unix, NodeAddr::unix("/tmp/telepathy.sock"));
+    assert_eq!(unix.to_string(), "unix:/tmp/telepathy.sock");
+    assert!(unix.socket_addr().is_none());
+
+    assert!(NodeAddr::from_str("/tmp/telepathy.sock").is_err());
+}
+
+#[test]
+fn node_addr_parses_host_names() {
+    let host = NodeAddr::from_str("seed-0.telepathy:1992").unwrap();
+    assert_eq!(host, NodeAddr::host("seed-0.telepathy", 1992));
+    assert_eq!(host.to_string(), "seed-0.telepathy:1992");
+    assert!(host.is_host());
+    assert!(host.socket_addr().is_none());
+
+    assert!(NodeAddr::from_str("seed").is_err());
+    assert!(NodeAddr::from_str("seed:port").is_err());
+    assert!(NodeAddr::from_str("127.0.0:1992").is_err());
+}
+
+#[test]
+fn advertised_addr_is_trusted_unless_unspecified() {
+    let source = NodeAddr::from_str("10.0.0.7:52314").unwrap();
+    let advertised = NodeAddr::from_str("203.0.113.5:31992").unwrap();
+    assert_eq!(source.listening_addr(advertised.clone()), advertised);
+
+    let unspecified = NodeAddr::from_str("0.0.0.0:1992").unwrap();
+    assert_eq!(
+        source.listening_addr(unspecified),
+        NodeAddr::from_str("10.0.0.7:1992").unwrap()
+    );
+}
+
+#[test]
+fn node_addr_keeps_ipv6_scope_id() {
+    let link_local = NodeAddr::from_str("[fe80::1%2]:1992").unwrap();
+    assert_eq!(link_local.to_string(), "[fe80::1%2]:1992");
+
+    let serialization = DefaultSerialization {};
+    let bytes = serialization.serialize(&link_local).unwrap();
+    let deserialized: NodeAddr = serialization.deserialize(&bytes).unwrap();
+    assert_eq!(deserialized.to_string(), "[fe80::1%2]:1992");
+
+    let source = NodeAddr::from_str("[fe80::7%2]:52314").unwrap();
+    let unspecified = NodeAddr::from_str("[::]:1992").unwrap();
+    assert_eq!(
+        source.listening_addr(unspecified).to_string(),
+        "[fe80::7%2]:1992"
+    );
+}
+
+#[actix_rt::test]
+async fn watch_without_network_interface_terminates_immediately() {
+    let (tx, mut terminated) = unbounded_channel();
+    let watcher = DeathWatcher { terminated: tx }.start();
+    RemoteAddr::new_from_id(
+        "127.0.0.1:1992".parse::<SocketAddr>().unwrap(),
+        "Unreachable",
+    )
+    .watch(watcher.recipient());
+    expect_terminated(&mut terminated, "Unreachable").await;
+}
+
+#[actix_rt::test]
+async fn watched_remote_actors_terminate() {
+    let controller = FaultController::new();
+    let cluster = TestCluster::builder(2)
+        .in_memory(MemoryNetwork::new())
+        .faults(controller.clone())
+        .start()
+        .await;
+    let worker = DeathWatchWorker {
+        cluster: cluster.handle(0).clone(),
+    }
+    .start();
+    let survivor = DeathWatchSurvivor {
+        cluster: cluster.handle(0).clone(),
+    }
+    .start();
+    let _lost = cluster.probe(0, "DeathWatchLost");
+    sleep(Duration::from_millis(100)).await;
+
+    let (tx, mut terminated) = unbounded_channel();
+    let watcher = DeathWatcher { terminated: tx }.start();
+    for id in [
+        "DeathWatchMissing",
+        DeathWatchWorker::ACTOR_ID,
+        DeathWatchSurvivor::ACTOR_ID,
+        "DeathWatchLost",
+    ] {
+        cluster
+            .remote_addr(1, 0, id)
+            .watch(watcher.clone().recipient());
+    }
+    // never registered
+    expect_terminated(&mut terminated, "DeathWatchMissing").await;
+
+    worker.do_send(StopWorker {});
+    expect_terminated(&mut terminated, DeathWatchWorker::ACTOR_ID).await;
+
+    survivor.do_send(StopWorker {});
+    expect_terminated(&mut terminated, DeathWatchSurvivor::ACTOR_ID).await;
+
+    // node 1 dialed node 0, it gives up after failing to reconnect
+    controller.partition(&[cluster.addr(0)], &[cluster.addr(1)]);
+    controller.disconnect(cluster.addr(0), cluster.addr(1));
+    expect_terminated(&mut terminated, "DeathWatchLost").await;
+}
+
+async fn watch_counts(cluster: &TestCluster, node: usize) -> (usize, usize) {
+    cluster
+        .handle(node)
+        .addr_resolver()
+        .send(WatchCounts)
+        .await
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn watches_are_removed_when_unwatched_stopped_or_left() {
+    let controller = FaultController::new();
+    let cluster = TestCluster::builder(2)
+        .in_memory(MemoryNetwork::new())
+        .faults(controller.clone())
+        .start()
+        .await;
+    let _worker = DeathWatchWorker {
+        cluster: cluster.handle(0).clone(),
+    }
+    .start();
+    sleep(Duration::from_millis(100)).await;
+
+    let worker = cluster.remote_addr(1, 0, DeathWatchWorker::ACTOR_ID);
+    let (tx, mut terminated) = unbounded_channel();
+    let unwatching = DeathWatcher { terminated: tx }.start();
+    let (tx, _stopping) = unbounded_channel();
+    let stopping = DeathWatcher { terminated: tx }.start();
+    worker.watch(unwatching.clone().recipient());
+    worker.watch(stopping.clone().recipient());
+    sleep(Duration::from_millis(100)).await;
+    // node 1 asks node 0 once for all its watchers
+    assert_eq!(watch_counts(&cluster, 0).await, (1, 0));
+    assert_eq!(watch_counts(&cluster, 1).await, (0, 2));
+
+    worker.unwatch(unwatching.clone().recipient());
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(watch_counts(&cluster, 1).await, (0, 1));
+
+    // stopped watchers are pruned, the last one unwatches the worker on node 0
+    stopping.do_send(StopWorker {});
+    sleep(Duration::from_millis(700)).await;
+    assert_eq!(watch_counts(&cluster, 1).await, (0, 0));
+    assert_eq!(watch_counts(&cluster, 0).await, (0, 0));
+
+    worker.watch(unwatching.clone().recipient());
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(watch_counts(&cluster, 0).await, (1, 0));
+
+    // node 0 forgets the watchers of node 1 once it left
+    let mut events = ClusterEvents::of(cluster.handle(0));
+    controller.partition(&[cluster.addr(0)], &[cluster.addr(1)]);
+    controller.disconnect(cluster.addr(0), cluster.addr(1));
+    expect_terminated(&mut terminated, DeathWatchWorker::ACTOR_ID).await;
+    assert_eq!(
+        events.expect_member_left(Duration::from_secs(5)).await,
+        cluster.addr(1)
+    );
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(watch_counts(&cluster, 0).await, (0, 0));
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(result = "usize")]
+#[remote_message(reply)]
+struct CountBytes(Vec<u8>);
+
+#[derive(RemoteActor)]
+#[remote_messages(CountBytes)]
+struct ByteCounter {
+    cluster: ClusterHandle,
+}
+
+impl Actor for ByteCounter {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+impl Handler<CountBytes> for ByteCounter {
+    type Result = usize;
+
+    fn handle(&mut self, msg: CountBytes, _ctx: &mut Self::Context) -> Self::Result {
+        msg.0.len()
+    }
+}
+
+#[actix_rt::test]
+async fn remote_addr_send_returns_result_of_remote_handler() {
+    let cluster = crate::testkit::TestCluster::builder(2)
+        .in_memory(crate::MemoryNetwork::new())
+        .start()
+        .await;
+    ByteCounter {
+        cluster: cluster.handle(1).clone(),
+    }
+    .start();
+    sleep(Duration::from_millis(100)).await;
+
+    let counter = cluster.remote_addr(0, 1, ByteCounter::ACTOR_ID);
+    assert_eq!(counter.send(CountBytes(vec![1, 2, 3])).await, Ok(3));
+    assert_eq!(counter.send(CountBytes(vec![])).await, Ok(0));
+
+    let unknown = cluster.remote_addr(0, 1, "unknown");
+    assert_eq!(
+        unknown
+            .send_timeout(CountBytes(vec![1]), Duration::from_millis(200))
+            .await,
+        Err(RemoteSendError::Timeout)
+    );
+    assert_eq!(
+        RemoteAddr::default().send(CountBytes(vec![1])).await,
+        Err(RemoteSendError::NotConnected)
+    );
+}
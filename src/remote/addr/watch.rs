@@ -0,0 +1,48 @@
+use crate::{DefaultSerialization, RemoteAddr, RemoteMessage};
+use actix::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Delivered to watchers when a watched remote actor stops, deregisters or its node goes down.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "()")]
+pub struct Terminated(pub RemoteAddr);
+
+/// Local request to the [AddrResolver](./struct.AddrResolver.html) to watch a remote actor,
+/// see [RemoteAddr::watch](./struct.RemoteAddr.html#method.watch).
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Watch {
+    pub watched: RemoteAddr,
+    pub watcher: Recipient<Terminated>,
+}
+
+/// Local request to the [AddrResolver](./struct.AddrResolver.html) to stop watching a remote actor,
+/// see [RemoteAddr::unwatch](./struct.RemoteAddr.html#method.unwatch).
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Unwatch {
+    pub watched: RemoteAddr,
+    pub watcher: Recipient<Terminated>,
+}
+
+/// Sent to the AddrResolver on the watched actor's node.
+#[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+#[serializer(DefaultSerialization)]
+pub(crate) struct WatchRequest {
+    /// The watched actor as known by the watching node
+    pub watched: RemoteAddr,
+}
+
+/// Sent to the AddrResolver on the watched actor's node once the watching node has no watchers left.
+#[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+#[serializer(DefaultSerialization)]
+pub(crate) struct UnwatchRequest {
+    pub watched: RemoteAddr,
+}
+
+/// Sent back to the watching node's AddrResolver once the watched actor is gone.
+#[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+#[serializer(DefaultSerialization)]
+pub(crate) struct TerminatedNotice {
+    pub watched: RemoteAddr,
+}
@@ -1,6 +1,9 @@
-use crate::{CustomSerialization, NetworkInterface, RemoteAddr};
+use crate::remote::trace::{enter_stage, StageGuard};
+use crate::telemetry::record_serialization;
+use crate::{CustomSerialization, Delivery, NetworkInterface, RemoteAddr, ReplyTo, TraceContext};
 use actix::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 use uuid::Uuid;
 
 /// Wrapper for messages to be sent to remote actor
@@ -16,6 +19,12 @@ pub struct RemoteWrapper {
     #[serde(skip_deserializing)]
     pub source: Option<Addr<NetworkInterface>>,
     pub conversation_id: Option<Uuid>,
+    /// Trace context of the sending span, missing for peers that do not propagate traces
+    #[serde(default)]
+    pub trace_context: Option<TraceContext>,
+    /// Sequence number if the message is sent with at-least-once delivery
+    #[serde(default)]
+    pub delivery: Option<Delivery>,
 }
 
 impl RemoteWrapper {
@@ -25,16 +34,48 @@ impl RemoteWrapper {
         conversation_id: Option<Uuid>,
     ) -> RemoteWrapper {
         let serializer = message.get_serializer();
+        let start = Instant::now();
+        let message_buffer = serializer
+            .serialize(&message)
+            .expect("Cannot serialize message");
+        record_serialization(start.elapsed());
         RemoteWrapper {
             destination,
-            message_buffer: serializer
-                .serialize(&message)
-                .expect("Cannot serialize message"),
+            message_buffer,
             identifier: message.get_identifier().to_string(),
             source: None,
             conversation_id,
+            trace_context: TraceContext::for_outgoing(),
+            delivery: None,
         }
     }
+
+    /// Where to send the result of the handler, if the message was sent with
+    /// [RemoteAddr::send](./struct.RemoteAddr.html#method.send).
+    pub fn reply_to(&self) -> Option<ReplyTo> {
+        match (self.conversation_id, &self.source) {
+            (Some(conversation_id), Some(source)) => Some(ReplyTo::new(
+                conversation_id,
+                self.destination.node.clone(),
+                source.clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Moves the trace context to a child span for the next receiving stage.
+    /// With the `tracing` feature, the returned guard keeps the span of that stage entered.
+    #[doc(hidden)]
+    pub fn enter_receive_stage(&mut self, stage: &'static str) -> StageGuard {
+        let parent = self.trace_context;
+        self.trace_context = parent.map(|context| context.child());
+        enter_stage(
+            stage,
+            &self.identifier,
+            parent.as_ref(),
+            self.trace_context.as_ref(),
+        )
+    }
 }
 
 impl Clone for RemoteWrapper {
@@ -45,6 +86,8 @@ impl Clone for RemoteWrapper {
             identifier: self.identifier.clone(),
             source: self.source.clone(),
             conversation_id: self.conversation_id,
+            trace_context: self.trace_context,
+            delivery: self.delivery,
         }
     }
 }
@@ -66,4 +109,31 @@ where
     fn generate_serializer() -> Box<Self::Serializer>;
 
     fn set_source(&mut self, source: Addr<NetworkInterface>);
+
+    /// Serializes the result of the handler to reply to [RemoteAddr::send](./struct.RemoteAddr.html#method.send).
+    /// The `RemoteMessage` derive implements it with the message's serializer; without it, there is no reply.
+    fn serialize_result(_result: &Self::Result) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Remote messages whose handler replies, so that [RemoteAddr::send](./struct.RemoteAddr.html#method.send)
+/// can wait for the result. The `RemoteMessage` derive implements it with `#[remote_message(reply)]`.
+pub trait RemoteReply: RemoteMessage {}
+
+/// Compares two identifiers in const context, so that the `RemoteActor` derive can reject
+/// messages whose identifiers collide at compile time.
+pub const fn identifiers_equal(first: &str, second: &str) -> bool {
+    let (first, second) = (first.as_bytes(), second.as_bytes());
+    if first.len() != second.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < first.len() {
+        if first[i] != second[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
 }
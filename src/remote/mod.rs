@@ -1,10 +1,17 @@
 mod actor;
 mod addr;
-mod message;
+pub(crate) mod message;
 #[cfg(test)]
 mod tests;
+mod trace;
+pub(crate) mod type_name;
 
 pub use self::actor::RemoteActor;
-pub use self::addr::{AnyAddr, Node, RemoteAddr};
-pub use self::message::{RemoteMessage, RemoteWrapper};
+pub use self::addr::{
+    AnyAddr, Node, NodeAddr, RemoteAddr, RemoteSendError, ReplyTo, DEFAULT_REPLY_TIMEOUT,
+};
+pub use self::message::{RemoteMessage, RemoteReply, RemoteWrapper};
+pub use self::trace::TraceContext;
+pub use self::type_name::TypeName;
 pub use addr::resolver::{AddrRepresentation, AddrRequest, AddrResolver, AddrResponse};
+pub use addr::watch::{Terminated, Unwatch, Watch};
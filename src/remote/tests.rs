@@ -1,7 +1,10 @@
 use crate::prelude::*;
+use crate::{ClusterHandle, RemoteSendError, TypeName};
 use actix::prelude::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
+use std::time::Duration;
 
 #[derive(Serialize, Deserialize, RemoteMessage)]
 struct MyRemoteMessage<T: Serialize + Send> {
@@ -36,3 +39,418 @@ async fn generic_remote_messages() {
     .start();
     addr.do_send(MyRemoteMessage { value: 4.2 });
 }
+
+mod billing {
+    use crate::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, RemoteMessage)]
+    #[remote_message(module_path)]
+    pub struct Ping {}
+}
+
+mod shipping {
+    use crate::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, RemoteMessage)]
+    #[remote_message(id = "shipping.Ping.v1")]
+    pub struct Ping {}
+}
+
+#[derive(Serialize, Deserialize, RemoteMessage)]
+struct Ping {}
+
+// compiles only because the identifiers of all three `Ping`s differ
+#[derive(RemoteActor)]
+#[remote_messages(Ping, billing::Ping, shipping::Ping)]
+struct PingActor {}
+
+impl Actor for PingActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<Ping> for PingActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+impl Handler<billing::Ping> for PingActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: billing::Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+impl Handler<shipping::Ping> for PingActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: shipping::Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+#[actix_rt::test]
+async fn remote_message_identifiers_can_be_explicit_or_namespaced() {
+    assert_eq!(Ping::IDENTIFIER, "Ping");
+    assert_eq!(
+        billing::Ping::IDENTIFIER,
+        "actix_telepathy::remote::tests::billing::Ping"
+    );
+    assert_eq!(shipping::Ping::IDENTIFIER, "shipping.Ping.v1");
+
+    assert!(crate::__private::identifiers_equal("Ping", "Ping"));
+    assert!(!crate::__private::identifiers_equal("Ping", "Pong"));
+    assert!(!crate::__private::identifiers_equal("Ping", "Ping.v1"));
+
+    let addr = PingActor {}.start();
+    addr.send(billing::Ping {}).await.unwrap();
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(result = "(usize, T)")]
+#[remote_message(reply)]
+struct Echo<T: 'static> {
+    value: T,
+}
+
+#[derive(RemoteActor)]
+#[remote_messages(Echo<T>)]
+struct Worker<T> {
+    cluster: ClusterHandle,
+    node: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Worker<T> {
+    fn new(cluster: ClusterHandle, node: usize) -> Self {
+        Self {
+            cluster,
+            node,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Actor for Worker<T>
+where
+    T: TypeName + Serialize + DeserializeOwned + Send + Unpin + 'static,
+{
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+impl<T> Handler<Echo<T>> for Worker<T>
+where
+    T: TypeName + Serialize + DeserializeOwned + Send + Unpin + 'static,
+{
+    type Result = MessageResult<Echo<T>>;
+
+    fn handle(&mut self, msg: Echo<T>, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult((self.node, msg.value))
+    }
+}
+
+#[actix_rt::test]
+async fn generic_remote_actors_have_an_identifier_per_instantiation() {
+    assert_eq!(Worker::<u32>::ACTOR_ID, "Worker<u32>");
+    assert_eq!(Worker::<Vec<String>>::ACTOR_ID, "Worker<Vec<String>>");
+    assert_eq!(Echo::<u32>::IDENTIFIER, "Echo<u32>");
+    assert_eq!(Echo::<Echo<bool>>::IDENTIFIER, "Echo<Echo<bool>>");
+
+    let cluster = crate::testkit::TestCluster::builder(2)
+        .in_memory(crate::MemoryNetwork::new())
+        .start()
+        .await;
+    Worker::<u32>::new(cluster.handle(0).clone(), 0).start();
+    Worker::<u32>::new(cluster.handle(1).clone(), 1).start();
+    Worker::<String>::new(cluster.handle(1).clone(), 1).start();
+    actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+    let numbers = cluster.remote_addr(0, 1, Worker::<u32>::ACTOR_ID);
+    assert_eq!(numbers.send(Echo { value: 7u32 }).await, Ok((1, 7)));
+    let strings = cluster.remote_addr(0, 1, Worker::<String>::ACTOR_ID);
+    assert_eq!(
+        strings
+            .send(Echo {
+                value: "hi".to_string()
+            })
+            .await,
+        Ok((1, "hi".to_string()))
+    );
+    let back = cluster.remote_addr(1, 0, Worker::<u32>::ACTOR_ID);
+    assert_eq!(back.send(Echo { value: 8u32 }).await, Ok((0, 8)));
+
+    // a Worker<String> does not know Echo<u32>
+    assert_eq!(
+        strings
+            .send_timeout(Echo { value: 9u32 }, Duration::from_millis(200))
+            .await,
+        Err(RemoteSendError::Timeout)
+    );
+}
+
+#[test]
+fn type_names_of_std_types() {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    assert_eq!(<HashMap<String, u32>>::TYPE_NAME, "HashMap<String,u32>");
+    assert_eq!(
+        <BTreeMap<u8, Vec<bool>>>::TYPE_NAME,
+        "BTreeMap<u8,Vec<bool>>"
+    );
+    assert_eq!(<HashSet<Box<i64>>>::TYPE_NAME, "HashSet<Box<i64>>");
+    assert_eq!(<(u32, String)>::TYPE_NAME, "(u32,String)");
+    assert_eq!(<[u8; 0]>::TYPE_NAME, "[u8;0]");
+    assert_eq!(<[(bool, char); 1024]>::TYPE_NAME, "[(bool,char);1024]");
+    assert_eq!(Echo::<[f64; 3]>::IDENTIFIER, "Echo<[f64;3]>");
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(result = "u32")]
+#[remote_message(reply)]
+struct Double(u32);
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(result = "u32")]
+#[remote_message(reply)]
+struct AddToTotal(u32);
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct Record(u32);
+
+#[derive(Message)]
+#[rtype(result = "Vec<u32>")]
+struct GetRecords;
+
+#[derive(RemoteActor)]
+#[remote_messages(Double, AddToTotal, Record)]
+struct Accumulator {
+    cluster: ClusterHandle,
+    total: u32,
+    records: Vec<u32>,
+}
+
+impl Accumulator {
+    fn new(cluster: ClusterHandle) -> Self {
+        Self {
+            cluster,
+            total: 0,
+            records: vec![],
+        }
+    }
+}
+
+impl Actor for Accumulator {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+impl Handler<Double> for Accumulator {
+    type Result = ResponseFuture<u32>;
+
+    fn handle(&mut self, msg: Double, _ctx: &mut Self::Context) -> Self::Result {
+        Box::pin(async move {
+            actix_rt::time::sleep(Duration::from_millis(10)).await;
+            msg.0 * 2
+        })
+    }
+}
+
+impl Handler<AddToTotal> for Accumulator {
+    type Result = ResponseActFuture<Self, u32>;
+
+    fn handle(&mut self, msg: AddToTotal, _ctx: &mut Self::Context) -> Self::Result {
+        Box::pin(
+            actix_rt::time::sleep(Duration::from_millis(10))
+                .into_actor(self)
+                .map(move |_, actor, _| {
+                    actor.total += msg.0;
+                    actor.total
+                }),
+        )
+    }
+}
+
+impl Handler<Record> for Accumulator {
+    type Result = ();
+
+    fn handle(&mut self, msg: Record, _ctx: &mut Self::Context) -> Self::Result {
+        self.records.push(msg.0);
+    }
+}
+
+impl Handler<GetRecords> for Accumulator {
+    type Result = MessageResult<GetRecords>;
+
+    fn handle(&mut self, _msg: GetRecords, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(self.records.clone())
+    }
+}
+
+#[actix_rt::test]
+async fn remote_messages_are_answered_by_future_responses() {
+    let cluster = crate::testkit::TestCluster::builder(2)
+        .in_memory(crate::MemoryNetwork::new())
+        .start()
+        .await;
+    Accumulator::new(cluster.handle(1).clone()).start();
+    actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+    let accumulator = cluster.remote_addr(0, 1, Accumulator::ACTOR_ID);
+    assert_eq!(accumulator.send(Double(21)).await, Ok(42));
+    assert_eq!(accumulator.send(AddToTotal(2)).await, Ok(2));
+    assert_eq!(accumulator.send(AddToTotal(3)).await, Ok(5));
+}
+
+#[actix_rt::test]
+async fn remote_messages_keep_their_order_with_local_messages() {
+    let cluster = crate::testkit::TestCluster::builder(1)
+        .in_memory(crate::MemoryNetwork::new())
+        .start()
+        .await;
+    let addr = Accumulator::new(cluster.handle(0).clone()).start();
+
+    addr.do_send(RemoteWrapper::new(RemoteAddr::default(), Record(1), None));
+    assert_eq!(addr.send(GetRecords).await.unwrap(), vec![1]);
+    addr.do_send(RemoteWrapper::new(RemoteAddr::default(), Record(2), None));
+    addr.do_send(Record(3));
+    assert_eq!(addr.send(GetRecords).await.unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn trace_context_traceparent_round_trip() {
+    let context = TraceContext::new_root();
+    let traceparent = context.to_traceparent();
+    assert_eq!(traceparent.len(), 55);
+    assert_eq!(TraceContext::from_traceparent(&traceparent), Some(context));
+
+    let parsed =
+        TraceContext::from_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .unwrap();
+    assert_eq!(parsed.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(parsed.span_id_hex(), "00f067aa0ba902b7");
+    assert!(parsed.is_sampled());
+}
+
+#[test]
+fn trace_context_rejects_invalid_traceparent() {
+    for traceparent in [
+        "",
+        "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e47-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+        "00-4bf92f3577b34da6a3ce929d0e0e473g-00f067aa0ba902b7-01",
+    ] {
+        assert_eq!(TraceContext::from_traceparent(traceparent), None);
+    }
+}
+
+#[test]
+fn remote_wrapper_continues_current_trace() {
+    let destination = RemoteAddr::new_from_id(
+        "127.0.0.1:1992".parse::<NodeAddr>().unwrap(),
+        "MyRemoteActor",
+    );
+    let parent = TraceContext::new_root();
+
+    let wrapper = TraceContext::scope(Some(parent), || {
+        RemoteWrapper::new(destination.clone(), MyRemoteMessage { value: 1.0 }, None)
+    });
+    let context = wrapper.trace_context.unwrap();
+    assert_eq!(context.trace_id, parent.trace_id);
+    assert_ne!(context.span_id, parent.span_id);
+    assert_eq!(TraceContext::current(), None);
+
+    let wrapper = RemoteWrapper::new(destination, MyRemoteMessage { value: 1.0 }, None);
+    assert_eq!(wrapper.trace_context.is_some(), cfg!(feature = "tracing"));
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, PartialEq)]
+struct Forward(u32);
+
+#[derive(RemoteActor)]
+#[remote_messages(Forward)]
+struct Relay {
+    cluster: ClusterHandle,
+    next: RemoteAddr,
+}
+
+impl Actor for Relay {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+impl Handler<Forward> for Relay {
+    type Result = ();
+
+    fn handle(&mut self, msg: Forward, _ctx: &mut Self::Context) -> Self::Result {
+        self.next.do_send(msg);
+    }
+}
+
+#[actix_rt::test]
+async fn trace_continues_across_remote_actors() {
+    let cluster = crate::testkit::TestCluster::builder(3)
+        .in_memory(crate::MemoryNetwork::new())
+        .start()
+        .await;
+    let mut probe = cluster.probe(2, "probe");
+    Relay {
+        cluster: cluster.handle(1).clone(),
+        next: cluster.remote_addr(1, 2, probe.id()),
+    }
+    .start();
+    actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+    let root = TraceContext::new_root();
+    let relay = cluster.remote_addr(0, 1, Relay::ACTOR_ID);
+    TraceContext::scope(Some(root), || relay.do_send(Forward(1)));
+
+    let received = probe.next(Duration::from_secs(1)).await.unwrap();
+    assert_eq!(received.identifier, Forward::IDENTIFIER);
+    let context = received.trace_context.unwrap();
+    assert_eq!(context.trace_id, root.trace_id);
+    assert_ne!(context.span_id, root.span_id);
+}
+
+#[test]
+fn remote_wrapper_header_carries_trace_context() {
+    #[derive(Serialize)]
+    struct LegacyRemoteWrapper {
+        destination: RemoteAddr,
+        identifier: String,
+        conversation_id: Option<uuid::Uuid>,
+    }
+
+    let destination = RemoteAddr::new_from_id(
+        "127.0.0.1:1992".parse::<NodeAddr>().unwrap(),
+        "MyRemoteActor",
+    );
+    let mut wrapper = RemoteWrapper::new(destination.clone(), MyRemoteMessage { value: 1.0 }, None);
+    wrapper.trace_context = Some(TraceContext::new_root());
+    let bytes = flexbuffers::to_vec(&wrapper).unwrap();
+    let received: RemoteWrapper = flexbuffers::from_slice(&bytes).unwrap();
+    assert_eq!(received.trace_context, wrapper.trace_context);
+
+    let legacy = LegacyRemoteWrapper {
+        destination,
+        identifier: wrapper.identifier.clone(),
+        conversation_id: None,
+    };
+    let bytes = flexbuffers::to_vec(&legacy).unwrap();
+    let received: RemoteWrapper = flexbuffers::from_slice(&bytes).unwrap();
+    assert_eq!(received.trace_context, None);
+}
@@ -0,0 +1,174 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+
+const TRACEPARENT_VERSION: &str = "00";
+const SAMPLED_FLAG: u8 = 0x01;
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = const { RefCell::new(None) };
+}
+
+/// Trace and span ids that are carried in the [RemoteWrapper](./struct.RemoteWrapper.html) header
+/// to keep the causal chain of messages across nodes.
+///
+/// The ids are compatible with the W3C Trace Context `traceparent` header, so traces can be exported.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Starts a new trace.
+    pub fn new_root() -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            trace_id: rng.gen::<u128>().max(1).to_be_bytes(),
+            span_id: rng.gen::<u64>().max(1).to_be_bytes(),
+            flags: SAMPLED_FLAG,
+        }
+    }
+
+    /// A new span in the same trace, having `self` as parent.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: rand::thread_rng().gen::<u64>().max(1).to_be_bytes(),
+            flags: self.flags,
+        }
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.flags & SAMPLED_FLAG == SAMPLED_FLAG
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        to_hex(&self.trace_id)
+    }
+
+    pub fn span_id_hex(&self) -> String {
+        to_hex(&self.span_id)
+    }
+
+    /// Formats the context as W3C `traceparent` header value.
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "{}-{}-{}-{:02x}",
+            TRACEPARENT_VERSION,
+            self.trace_id_hex(),
+            self.span_id_hex(),
+            self.flags
+        )
+    }
+
+    /// Parses a W3C `traceparent` header value. Returns `None` for malformed or all-zero ids.
+    pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        if version != TRACEPARENT_VERSION || parts.next().is_some() || flags.len() != 2 {
+            return None;
+        }
+
+        let mut context = Self {
+            trace_id: [0; 16],
+            span_id: [0; 8],
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        };
+        from_hex(trace_id, &mut context.trace_id)?;
+        from_hex(span_id, &mut context.span_id)?;
+        if context.trace_id == [0; 16] || context.span_id == [0; 8] {
+            return None;
+        }
+        Some(context)
+    }
+
+    /// The context of the message that is currently being handled on this thread, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| *current.borrow())
+    }
+
+    /// Runs `f` with `context` as the current context. Remote messages sent from within `f` become
+    /// children of `context`.
+    pub fn scope<R>(context: Option<Self>, f: impl FnOnce() -> R) -> R {
+        struct Reset(Option<TraceContext>);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                CURRENT.with(|current| *current.borrow_mut() = self.0);
+            }
+        }
+
+        let _reset = Reset(CURRENT.with(|current| current.replace(context)));
+        f()
+    }
+
+    /// Context for an outgoing message: a child of the current context. With the `tracing` feature
+    /// enabled, a new trace is started if there is no current context.
+    pub(crate) fn for_outgoing() -> Option<Self> {
+        match Self::current() {
+            Some(context) => Some(context.child()),
+            None if cfg!(feature = "tracing") => Some(Self::new_root()),
+            None => None,
+        }
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_traceparent())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str, bytes: &mut [u8]) -> Option<()> {
+    if hex.len() != bytes.len() * 2 || !hex.is_ascii() {
+        return None;
+    }
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(())
+}
+
+#[cfg(feature = "tracing")]
+pub type StageGuard = tracing::span::EnteredSpan;
+#[cfg(not(feature = "tracing"))]
+pub struct StageGuard;
+
+/// Enters a span for handling a received message as child of the sender's span.
+#[cfg(feature = "tracing")]
+pub(crate) fn enter_stage(
+    stage: &'static str,
+    identifier: &str,
+    parent: Option<&TraceContext>,
+    context: Option<&TraceContext>,
+) -> StageGuard {
+    tracing::debug_span!(
+        "telepathy.receive",
+        stage,
+        identifier,
+        trace_id = context.map(|c| c.trace_id_hex()).as_deref(),
+        span_id = context.map(|c| c.span_id_hex()).as_deref(),
+        parent_span_id = parent.map(|c| c.span_id_hex()).as_deref(),
+    )
+    .entered()
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn enter_stage(
+    _stage: &'static str,
+    _identifier: &str,
+    _parent: Option<&TraceContext>,
+    _context: Option<&TraceContext>,
+) -> StageGuard {
+    StageGuard
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Name of a type in the `ACTOR_ID` or `IDENTIFIER` of a generic [RemoteActor](./trait.RemoteActor.html) or
+/// [RemoteMessage](./trait.RemoteMessage.html), so that each instantiation, e.g. `Worker<u32>` and `Worker<String>`,
+/// is told apart.
+///
+/// The derives require it of all type parameters and implement it for the deriving type.
+/// The crate implements it for primitives, `String`, `Vec`, `Option`, `Box`, the maps and sets of `std::collections`,
+/// tuples of up to six elements and arrays. Other types, like plain data types, implement it by hand:
+///
+/// ```ignore
+/// impl TypeName for Temperature {
+///     const TYPE_NAME: &'static str = "weather.Temperature";
+/// }
+/// ```
+pub trait TypeName {
+    const TYPE_NAME: &'static str;
+}
+
+macro_rules! type_names {
+    ($($ty:ty),*) => {
+        $(
+            impl TypeName for $ty {
+                const TYPE_NAME: &'static str = stringify!($ty);
+            }
+        )*
+    };
+}
+
+type_names!(bool, char, String, ());
+type_names!(u8, u16, u32, u64, u128, usize);
+type_names!(i8, i16, i32, i64, i128, isize);
+type_names!(f32, f64);
+
+impl<T: TypeName> TypeName for Vec<T> {
+    const TYPE_NAME: &'static str = IdentifierBuf::concat(&["Vec<", T::TYPE_NAME, ">"]).as_str();
+}
+
+impl<T: TypeName> TypeName for Option<T> {
+    const TYPE_NAME: &'static str = IdentifierBuf::concat(&["Option<", T::TYPE_NAME, ">"]).as_str();
+}
+
+impl<T: TypeName> TypeName for Box<T> {
+    const TYPE_NAME: &'static str = IdentifierBuf::concat(&["Box<", T::TYPE_NAME, ">"]).as_str();
+}
+
+impl<T: TypeName> TypeName for HashSet<T> {
+    const TYPE_NAME: &'static str =
+        IdentifierBuf::concat(&["HashSet<", T::TYPE_NAME, ">"]).as_str();
+}
+
+impl<T: TypeName> TypeName for BTreeSet<T> {
+    const TYPE_NAME: &'static str =
+        IdentifierBuf::concat(&["BTreeSet<", T::TYPE_NAME, ">"]).as_str();
+}
+
+impl<K: TypeName, V: TypeName> TypeName for HashMap<K, V> {
+    const TYPE_NAME: &'static str =
+        IdentifierBuf::concat(&["HashMap<", K::TYPE_NAME, ",", V::TYPE_NAME, ">"]).as_str();
+}
+
+impl<K: TypeName, V: TypeName> TypeName for BTreeMap<K, V> {
+    const TYPE_NAME: &'static str =
+        IdentifierBuf::concat(&["BTreeMap<", K::TYPE_NAME, ",", V::TYPE_NAME, ">"]).as_str();
+}
+
+impl<T: TypeName, const N: usize> TypeName for [T; N] {
+    const TYPE_NAME: &'static str = IdentifierBuf::concat(&[
+        "[",
+        T::TYPE_NAME,
+        ";",
+        IdentifierBuf::number(N).as_str(),
+        "]",
+    ])
+    .as_str();
+}
+
+macro_rules! tuple_type_names {
+    ($(($first:ident, $($rest:ident),+)),*) => {
+        $(
+            impl<$first: TypeName, $($rest: TypeName),+> TypeName for ($first, $($rest),+) {
+                const TYPE_NAME: &'static str =
+                    IdentifierBuf::concat(&["(", $first::TYPE_NAME, $(",", $rest::TYPE_NAME,)+ ")"]).as_str();
+            }
+        )*
+    };
+}
+
+tuple_type_names!(
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
+
+/// Longest identifier that can be composed in const context.
+const MAX_IDENTIFIER_LEN: usize = 256;
+
+/// Composes identifiers of generic types in const context, since `concat!` only takes literals.
+pub struct IdentifierBuf {
+    bytes: [u8; MAX_IDENTIFIER_LEN],
+    len: usize,
+}
+
+impl IdentifierBuf {
+    pub const fn concat(parts: &[&str]) -> Self {
+        let mut bytes = [0; MAX_IDENTIFIER_LEN];
+        let mut len = 0;
+        let mut i = 0;
+        while i < parts.len() {
+            let part = parts[i].as_bytes();
+            assert!(
+                len + part.len() <= MAX_IDENTIFIER_LEN,
+                "identifier of generic type is too long"
+            );
+            let mut j = 0;
+            while j < part.len() {
+                bytes[len] = part[j];
+                len += 1;
+                j += 1;
+            }
+            i += 1;
+        }
+        Self { bytes, len }
+    }
+
+    /// Decimal representation of `number`, e.g. the length of an array.
+    pub const fn number(mut number: usize) -> Self {
+        let mut digits = [0; 20];
+        let mut count = 0;
+        loop {
+            digits[count] = b'0' + (number % 10) as u8;
+            count += 1;
+            number /= 10;
+            if number == 0 {
+                break;
+            }
+        }
+        let mut bytes = [0; MAX_IDENTIFIER_LEN];
+        let mut len = 0;
+        while len < count {
+            bytes[len] = digits[count - 1 - len];
+            len += 1;
+        }
+        Self { bytes, len }
+    }
+
+    pub const fn as_str(&self) -> &str {
+        match std::str::from_utf8(self.bytes.split_at(self.len).0) {
+            Ok(identifier) => identifier,
+            // only whole strings are concatenated
+            Err(_) => unreachable!(),
+        }
+    }
+}
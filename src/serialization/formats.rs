@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use super::{CustomSerialization, CustomSerializationError};
+
+/// Compact and fast serialization with [bincode](https://docs.rs/bincode), for clusters of Rust nodes only.
+///
+/// Like all non-self-describing formats, it does not support `#[serde(flatten)]` or untagged enums.
+#[cfg(feature = "bincode")]
+pub struct BincodeSerialization {}
+
+#[cfg(feature = "bincode")]
+#[allow(clippy::needless_maybe_sized)]
+impl CustomSerialization for BincodeSerialization {
+    fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, CustomSerializationError>
+    where
+        T: ?Sized + Serialize,
+    {
+        bincode::serialize(value).map_err(|_| CustomSerializationError)
+    }
+
+    fn deserialize<'a, T>(&self, s: &'a [u8]) -> Result<T, CustomSerializationError>
+    where
+        T: ?Sized + Deserialize<'a>,
+    {
+        bincode::deserialize(s).map_err(|_| CustomSerializationError)
+    }
+}
+
+/// JSON serialization with [serde_json](https://docs.rs/serde_json), readable by nodes in any language.
+#[cfg(feature = "serde_json")]
+pub struct JsonSerialization {}
+
+#[cfg(feature = "serde_json")]
+#[allow(clippy::needless_maybe_sized)]
+impl CustomSerialization for JsonSerialization {
+    fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, CustomSerializationError>
+    where
+        T: ?Sized + Serialize,
+    {
+        serde_json::to_vec(value).map_err(|_| CustomSerializationError)
+    }
+
+    fn deserialize<'a, T>(&self, s: &'a [u8]) -> Result<T, CustomSerializationError>
+    where
+        T: ?Sized + Deserialize<'a>,
+    {
+        serde_json::from_slice(s).map_err(|_| CustomSerializationError)
+    }
+}
+
+/// [MessagePack](https://msgpack.org) serialization with [rmp-serde](https://docs.rs/rmp-serde).
+///
+/// Structs are written as maps with field names, so that other MessagePack implementations can read them.
+#[cfg(feature = "rmp-serde")]
+pub struct MessagePackSerialization {}
+
+#[cfg(feature = "rmp-serde")]
+#[allow(clippy::needless_maybe_sized)]
+impl CustomSerialization for MessagePackSerialization {
+    fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, CustomSerializationError>
+    where
+        T: ?Sized + Serialize,
+    {
+        rmp_serde::to_vec_named(value).map_err(|_| CustomSerializationError)
+    }
+
+    fn deserialize<'a, T>(&self, s: &'a [u8]) -> Result<T, CustomSerializationError>
+    where
+        T: ?Sized + Deserialize<'a>,
+    {
+        rmp_serde::from_slice(s).map_err(|_| CustomSerializationError)
+    }
+}
+
+/// [CBOR](https://cbor.io) serialization with [ciborium](https://docs.rs/ciborium).
+///
+/// ciborium only deserializes owned data, so messages are read into a `ciborium::Value` first;
+/// borrowed fields like `&str` are not supported.
+#[cfg(feature = "ciborium")]
+pub struct CborSerialization {}
+
+#[cfg(feature = "ciborium")]
+#[allow(clippy::needless_maybe_sized)]
+impl CustomSerialization for CborSerialization {
+    fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, CustomSerializationError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut vec = vec![];
+        ciborium::into_writer(value, &mut vec).map_err(|_| CustomSerializationError)?;
+        Ok(vec)
+    }
+
+    fn deserialize<'a, T>(&self, s: &'a [u8]) -> Result<T, CustomSerializationError>
+    where
+        T: ?Sized + Deserialize<'a>,
+    {
+        ciborium::from_reader::<ciborium::Value, _>(s)
+            .map_err(|_| CustomSerializationError)?
+            .deserialized()
+            .map_err(|_| CustomSerializationError)
+    }
+}
@@ -1,9 +1,25 @@
+#[cfg(any(
+    feature = "bincode",
+    feature = "serde_json",
+    feature = "rmp-serde",
+    feature = "ciborium"
+))]
+mod formats;
 #[cfg(test)]
 mod tests;
 use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "bincode")]
+pub use self::formats::BincodeSerialization;
+#[cfg(feature = "ciborium")]
+pub use self::formats::CborSerialization;
+#[cfg(feature = "serde_json")]
+pub use self::formats::JsonSerialization;
+#[cfg(feature = "rmp-serde")]
+pub use self::formats::MessagePackSerialization;
+
 /// Provides template for creating custom serializer
 ///
 /// # Example Used in DefaultSerialization
@@ -38,12 +54,18 @@ use serde::{Deserialize, Serialize};
 /// ```
 ///
 /// # Telling Actix-Telepathy to use Custom Serializer
-/// - create a `telepathy.yaml` file in your crates root
-/// - add your custom struct's name:
-/// ```yaml
-/// custom_serializer: "MySerializer"
+/// - per message, name it in the `serializer` attribute next to the `RemoteMessage` derive:
+/// ```ignore
+/// #[derive(RemoteMessage, Serialize, Deserialize)]
+/// #[serializer(MySerializer)]
+/// struct MyMessage {}
 /// ```
-/// - import your custom struct whenever you are using the `RemoteMessage` derive macro.
+/// - for all messages without the attribute, enable one of the cargo features `default-serializer-bincode`,
+///   `default-serializer-json`, `default-serializer-msgpack` or `default-serializer-cbor`, which select the
+///   [MessageSerialization](./type.MessageSerialization.html). All nodes of a cluster need the same one.
+/// - the `custom_serializer` of a `telepathy.yaml` next to the crate's `Cargo.toml` is deprecated
+///   and reported as warning; use the attribute or a feature instead.
+#[allow(clippy::needless_maybe_sized)]
 pub trait CustomSerialization {
     fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, CustomSerializationError>
     where
@@ -74,6 +96,48 @@ impl fmt::Debug for CustomSerializationError {
 /// The default de/serializer is the Rust version of Flatbuffers - Flexbuffers.
 pub struct DefaultSerialization {}
 
+#[cfg(any(
+    all(
+        feature = "default-serializer-bincode",
+        any(
+            feature = "default-serializer-json",
+            feature = "default-serializer-msgpack",
+            feature = "default-serializer-cbor"
+        )
+    ),
+    all(
+        feature = "default-serializer-json",
+        any(
+            feature = "default-serializer-msgpack",
+            feature = "default-serializer-cbor"
+        )
+    ),
+    all(
+        feature = "default-serializer-msgpack",
+        feature = "default-serializer-cbor"
+    )
+))]
+compile_error!("At most one of the `default-serializer-*` features can be enabled");
+
+/// Serialization of the messages deriving `RemoteMessage` that do not choose another one,
+/// selected by the `default-serializer-*` features.
+#[cfg(not(any(
+    feature = "default-serializer-bincode",
+    feature = "default-serializer-json",
+    feature = "default-serializer-msgpack",
+    feature = "default-serializer-cbor"
+)))]
+pub type MessageSerialization = DefaultSerialization;
+#[cfg(feature = "default-serializer-bincode")]
+pub type MessageSerialization = BincodeSerialization;
+#[cfg(feature = "default-serializer-json")]
+pub type MessageSerialization = JsonSerialization;
+#[cfg(feature = "default-serializer-msgpack")]
+pub type MessageSerialization = MessagePackSerialization;
+#[cfg(feature = "default-serializer-cbor")]
+pub type MessageSerialization = CborSerialization;
+
+#[allow(clippy::needless_maybe_sized)]
 impl CustomSerialization for DefaultSerialization {
     fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, CustomSerializationError>
     where
@@ -1,4 +1,4 @@
-use crate::{CustomSerialization, DefaultSerialization};
+use crate::{CustomSerialization, CustomSerializationError, DefaultSerialization, RemoteMessage};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -20,3 +20,158 @@ fn default_serializer_correctly_ser_des() {
 
     assert_eq!(deserialized_value.value, test_usize);
 }
+
+/// Flexbuffers behind a tag byte
+struct TaggedSerialization {}
+
+const TAG: u8 = 42;
+
+#[allow(clippy::needless_maybe_sized)]
+impl CustomSerialization for TaggedSerialization {
+    fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, CustomSerializationError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut bytes = vec![TAG];
+        bytes.extend(DefaultSerialization {}.serialize(value)?);
+        Ok(bytes)
+    }
+
+    fn deserialize<'a, T>(&self, s: &'a [u8]) -> Result<T, CustomSerializationError>
+    where
+        T: ?Sized + Deserialize<'a>,
+    {
+        match s.split_first() {
+            Some((&TAG, rest)) => DefaultSerialization {}.deserialize(rest),
+            _ => Err(CustomSerializationError),
+        }
+    }
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, PartialEq)]
+#[serializer(TaggedSerialization)]
+struct TaggedMessage {
+    pub value: usize,
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct UntaggedMessage {
+    pub value: usize,
+}
+
+#[test]
+fn serializer_attribute_selects_serializer() {
+    let message = TaggedMessage { value: 8 };
+    let serialized = message.get_serializer().serialize(&message).unwrap();
+    assert_eq!(serialized[0], TAG);
+    let deserialized: TaggedMessage = TaggedMessage::generate_serializer()
+        .deserialize(&serialized)
+        .unwrap();
+    assert_eq!(deserialized, message);
+
+    let message = UntaggedMessage { value: 8 };
+    let serialized = message.get_serializer().serialize(&message).unwrap();
+    assert_eq!(
+        serialized,
+        crate::MessageSerialization {}.serialize(&message).unwrap()
+    );
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, PartialEq)]
+#[with_source(source)]
+struct Order {
+    id: u64,
+    items: Vec<(String, f32)>,
+    note: Option<String>,
+    priority: Priority,
+    source: crate::RemoteAddr,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Priority {
+    Normal,
+    Express { until: u32 },
+}
+
+/// The messages every serialization has to round-trip.
+fn assert_round_trips<S: CustomSerialization>(serialization: S) {
+    let order = Order {
+        id: 7,
+        items: vec![("apple".to_string(), 0.5), ("pear".to_string(), 1.25)],
+        note: None,
+        priority: Priority::Express { until: 1992 },
+        source: crate::RemoteAddr::default(),
+    };
+    let serialized = serialization.serialize(&order).unwrap();
+    let deserialized: Order = serialization.deserialize(&serialized).unwrap();
+    assert_eq!(deserialized, order);
+
+    let value = TestValue { value: usize::MAX };
+    let serialized = serialization.serialize(&value).unwrap();
+    let deserialized: TestValue = serialization.deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.value, usize::MAX);
+
+    assert!(serialization
+        .deserialize::<Order>(&serialized[..serialized.len() / 2])
+        .is_err());
+}
+
+#[test]
+fn default_serialization_round_trips() {
+    assert_round_trips(DefaultSerialization {});
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn bincode_serialization_round_trips() {
+    assert_round_trips(crate::BincodeSerialization {});
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn json_serialization_round_trips() {
+    assert_round_trips(crate::JsonSerialization {});
+}
+
+#[cfg(feature = "rmp-serde")]
+#[test]
+fn message_pack_serialization_round_trips() {
+    assert_round_trips(crate::MessagePackSerialization {});
+}
+
+#[cfg(feature = "ciborium")]
+#[test]
+fn cbor_serialization_round_trips() {
+    assert_round_trips(crate::CborSerialization {});
+}
+
+#[cfg(feature = "serde_json")]
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[serializer(crate::JsonSerialization)]
+struct JsonMessage {
+    pub value: usize,
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn serializer_attribute_takes_built_in_serializers() {
+    let message = JsonMessage { value: 8 };
+    let serialized = message.get_serializer().serialize(&message).unwrap();
+    assert_eq!(serialized, br#"{"value":8}"#);
+}
+
+#[test]
+fn message_serialization_is_selected_by_feature() {
+    let expected = if cfg!(feature = "default-serializer-bincode") {
+        "BincodeSerialization"
+    } else if cfg!(feature = "default-serializer-json") {
+        "JsonSerialization"
+    } else if cfg!(feature = "default-serializer-msgpack") {
+        "MessagePackSerialization"
+    } else if cfg!(feature = "default-serializer-cbor") {
+        "CborSerialization"
+    } else {
+        "DefaultSerialization"
+    };
+    assert!(std::any::type_name::<crate::MessageSerialization>().ends_with(expected));
+}
@@ -0,0 +1,257 @@
+//! Counters for network traffic and messaging.
+//!
+//! With the `metrics` feature, all values are recorded via the [metrics](https://docs.rs/metrics) facade,
+//! so any installed recorder (e.g. a Prometheus exporter) picks them up. Additionally, the values are
+//! aggregated in-process and can be fetched with [metrics_snapshot](./fn.metrics_snapshot.html).
+//! Without the feature, recording compiles to nothing.
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests;
+
+use crate::NodeAddr;
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use parking_lot::Mutex;
+#[cfg(feature = "metrics")]
+use std::collections::HashMap;
+
+#[cfg(feature = "metrics")]
+static SNAPSHOT: Lazy<Mutex<MetricsSnapshot>> =
+    Lazy::new(|| Mutex::new(MetricsSnapshot::default()));
+
+/// Traffic to and from a single peer. Bytes count the serialized message payloads.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerMetrics {
+    pub frames_sent: u64,
+    pub frames_received: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub handshakes: u64,
+    pub declined_handshakes: u64,
+    pub reconnects: u64,
+}
+
+/// Aggregated metrics of this process since startup.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub peers: HashMap<NodeAddr, PeerMetrics>,
+    pub sent_by_identifier: HashMap<String, u64>,
+    pub received_by_identifier: HashMap<String, u64>,
+    pub dropped_by_identifier: HashMap<String, u64>,
+    pub serializations: u64,
+    pub serialization_time: Duration,
+    /// Messages waiting in the mailboxes of all NetworkInterfaces
+    pub network_interface_backlog: u64,
+    /// Frames waiting to be written to the connections
+    pub writer_backlog: u64,
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsSnapshot {
+    /// Metrics of `peer`, empty if there was no traffic yet.
+    pub fn peer(&self, peer: &NodeAddr) -> PeerMetrics {
+        self.peers.get(peer).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub fn metrics_snapshot() -> MetricsSnapshot {
+    SNAPSHOT.lock().clone()
+}
+
+#[cfg(feature = "metrics")]
+fn update_peer(peer: &NodeAddr, update: impl FnOnce(&mut PeerMetrics)) {
+    update(peer_metrics(&mut SNAPSHOT.lock(), peer))
+}
+
+/// Looks the peer up before cloning its address, frames of known peers do not allocate.
+#[cfg(feature = "metrics")]
+fn peer_metrics<'a>(snapshot: &'a mut MetricsSnapshot, peer: &NodeAddr) -> &'a mut PeerMetrics {
+    if !snapshot.peers.contains_key(peer) {
+        snapshot.peers.insert(peer.clone(), PeerMetrics::default());
+    }
+    snapshot.peers.get_mut(peer).unwrap()
+}
+
+#[cfg(feature = "metrics")]
+fn count(counts: &mut HashMap<String, u64>, identifier: &str) {
+    match counts.get_mut(identifier) {
+        Some(count) => *count += 1,
+        None => {
+            counts.insert(identifier.to_string(), 1);
+        }
+    }
+}
+
+/// A frame was written to `peer`; `message` holds identifier and payload size of remote messages.
+pub(crate) fn record_frame_sent(peer: &NodeAddr, message: Option<(&str, usize)>) {
+    #[cfg(feature = "metrics")]
+    {
+        let peer_label = peer.to_string();
+        metrics::counter!("telepathy_frames_sent_total", "peer" => peer_label.clone()).increment(1);
+        if let Some((identifier, bytes)) = message {
+            metrics::counter!("telepathy_messages_sent_total", "peer" => peer_label.clone())
+                .increment(1);
+            metrics::counter!("telepathy_bytes_sent_total", "peer" => peer_label)
+                .increment(bytes as u64);
+            metrics::counter!("telepathy_remote_messages_total", "identifier" => identifier.to_string(), "direction" => "sent")
+                .increment(1);
+        }
+        // one lock per frame, the NetworkInterfaces of all peers record here
+        let mut snapshot = SNAPSHOT.lock();
+        if let Some((identifier, _)) = message {
+            count(&mut snapshot.sent_by_identifier, identifier);
+        }
+        let metrics = peer_metrics(&mut snapshot, peer);
+        metrics.frames_sent += 1;
+        if let Some((_, bytes)) = message {
+            metrics.messages_sent += 1;
+            metrics.bytes_sent += bytes as u64;
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (peer, message);
+}
+
+/// A frame was read from `peer`; `message` holds identifier and payload size of remote messages.
+pub(crate) fn record_frame_received(peer: &NodeAddr, message: Option<(&str, usize)>) {
+    #[cfg(feature = "metrics")]
+    {
+        let peer_label = peer.to_string();
+        metrics::counter!("telepathy_frames_received_total", "peer" => peer_label.clone())
+            .increment(1);
+        if let Some((identifier, bytes)) = message {
+            metrics::counter!("telepathy_messages_received_total", "peer" => peer_label.clone())
+                .increment(1);
+            metrics::counter!("telepathy_bytes_received_total", "peer" => peer_label)
+                .increment(bytes as u64);
+            metrics::counter!("telepathy_remote_messages_total", "identifier" => identifier.to_string(), "direction" => "received")
+                .increment(1);
+        }
+        // one lock per frame, the NetworkInterfaces of all peers record here
+        let mut snapshot = SNAPSHOT.lock();
+        if let Some((identifier, _)) = message {
+            count(&mut snapshot.received_by_identifier, identifier);
+        }
+        let metrics = peer_metrics(&mut snapshot, peer);
+        metrics.frames_received += 1;
+        if let Some((_, bytes)) = message {
+            metrics.messages_received += 1;
+            metrics.bytes_received += bytes as u64;
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (peer, message);
+}
+
+pub(crate) fn record_handshake(peer: &NodeAddr, approved: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        let result = if approved { "approved" } else { "declined" };
+        metrics::counter!("telepathy_handshakes_total", "peer" => peer.to_string(), "result" => result)
+            .increment(1);
+        update_peer(peer, |metrics| {
+            if approved {
+                metrics.handshakes += 1
+            } else {
+                metrics.declined_handshakes += 1
+            }
+        });
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (peer, approved);
+}
+
+pub(crate) fn record_reconnect(peer: &NodeAddr) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!("telepathy_reconnects_total", "peer" => peer.to_string()).increment(1);
+        update_peer(peer, |metrics| metrics.reconnects += 1);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = peer;
+}
+
+/// A received message could not be delivered to a local actor, or a reliable message was given up.
+pub(crate) fn record_dropped(identifier: &str) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!("telepathy_dropped_messages_total", "identifier" => identifier.to_string())
+            .increment(1);
+        count(&mut SNAPSHOT.lock().dropped_by_identifier, identifier);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = identifier;
+}
+
+pub(crate) fn record_serialization(duration: Duration) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::histogram!("telepathy_serialization_seconds").record(duration.as_secs_f64());
+        let mut snapshot = SNAPSHOT.lock();
+        snapshot.serializations += 1;
+        snapshot.serialization_time += duration;
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = duration;
+}
+
+/// Mailboxes whose backlog is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Queue {
+    NetworkInterface,
+    Writer,
+}
+
+impl Queue {
+    #[cfg(feature = "metrics")]
+    fn name(self) -> &'static str {
+        match self {
+            Queue::NetworkInterface => "network_interface",
+            Queue::Writer => "writer",
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    fn update(self, update: impl FnOnce(&mut u64)) {
+        let mut snapshot = SNAPSHOT.lock();
+        match self {
+            Queue::NetworkInterface => update(&mut snapshot.network_interface_backlog),
+            Queue::Writer => update(&mut snapshot.writer_backlog),
+        }
+    }
+}
+
+/// Counts a message in the backlog of `queue` until it is dropped, i.e. handled or discarded
+/// with the mailbox.
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+pub(crate) struct Backlog(Queue);
+
+impl Backlog {
+    pub(crate) fn new(queue: Queue) -> Self {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!("telepathy_queue_depth", "queue" => queue.name()).increment(1.0);
+            queue.update(|backlog| *backlog += 1);
+        }
+        Self(queue)
+    }
+}
+
+impl Drop for Backlog {
+    fn drop(&mut self) {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!("telepathy_queue_depth", "queue" => self.0.name()).decrement(1.0);
+            self.0
+                .update(|backlog| *backlog = backlog.saturating_sub(1));
+        }
+    }
+}
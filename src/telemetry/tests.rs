@@ -0,0 +1,92 @@
+use crate::telemetry::*;
+use crate::{
+    AddrRepresentation, AddrResolver, Node, NodeAddr, RemoteAddr, RemoteMessage, RemoteWrapper,
+};
+use actix::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct MetricsTestMessage {
+    value: u32,
+}
+
+fn peer(port: u16) -> NodeAddr {
+    format!("127.0.0.1:{}", port).parse().unwrap()
+}
+
+#[test]
+fn records_per_peer_traffic() {
+    let peer = peer(10001);
+    record_frame_sent(&peer, None);
+    record_frame_sent(&peer, Some(("MetricsTestSent", 10)));
+    record_frame_received(&peer, Some(("MetricsTestReceived", 7)));
+    record_handshake(&peer, true);
+    record_handshake(&peer, false);
+    record_reconnect(&peer);
+
+    let snapshot = metrics_snapshot();
+    assert_eq!(
+        snapshot.peer(&peer),
+        PeerMetrics {
+            frames_sent: 2,
+            frames_received: 1,
+            messages_sent: 1,
+            messages_received: 1,
+            bytes_sent: 10,
+            bytes_received: 7,
+            handshakes: 1,
+            declined_handshakes: 1,
+            reconnects: 1,
+        }
+    );
+    assert_eq!(snapshot.sent_by_identifier.get("MetricsTestSent"), Some(&1));
+    assert_eq!(
+        snapshot.received_by_identifier.get("MetricsTestReceived"),
+        Some(&1)
+    );
+    assert_eq!(snapshot.peer(&self::peer(10002)), PeerMetrics::default());
+}
+
+#[test]
+fn records_serialization_time() {
+    let before = metrics_snapshot().serializations;
+    let _wrapper = RemoteWrapper::new(
+        RemoteAddr::new_from_id(peer(10003), "MetricsTestActor"),
+        MetricsTestMessage { value: 1 },
+        None,
+    );
+    assert!(metrics_snapshot().serializations > before);
+}
+
+#[actix_rt::test]
+async fn records_dropped_messages() {
+    let wrapper = RemoteWrapper::new(
+        RemoteAddr::new(
+            Node::new(peer(10004), None),
+            AddrRepresentation::Key("MetricsTestUnknown".to_string()),
+        ),
+        MetricsTestMessage { value: 1 },
+        None,
+    );
+    AddrResolver::from_registry().send(wrapper).await.unwrap();
+    assert_eq!(
+        metrics_snapshot()
+            .dropped_by_identifier
+            .get(MetricsTestMessage::IDENTIFIER),
+        Some(&1)
+    );
+}
+
+#[test]
+fn backlog_counts_queued_messages_until_dropped() {
+    // other tests may hold messages in their queues at the same time
+    let queued = vec![
+        Backlog::new(Queue::NetworkInterface),
+        Backlog::new(Queue::Writer),
+        Backlog::new(Queue::Writer),
+    ];
+    let snapshot = metrics_snapshot();
+    assert!(snapshot.network_interface_backlog >= 1);
+    assert!(snapshot.writer_backlog >= 2);
+    drop(queued);
+}
@@ -0,0 +1,91 @@
+use crate::{ClusterHandle, ClusterListener, ClusterLog, Node, NodeAddr};
+use actix::prelude::*;
+use actix_broker::BrokerSubscribe;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::timeout;
+
+/// Forwards the [ClusterLog](./enum.ClusterLog.html) messages it receives to [ClusterEvents](./struct.ClusterEvents.html).
+pub struct TestClusterListener {
+    sender: UnboundedSender<ClusterLog>,
+    subscribe_system: bool,
+}
+
+impl Actor for TestClusterListener {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if self.subscribe_system {
+            self.subscribe_system_async::<ClusterLog>(ctx);
+        }
+    }
+}
+
+impl Handler<ClusterLog> for TestClusterListener {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClusterLog, _ctx: &mut Self::Context) -> Self::Result {
+        let _ = self.sender.send(msg);
+    }
+}
+
+impl ClusterListener for TestClusterListener {}
+
+/// Membership changes of one node, recorded by a [TestClusterListener](./struct.TestClusterListener.html).
+pub struct ClusterEvents {
+    receiver: UnboundedReceiver<ClusterLog>,
+    _listener: Addr<TestClusterListener>,
+}
+
+impl ClusterEvents {
+    /// Records the events of the cluster started with [Cluster::new](./struct.Cluster.html#method.new).
+    pub fn system() -> Self {
+        Self::start(true)
+    }
+
+    pub fn of(cluster: &ClusterHandle) -> Self {
+        let events = Self::start(false);
+        cluster.subscribe(events._listener.clone().recipient());
+        events
+    }
+
+    fn start(subscribe_system: bool) -> Self {
+        let (sender, receiver) = unbounded_channel();
+        let listener = TestClusterListener {
+            sender,
+            subscribe_system,
+        }
+        .start();
+        Self {
+            receiver,
+            _listener: listener,
+        }
+    }
+
+    /// The next event, if it happens within `wait`.
+    pub async fn next(&mut self, wait: Duration) -> Option<ClusterLog> {
+        timeout(wait, self.receiver.recv()).await.ok().flatten()
+    }
+
+    /// Panics if no member joins within `wait`.
+    pub async fn expect_new_member(&mut self, wait: Duration) -> Node {
+        match self.next(wait).await {
+            Some(ClusterLog::NewMember(node)) => node,
+            Some(ClusterLog::MemberLeft(addr)) => {
+                panic!("Expected a new member, but {} left", addr)
+            }
+            None => panic!("Expected a new member within {:?}", wait),
+        }
+    }
+
+    /// Panics if no member leaves within `wait`.
+    pub async fn expect_member_left(&mut self, wait: Duration) -> NodeAddr {
+        match self.next(wait).await {
+            Some(ClusterLog::MemberLeft(addr)) => addr,
+            Some(ClusterLog::NewMember(node)) => {
+                panic!("Expected a member to leave, but {} joined", node.addr)
+            }
+            None => panic!("Expected a member to leave within {:?}", wait),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::testkit::{free_local_addrs, ClusterEvents, Probe};
+use crate::{
+    ClusterHandle, ClusterLog, ConnectionProtocol, FaultController, FaultyTransport, MemoryNetwork,
+    Node, NodeAddr, RemoteAddr, TcpTransport,
+};
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::timeout;
+
+const CONVERGENCE_TIMEOUT: Duration = Duration::from_secs(10);
+const MEMORY_BASE_PORT: u16 = 20000;
+
+/// Configures a [TestCluster](./struct.TestCluster.html).
+pub struct TestClusterBuilder {
+    size: usize,
+    connection_protocol: ConnectionProtocol,
+    network: Option<MemoryNetwork>,
+    socket_dir: Option<PathBuf>,
+    faults: Option<FaultController>,
+    convergence_timeout: Duration,
+}
+
+impl TestClusterBuilder {
+    pub fn connection_protocol(mut self, connection_protocol: ConnectionProtocol) -> Self {
+        self.connection_protocol = connection_protocol;
+        self
+    }
+
+    /// Connects the nodes via `network` instead of TCP.
+    pub fn in_memory(mut self, network: MemoryNetwork) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// The nodes listen on Unix domain sockets in `dir` instead of TCP ports.
+    pub fn unix_sockets<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.socket_dir = Some(dir.into());
+        self
+    }
+
+    /// Injects the faults of `controller` into the messages between the nodes.
+    pub fn faults(mut self, controller: FaultController) -> Self {
+        self.faults = Some(controller);
+        self
+    }
+
+    pub fn convergence_timeout(mut self, convergence_timeout: Duration) -> Self {
+        self.convergence_timeout = convergence_timeout;
+        self
+    }
+
+    /// Starts the nodes with the first node as seed and waits until every node knows all others.
+    /// Panics if the cluster does not converge in time.
+    pub async fn start(self) -> TestCluster {
+        let addrs: Vec<NodeAddr> = match (&self.socket_dir, &self.network) {
+            (Some(dir), _) => (0..self.size)
+                .map(|i| NodeAddr::unix(dir.join(format!("node-{}.sock", i))))
+                .collect(),
+            (None, Some(_)) => (0..self.size as u16)
+                .map(|i| SocketAddr::from(([127, 0, 0, 1], MEMORY_BASE_PORT + i)).into())
+                .collect(),
+            (None, None) => free_local_addrs(self.size)
+                .into_iter()
+                .map(NodeAddr::from)
+                .collect(),
+        };
+
+        let mut handles = vec![];
+        let mut events = vec![];
+        for addr in addrs.iter() {
+            let seed_nodes = if *addr == addrs[0] {
+                vec![]
+            } else {
+                vec![addrs[0].clone()]
+            };
+            let handle = self.start_node(addr.clone(), seed_nodes);
+            events.push(ClusterEvents::of(&handle));
+            handles.push(handle);
+        }
+
+        let mut cluster = TestCluster {
+            addrs,
+            handles,
+            events,
+            members: HashMap::new(),
+        };
+        cluster.await_convergence(self.convergence_timeout).await;
+        cluster
+    }
+
+    fn start_node(&self, addr: NodeAddr, seed_nodes: Vec<NodeAddr>) -> ClusterHandle {
+        let protocol = self.connection_protocol;
+        match (&self.network, &self.faults) {
+            (None, None) => {
+                ClusterHandle::new_with_transport(addr, seed_nodes, protocol, TcpTransport)
+            }
+            (None, Some(faults)) => ClusterHandle::new_with_transport(
+                addr,
+                seed_nodes,
+                protocol,
+                FaultyTransport::new(TcpTransport, faults.clone()),
+            ),
+            (Some(network), None) => {
+                ClusterHandle::new_with_transport(addr, seed_nodes, protocol, network.clone())
+            }
+            (Some(network), Some(faults)) => ClusterHandle::new_with_transport(
+                addr,
+                seed_nodes,
+                protocol,
+                FaultyTransport::new(network.clone(), faults.clone()),
+            ),
+        }
+    }
+}
+
+/// Multiple cluster nodes running in the current actix system for tests, see
+/// [ClusterHandle](./struct.ClusterHandle.html).
+pub struct TestCluster {
+    addrs: Vec<NodeAddr>,
+    handles: Vec<ClusterHandle>,
+    events: Vec<ClusterEvents>,
+    /// Members by (node index, member address)
+    members: HashMap<(usize, NodeAddr), Node>,
+}
+
+impl TestCluster {
+    /// `size` nodes connected via TCP on free local ports.
+    pub fn builder(size: usize) -> TestClusterBuilder {
+        TestClusterBuilder {
+            size,
+            connection_protocol: ConnectionProtocol::SingleSeed,
+            network: None,
+            socket_dir: None,
+            faults: None,
+            convergence_timeout: CONVERGENCE_TIMEOUT,
+        }
+    }
+
+    pub async fn start(size: usize) -> Self {
+        Self::builder(size).start().await
+    }
+
+    async fn await_convergence(&mut self, wait: Duration) {
+        let size = self.addrs.len();
+        let joined = join_all(self.events.iter_mut().map(|events| async move {
+            let mut members = HashMap::new();
+            while members.len() + 1 < size {
+                match events.next(wait).await {
+                    Some(ClusterLog::NewMember(node)) => {
+                        members.insert(node.addr.clone(), node);
+                    }
+                    Some(ClusterLog::MemberLeft(addr)) => {
+                        members.remove(&addr);
+                    }
+                    None => break,
+                }
+            }
+            members
+        }));
+        let members = timeout(wait, joined)
+            .await
+            .unwrap_or_else(|_| panic!("Cluster did not converge within {:?}", wait));
+
+        for (i, node_members) in members.into_iter().enumerate() {
+            assert_eq!(
+                node_members.len() + 1,
+                size,
+                "Node {} did not see all members within {:?}",
+                self.addrs[i],
+                wait
+            );
+            for (addr, node) in node_members {
+                self.members.insert((i, addr), node);
+            }
+        }
+    }
+
+    pub fn addrs(&self) -> &[NodeAddr] {
+        &self.addrs
+    }
+
+    pub fn addr(&self, i: usize) -> NodeAddr {
+        self.addrs[i].clone()
+    }
+
+    pub fn handle(&self, i: usize) -> &ClusterHandle {
+        &self.handles[i]
+    }
+
+    /// Membership changes of node `i` after the cluster converged.
+    pub fn events(&mut self, i: usize) -> &mut ClusterEvents {
+        &mut self.events[i]
+    }
+
+    /// Node `to` as known by node `from`.
+    pub fn node(&self, from: usize, to: usize) -> Node {
+        self.members[&(from, self.addrs[to].clone())].clone()
+    }
+
+    /// Address of the actor registered under `id` on node `to`, for sending from node `from`.
+    pub fn remote_addr(&self, from: usize, to: usize, id: &str) -> RemoteAddr {
+        self.node(from, to).get_remote_addr(id.to_string())
+    }
+
+    /// Starts a [Probe](./struct.Probe.html) registered under `id` on node `i`.
+    pub fn probe(&self, i: usize, id: &str) -> Probe {
+        Probe::start(self.handle(i), id)
+    }
+}
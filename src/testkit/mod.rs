@@ -0,0 +1,22 @@
+use std::net::{SocketAddr, TcpListener};
+
+pub use self::cluster_listener::{ClusterEvents, TestClusterListener};
+pub use self::harness::{TestCluster, TestClusterBuilder};
+pub use self::probe::Probe;
+
+mod cluster_listener;
+mod harness;
+mod probe;
+#[cfg(test)]
+mod tests;
+
+/// `n` distinct local addresses with ports that are free at the time of the call.
+pub fn free_local_addrs(n: usize) -> Vec<SocketAddr> {
+    let listeners: Vec<TcpListener> = (0..n)
+        .map(|_| TcpListener::bind("127.0.0.1:0").expect("No free local port"))
+        .collect();
+    listeners
+        .iter()
+        .map(|listener| listener.local_addr().unwrap())
+        .collect()
+}
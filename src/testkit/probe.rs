@@ -0,0 +1,104 @@
+use crate::{AddrRequest, ClusterHandle, CustomSerialization, RemoteMessage, RemoteWrapper};
+use actix::prelude::*;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::timeout;
+
+struct ProbeActor {
+    sender: UnboundedSender<RemoteWrapper>,
+}
+
+impl Actor for ProbeActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<RemoteWrapper> for ProbeActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: RemoteWrapper, _ctx: &mut Self::Context) -> Self::Result {
+        let _ = self.sender.send(msg);
+    }
+}
+
+/// Receives the remote messages sent to `id` on one node, so tests can assert on them.
+///
+/// Other nodes address the probe via `node.get_remote_addr(id)`.
+pub struct Probe {
+    id: String,
+    receiver: UnboundedReceiver<RemoteWrapper>,
+    received: Vec<String>,
+    _actor: Addr<ProbeActor>,
+}
+
+impl Probe {
+    pub fn start(cluster: &ClusterHandle, id: &str) -> Self {
+        let (sender, receiver) = unbounded_channel();
+        let actor = ProbeActor { sender }.start();
+        cluster.addr_resolver().do_send(AddrRequest::Register(
+            actor.clone().recipient(),
+            id.to_string(),
+        ));
+        Self {
+            id: id.to_string(),
+            receiver,
+            received: vec![],
+            _actor: actor,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Identifiers of all messages taken from the probe so far.
+    pub fn received(&self) -> &[String] {
+        &self.received
+    }
+
+    /// The next message, if one arrives within `wait`.
+    pub async fn next(&mut self, wait: Duration) -> Option<RemoteWrapper> {
+        let wrapper = timeout(wait, self.receiver.recv()).await.ok().flatten()?;
+        self.received.push(wrapper.identifier.clone());
+        Some(wrapper)
+    }
+
+    /// Panics if the next message is not an `M` or does not arrive within `wait`.
+    pub async fn expect_message<M>(&mut self, wait: Duration) -> M
+    where
+        M: RemoteMessage + DeserializeOwned,
+    {
+        let wrapper = match self.next(wait).await {
+            Some(wrapper) => wrapper,
+            None => panic!(
+                "Probe '{}' expected {} within {:?}",
+                self.id,
+                M::IDENTIFIER,
+                wait
+            ),
+        };
+        assert_eq!(
+            wrapper.identifier,
+            M::IDENTIFIER,
+            "Probe '{}' received an unexpected message",
+            self.id
+        );
+        let mut message: M = M::generate_serializer()
+            .deserialize(&wrapper.message_buffer)
+            .unwrap_or_else(|_| panic!("Cannot deserialize {}", M::IDENTIFIER));
+        if let Some(source) = wrapper.source {
+            message.set_source(source);
+        }
+        message
+    }
+
+    /// Panics if a message arrives within `wait`.
+    pub async fn expect_no_message(&mut self, wait: Duration) {
+        if let Some(wrapper) = self.next(wait).await {
+            panic!(
+                "Probe '{}' expected no message, but received {}",
+                self.id, wrapper.identifier
+            );
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::testkit::TestCluster;
+use crate::{ConnectionProtocol, MemoryNetwork, RemoteMessage};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug, PartialEq)]
+struct Ping(u32);
+
+#[derive(RemoteMessage, Serialize, Deserialize, Debug)]
+struct Pong;
+
+const WAIT: Duration = Duration::from_secs(1);
+
+#[actix_rt::test]
+async fn test_cluster_converges_via_tcp() {
+    let cluster = TestCluster::start(3).await;
+    let mut probe = cluster.probe(2, "probe");
+
+    cluster.remote_addr(0, 2, probe.id()).do_send(Ping(1));
+    cluster.remote_addr(1, 2, probe.id()).do_send(Pong);
+
+    assert_eq!(probe.expect_message::<Ping>(WAIT).await, Ping(1));
+    probe.expect_message::<Pong>(WAIT).await;
+    probe.expect_no_message(Duration::from_millis(100)).await;
+    assert_eq!(probe.received(), ["Ping", "Pong"]);
+}
+
+#[actix_rt::test]
+async fn test_cluster_converges_in_memory() {
+    let mut cluster = TestCluster::builder(5)
+        .connection_protocol(ConnectionProtocol::Gossip)
+        .in_memory(MemoryNetwork::new())
+        .start()
+        .await;
+    let mut probe = cluster.probe(0, "probe");
+
+    for i in 1..5 {
+        cluster
+            .remote_addr(i, 0, probe.id())
+            .do_send(Ping(i as u32));
+    }
+    let mut received = vec![];
+    for _ in 1..5 {
+        received.push(probe.expect_message::<Ping>(WAIT).await.0);
+    }
+    received.sort();
+    assert_eq!(received, vec![1, 2, 3, 4]);
+    assert!(cluster
+        .events(0)
+        .next(Duration::from_millis(100))
+        .await
+        .is_none());
+}
+
+#[cfg(unix)]
+#[actix_rt::test]
+async fn test_cluster_converges_via_unix_sockets() {
+    let dir = std::env::temp_dir().join(format!("telepathy-testkit-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cluster = TestCluster::builder(3).unix_sockets(&dir).start().await;
+    assert!(cluster
+        .addrs()
+        .iter()
+        .all(|addr| addr.unix_path().is_some()));
+    let mut probe = cluster.probe(1, "probe");
+
+    cluster.remote_addr(2, 1, probe.id()).do_send(Ping(3));
+
+    assert_eq!(probe.expect_message::<Ping>(WAIT).await, Ping(3));
+}
+
+#[actix_rt::test]
+#[should_panic(expected = "expected Pong")]
+async fn probe_fails_on_missing_message() {
+    let cluster = TestCluster::builder(1)
+        .in_memory(MemoryNetwork::new())
+        .start()
+        .await;
+    cluster
+        .probe(0, "probe")
+        .expect_message::<Pong>(Duration::from_millis(50))
+        .await;
+}
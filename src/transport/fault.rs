@@ -0,0 +1,445 @@
+use crate::codec::{ClusterMessage, ConnectCodec};
+use crate::transport::{Connection, ConnectionReader, ConnectionWriter, Listener, Transport};
+use crate::NodeAddr;
+use bytes::BytesMut;
+use futures::future::{select, LocalBoxFuture};
+use futures::{FutureExt, StreamExt};
+use log::*;
+use parking_lot::Mutex;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::time::{sleep, timeout};
+use tokio_util::codec::{Encoder, FramedRead};
+use tokio_util::sync::CancellationToken;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+/// How long a frame is held back to be overtaken by the next one
+const REORDER_WINDOW: Duration = Duration::from_millis(50);
+
+/// Faults applied to the messages sent from one node to another. Probabilities range from 0 to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinkFaults {
+    pub drop: f64,
+    pub duplicate: f64,
+    pub reorder: f64,
+    pub delay: Duration,
+}
+
+/// Number of messages affected by faults so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    pub delayed: u64,
+}
+
+/// The peer of a relayed connection, once its handshake revealed it
+type LinkPeer = Weak<Mutex<Option<NodeAddr>>>;
+
+#[derive(Default)]
+struct FaultState {
+    default: LinkFaults,
+    links: HashMap<(NodeAddr, NodeAddr), LinkFaults>,
+    /// (from, to) pairs that cannot reach each other
+    blocked: HashSet<(NodeAddr, NodeAddr)>,
+    /// Relayed connections: own address, peer once known and the token that closes them
+    connections: Vec<(NodeAddr, LinkPeer, CancellationToken)>,
+    stats: FaultStats,
+}
+
+/// Changes the faults of [FaultyTransport](./struct.FaultyTransport.html)s at runtime. All nodes of a test
+/// share clones of one controller.
+///
+/// Faults only affect remote messages and acknowledgements; the handshake of a connection is not
+/// disturbed. Partitioned nodes refuse new connections and drop messages on existing ones.
+#[derive(Clone, Default)]
+pub struct FaultController {
+    state: Arc<Mutex<FaultState>>,
+}
+
+impl FaultController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Faults for all links without own settings.
+    pub fn set_default(&self, faults: LinkFaults) {
+        self.state.lock().default = faults;
+    }
+
+    pub fn set_link<A: Into<NodeAddr>>(&self, from: A, to: A, faults: LinkFaults) {
+        self.state
+            .lock()
+            .links
+            .insert((from.into(), to.into()), faults);
+    }
+
+    pub fn clear_link<A: Into<NodeAddr>>(&self, from: A, to: A) {
+        self.state.lock().links.remove(&(from.into(), to.into()));
+    }
+
+    /// Separates the nodes in `a` from the nodes in `b` in both directions.
+    pub fn partition<A: Into<NodeAddr> + Clone>(&self, a: &[A], b: &[A]) {
+        self.partition_one_way(a, b);
+        self.partition_one_way(b, a);
+    }
+
+    /// Nodes in `from` can no longer reach the nodes in `to`, while the other direction still works.
+    pub fn partition_one_way<A: Into<NodeAddr> + Clone>(&self, from: &[A], to: &[A]) {
+        let mut state = self.state.lock();
+        for from in from {
+            for to in to {
+                state
+                    .blocked
+                    .insert((from.clone().into(), to.clone().into()));
+            }
+        }
+    }
+
+    /// Closes the established connections between `a` and `b`, like a connection reset, so that
+    /// the nodes reconnect.
+    pub fn disconnect<A: Into<NodeAddr>>(&self, a: A, b: A) {
+        let (a, b) = (a.into(), b.into());
+        let mut state = self.state.lock();
+        state.connections.retain(|(own_addr, peer, closed)| {
+            let peer = match peer.upgrade() {
+                Some(peer) => peer.lock().clone(),
+                None => return false,
+            };
+            let between = match peer {
+                Some(peer) => (*own_addr == a && peer == b) || (*own_addr == b && peer == a),
+                None => false,
+            };
+            if between {
+                closed.cancel();
+            }
+            !between
+        });
+    }
+
+    /// Removes all partitions.
+    pub fn heal(&self) {
+        self.state.lock().blocked.clear();
+    }
+
+    /// Removes all partitions and link faults.
+    pub fn reset(&self) {
+        let mut state = self.state.lock();
+        state.default = LinkFaults::default();
+        state.links.clear();
+        state.blocked.clear();
+    }
+
+    pub fn is_blocked<A: Into<NodeAddr>>(&self, from: A, to: A) -> bool {
+        self.state
+            .lock()
+            .blocked
+            .contains(&(from.into(), to.into()))
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        self.state.lock().stats
+    }
+
+    fn faults(&self, from: &NodeAddr, to: &NodeAddr) -> Option<LinkFaults> {
+        let state = self.state.lock();
+        let link = (from.clone(), to.clone());
+        if state.blocked.contains(&link) {
+            None
+        } else {
+            Some(state.links.get(&link).copied().unwrap_or(state.default))
+        }
+    }
+
+    fn record(&self, f: impl FnOnce(&mut FaultStats)) {
+        f(&mut self.state.lock().stats)
+    }
+
+    fn register(&self, link: &Link) {
+        let mut state = self.state.lock();
+        state
+            .connections
+            .retain(|(_, peer, _)| peer.strong_count() > 0);
+        state.connections.push((
+            link.own_addr.clone(),
+            Arc::downgrade(&link.peer),
+            link.closed.clone(),
+        ));
+    }
+}
+
+/// [Transport](./trait.Transport.html) that injects the faults of a [FaultController](./struct.FaultController.html)
+/// into the messages sent via the wrapped transport. Works with every `ConnectionProtocol`.
+#[derive(Clone)]
+pub struct FaultyTransport<T: Transport> {
+    inner: Arc<T>,
+    controller: FaultController,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    pub fn new(inner: T, controller: FaultController) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            controller,
+        }
+    }
+
+    pub fn controller(&self) -> &FaultController {
+        &self.controller
+    }
+
+    /// Relays the connection established by `connect` unless the link is partitioned.
+    fn relay_connection(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+        connect: impl FnOnce(&T) -> LocalBoxFuture<'static, io::Result<Connection>>,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        if self.controller.is_blocked(own_addr.clone(), addr.clone()) {
+            return async move {
+                Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("{} is partitioned from {}", own_addr, addr),
+                ))
+            }
+            .boxed_local();
+        }
+        let connect = connect(&self.inner);
+        let link = Link::new(self.controller.clone(), own_addr, Some(addr.clone()), addr);
+        async move { Ok(link.relay(connect.await?)) }.boxed_local()
+    }
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    fn bind(&self, addr: NodeAddr) -> LocalBoxFuture<'static, io::Result<(Listener, NodeAddr)>> {
+        let bind = self.inner.bind(addr);
+        let controller = self.controller.clone();
+        async move {
+            let (inner, addr) = bind.await?;
+            let local_addr = addr.clone();
+            let listener: Listener = inner
+                .map(move |incoming| {
+                    let (connection, source) = incoming?;
+                    // the peer is known once it sends its listening address in the handshake
+                    let link = Link::new(controller.clone(), addr.clone(), None, source.clone());
+                    Ok((link.relay(connection), source))
+                })
+                .boxed_local();
+            Ok((listener, local_addr))
+        }
+        .boxed_local()
+    }
+
+    fn connect(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        let (own, peer) = (own_addr.clone(), addr.clone());
+        self.relay_connection(own_addr, addr, |inner| inner.connect(own, peer))
+    }
+
+    fn connect_timeout(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+        connect_timeout: Duration,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        let (own, peer) = (own_addr.clone(), addr.clone());
+        self.relay_connection(own_addr, addr, |inner| {
+            inner.connect_timeout(own, peer, connect_timeout)
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Link {
+    controller: FaultController,
+    own_addr: NodeAddr,
+    peer: Arc<Mutex<Option<NodeAddr>>>,
+    source: NodeAddr,
+    closed: CancellationToken,
+}
+
+impl Link {
+    fn new(
+        controller: FaultController,
+        own_addr: NodeAddr,
+        peer: Option<NodeAddr>,
+        source: NodeAddr,
+    ) -> Self {
+        let link = Self {
+            controller,
+            own_addr,
+            peer: Arc::new(Mutex::new(peer)),
+            source,
+            closed: CancellationToken::new(),
+        };
+        link.controller.register(&link);
+        link
+    }
+
+    /// Puts relays between the NetworkInterface and the connection of the wrapped transport.
+    fn relay(self, mut connection: Connection) -> Connection {
+        let messages = connection.take_message_stream().map(|messages| {
+            self.clone()
+                .relay_stream(messages, ConnectCodec::without_handshake)
+        });
+        let relayed = self.relay_stream(connection, ConnectCodec::new);
+        match messages {
+            Some(messages) => relayed.with_message_stream(messages),
+            None => relayed,
+        }
+    }
+
+    fn relay_stream(self, connection: Connection, codec: fn() -> ConnectCodec) -> Connection {
+        let (inner_reader, inner_writer) = connection.into_split();
+        let (local, remote) = tokio::io::duplex(BUFFER_SIZE);
+        let (reader, writer) = Connection::from(remote).into_split();
+        let incoming = self.clone().forward_incoming(inner_reader, writer, codec);
+        self.spawn_until_closed(incoming);
+        let outgoing = self.clone().forward_outgoing(reader, inner_writer, codec);
+        self.spawn_until_closed(outgoing);
+        Connection::from(local)
+    }
+
+    /// Dropping the forwarding closes both sides of the relayed connection.
+    fn spawn_until_closed(&self, forward: impl Future<Output = ()> + 'static) {
+        let closed = self.closed.clone();
+        actix::spawn(async move {
+            select(Box::pin(forward), Box::pin(closed.cancelled())).await;
+        });
+    }
+
+    async fn forward_incoming(
+        self,
+        reader: ConnectionReader,
+        mut writer: ConnectionWriter,
+        codec: fn() -> ConnectCodec,
+    ) {
+        let mut frames = FramedRead::new(reader, codec());
+        let mut codec = codec();
+        while let Some(Ok(frame)) = frames.next().await {
+            if let ClusterMessage::Request(advertised, _) = &frame {
+                *self.peer.lock() = Some(self.source.listening_addr(advertised.clone()));
+            }
+            if write_frame(&mut codec, frame, &mut writer).await.is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown().await;
+    }
+
+    async fn forward_outgoing(
+        self,
+        reader: ConnectionReader,
+        mut writer: ConnectionWriter,
+        codec: fn() -> ConnectCodec,
+    ) {
+        let mut frames = FramedRead::new(reader, codec());
+        let mut codec = codec();
+        let mut held: Option<BytesMut> = None;
+        loop {
+            let frame = match held {
+                Some(_) => match timeout(REORDER_WINDOW, frames.next()).await {
+                    Ok(frame) => frame,
+                    Err(_) => {
+                        let bytes = held.take().unwrap();
+                        if writer.write_all(&bytes).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                },
+                None => frames.next().await,
+            };
+            let frame = match frame {
+                Some(Ok(frame)) => frame,
+                _ => break,
+            };
+
+            let peer = self.peer.lock().clone();
+            let faults = match (&frame, peer) {
+                (ClusterMessage::Message(_) | ClusterMessage::Ack(_), Some(peer)) => {
+                    self.controller.faults(&self.own_addr, &peer)
+                }
+                _ => Some(LinkFaults::default()),
+            };
+            let mut bytes = BytesMut::new();
+            if codec.encode(frame, &mut bytes).is_err() {
+                break;
+            }
+            let sent = match faults {
+                Some(faults) => self.apply(faults, bytes, &mut held, &mut writer).await,
+                None => {
+                    debug!(
+                        "Dropping message of partitioned link from {}",
+                        self.own_addr
+                    );
+                    self.controller.record(|stats| stats.dropped += 1);
+                    Ok(())
+                }
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown().await;
+    }
+
+    async fn apply(
+        &self,
+        faults: LinkFaults,
+        bytes: BytesMut,
+        held: &mut Option<BytesMut>,
+        writer: &mut ConnectionWriter,
+    ) -> io::Result<()> {
+        let (dropped, duplicated, reordered) = {
+            let mut rng = rand::thread_rng();
+            (
+                rng.gen_bool(faults.drop.clamp(0.0, 1.0)),
+                rng.gen_bool(faults.duplicate.clamp(0.0, 1.0)),
+                rng.gen_bool(faults.reorder.clamp(0.0, 1.0)),
+            )
+        };
+        if dropped {
+            self.controller.record(|stats| stats.dropped += 1);
+            return Ok(());
+        }
+        if !faults.delay.is_zero() {
+            self.controller.record(|stats| stats.delayed += 1);
+            sleep(faults.delay).await;
+        }
+        if reordered && held.is_none() {
+            self.controller.record(|stats| stats.reordered += 1);
+            *held = Some(bytes);
+            return Ok(());
+        }
+
+        writer.write_all(&bytes).await?;
+        if duplicated {
+            self.controller.record(|stats| stats.duplicated += 1);
+            writer.write_all(&bytes).await?;
+        }
+        if let Some(bytes) = held.take() {
+            writer.write_all(&bytes).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn write_frame(
+    codec: &mut ConnectCodec,
+    frame: ClusterMessage,
+    writer: &mut ConnectionWriter,
+) -> io::Result<()> {
+    let mut bytes = BytesMut::new();
+    codec.encode(frame, &mut bytes)?;
+    writer.write_all(&bytes).await
+}
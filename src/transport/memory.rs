@@ -0,0 +1,121 @@
+use crate::transport::{unnamed_peer, Connection, Listener, Transport};
+use crate::NodeAddr;
+use futures::future::LocalBoxFuture;
+use futures::{FutureExt, StreamExt};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+const EPHEMERAL_PORTS: std::ops::RangeInclusive<u16> = 49152..=65535;
+
+#[derive(Default)]
+struct Listeners {
+    bound: HashMap<NodeAddr, UnboundedSender<(Connection, NodeAddr)>>,
+    next_port: u16,
+}
+
+impl Listeners {
+    /// Source address of an outgoing connection, like an ephemeral port of a TCP socket.
+    fn ephemeral_addr(&mut self, own_addr: &NodeAddr) -> NodeAddr {
+        loop {
+            if !EPHEMERAL_PORTS.contains(&self.next_port) {
+                self.next_port = *EPHEMERAL_PORTS.start();
+            }
+            let addr = match own_addr {
+                NodeAddr::Tcp(own_addr) => {
+                    NodeAddr::Tcp(SocketAddr::new(own_addr.ip(), self.next_port))
+                }
+                NodeAddr::Unix(path) => unnamed_peer(path, self.next_port.into()),
+                NodeAddr::Host(host, _) => NodeAddr::host(host.clone(), self.next_port),
+            };
+            self.next_port = self.next_port.wrapping_add(1);
+            if !self.bound.contains_key(&addr) {
+                return addr;
+            }
+        }
+    }
+}
+
+/// In-process [Transport](./trait.Transport.html). Nodes that share a clone of the same MemoryNetwork
+/// connect to each other via in-memory pipes, so no ports are bound.
+#[derive(Clone, Default)]
+pub struct MemoryNetwork {
+    listeners: Arc<Mutex<Listeners>>,
+}
+
+impl MemoryNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a node listens on `addr`.
+    pub fn is_bound(&self, addr: &NodeAddr) -> bool {
+        self.listeners
+            .lock()
+            .bound
+            .get(addr)
+            .is_some_and(|sender| !sender.is_closed())
+    }
+}
+
+impl Transport for MemoryNetwork {
+    fn bind(&self, addr: NodeAddr) -> LocalBoxFuture<'static, io::Result<(Listener, NodeAddr)>> {
+        let mut listeners = self.listeners.lock();
+        let addr = match addr {
+            NodeAddr::Tcp(socket_addr) if socket_addr.port() == 0 => {
+                listeners.ephemeral_addr(&addr)
+            }
+            NodeAddr::Host(_, 0) => listeners.ephemeral_addr(&addr),
+            addr => addr,
+        };
+        let bound = match listeners.bound.get(&addr) {
+            Some(sender) if !sender.is_closed() => Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is already bound", addr),
+            )),
+            _ => {
+                let (sender, receiver) = unbounded_channel();
+                listeners.bound.insert(addr.clone(), sender);
+                let incoming: Listener =
+                    UnboundedReceiverStream::new(receiver).map(Ok).boxed_local();
+                Ok((incoming, addr))
+            }
+        };
+        async move { bound }.boxed_local()
+    }
+
+    fn connect(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        let mut listeners = self.listeners.lock();
+        let source = listeners.ephemeral_addr(&own_addr);
+        let (local, remote) = tokio::io::duplex(BUFFER_SIZE);
+        let connected = match listeners.bound.get(&addr) {
+            Some(sender) if sender.send((Connection::from(remote), source)).is_ok() => {
+                Ok(Connection::from(local))
+            }
+            _ => {
+                listeners.bound.remove(&addr);
+                Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("Nothing listens on {}", addr),
+                ))
+            }
+        };
+        async move { connected }.boxed_local()
+    }
+}
+
+impl From<tokio::io::DuplexStream> for Connection {
+    fn from(stream: tokio::io::DuplexStream) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Connection::new(Box::new(reader), Box::new(writer))
+    }
+}
@@ -0,0 +1,112 @@
+use crate::NodeAddr;
+use futures::future::LocalBoxFuture;
+use futures::stream::LocalBoxStream;
+use futures::FutureExt;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+pub use self::fault::{FaultController, FaultStats, FaultyTransport, LinkFaults};
+pub use self::memory::MemoryNetwork;
+#[cfg(feature = "quic")]
+pub use self::quic::{QuicError, QuicTransport, QUIC_SERVER_NAME};
+pub use self::tcp::TcpTransport;
+
+mod fault;
+mod memory;
+#[cfg(feature = "quic")]
+mod quic;
+mod tcp;
+#[cfg(test)]
+mod tests;
+
+pub type ConnectionReader = Box<dyn AsyncRead + Unpin + Send>;
+pub type ConnectionWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Incoming connections of a bound [Transport](./trait.Transport.html) with the peer's address.
+/// Peers without an address of their own, like clients of a Unix domain socket, get one from [unnamed_peer](./fn.unnamed_peer.html).
+pub type Listener = LocalBoxStream<'static, io::Result<(Connection, NodeAddr)>>;
+
+/// Bidirectional byte stream between two nodes, split into its read and write half.
+pub struct Connection {
+    reader: ConnectionReader,
+    writer: ConnectionWriter,
+    messages: Option<Box<Connection>>,
+    peer_addr: Option<NodeAddr>,
+}
+
+impl Connection {
+    pub fn new(reader: ConnectionReader, writer: ConnectionWriter) -> Self {
+        Self {
+            reader,
+            writer,
+            messages: None,
+            peer_addr: None,
+        }
+    }
+
+    /// Address the connection was established with, e.g. the IP a host name of a seed node resolved to.
+    pub fn with_peer_addr(mut self, peer_addr: NodeAddr) -> Self {
+        self.peer_addr = Some(peer_addr);
+        self
+    }
+
+    pub fn peer_addr(&self) -> Option<&NodeAddr> {
+        self.peer_addr.as_ref()
+    }
+
+    /// Sends messages to actors via `messages`, so that large messages do not hold back the handshake
+    /// and the system messages on this connection. For transports that multiplex streams, like QUIC.
+    pub fn with_message_stream(mut self, messages: Connection) -> Self {
+        self.messages = Some(Box::new(messages));
+        self
+    }
+
+    pub fn take_message_stream(&mut self) -> Option<Connection> {
+        self.messages.take().map(|messages| *messages)
+    }
+
+    pub fn into_split(self) -> (ConnectionReader, ConnectionWriter) {
+        (self.reader, self.writer)
+    }
+}
+
+/// Placeholder address of the `n`th unnamed peer that connected via `path`. The peer's listening
+/// address replaces it once the peer sent it in the handshake.
+pub fn unnamed_peer(path: &Path, n: u64) -> NodeAddr {
+    NodeAddr::unix(format!("{}#{}", path.display(), n))
+}
+
+/// Moves bytes between the nodes of a cluster. The [Cluster](./struct.Cluster.html) binds its own address
+/// to accept connections and the [NetworkInterface](./struct.NetworkInterface.html)s connect to other nodes.
+pub trait Transport: Send + Sync + 'static {
+    /// Listens on `addr` and returns the address actually bound, e.g. with the port chosen for port 0.
+    fn bind(&self, addr: NodeAddr) -> LocalBoxFuture<'static, io::Result<(Listener, NodeAddr)>>;
+
+    /// Connects `own_addr` to the node listening on `addr`.
+    fn connect(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>>;
+
+    /// Like [connect](#tymethod.connect), but fails with `ErrorKind::TimedOut` if connecting takes longer than `timeout`.
+    fn connect_timeout(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+        timeout: Duration,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        let connect = self.connect(own_addr, addr.clone());
+        async move {
+            tokio::time::timeout(timeout, connect).await.map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("Connecting to {} timed out", addr),
+                )
+            })?
+        }
+        .boxed_local()
+    }
+}
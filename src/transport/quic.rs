@@ -0,0 +1,219 @@
+use crate::transport::{Connection, Listener, Transport};
+use crate::NodeAddr;
+use derive_more::Display;
+use futures::future::LocalBoxFuture;
+use futures::{stream, FutureExt, StreamExt};
+use parking_lot::Mutex;
+use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use quinn::rustls::RootCertStore;
+use quinn::{ClientConfig, Endpoint, RecvStream, SendStream, ServerConfig, TransportConfig};
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::lookup_host;
+use tokio::time::timeout;
+
+/// Name the certificate of a cluster using the [QuicTransport](./struct.QuicTransport.html) is issued for.
+pub const QUIC_SERVER_NAME: &str = "telepathy";
+
+const CONTROL_STREAM: u8 = 0;
+const MESSAGE_STREAM: u8 = 1;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+const STREAM_TIMEOUT: Duration = Duration::from_secs(5);
+const CONCURRENT_HANDSHAKES: usize = 16;
+
+#[derive(Debug, Display)]
+pub enum QuicError {
+    /// Generating the self-signed certificate failed
+    #[display(fmt = "Failed generating certificate: {}", _0)]
+    Certificate(String),
+
+    /// Certificate or key are not accepted by TLS
+    #[display(fmt = "Invalid TLS configuration: {}", _0)]
+    Tls(String),
+}
+
+impl std::error::Error for QuicError {}
+
+/// [Transport](./trait.Transport.html) via QUIC (`quic` feature). Each connection between two nodes has
+/// a stream for the handshake and the system messages, e.g. gossip, and one for the messages to actors,
+/// so that large messages do not hold back the control traffic.
+///
+/// All nodes of a cluster share a certificate for [QUIC_SERVER_NAME](./constant.QUIC_SERVER_NAME.html)
+/// and trust only this certificate.
+#[derive(Clone)]
+pub struct QuicTransport {
+    server: ServerConfig,
+    client: ClientConfig,
+    endpoints: Arc<Mutex<HashMap<SocketAddr, Endpoint>>>,
+}
+
+impl QuicTransport {
+    pub fn new(
+        certificate: CertificateDer<'static>,
+        key: PrivateKeyDer<'static>,
+    ) -> Result<Self, QuicError> {
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(certificate.clone())
+            .map_err(|err| QuicError::Tls(err.to_string()))?;
+        let mut server = ServerConfig::with_single_cert(vec![certificate], key)
+            .map_err(|err| QuicError::Tls(err.to_string()))?;
+        let mut client = ClientConfig::with_root_certificates(Arc::new(roots))
+            .map_err(|err| QuicError::Tls(err.to_string()))?;
+
+        // connections between nodes are idle as long as there is nothing to send
+        let mut transport = TransportConfig::default();
+        transport.keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+        let transport = Arc::new(transport);
+        server.transport_config(transport.clone());
+        client.transport_config(transport);
+
+        Ok(Self {
+            server,
+            client,
+            endpoints: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Generates a new certificate for the cluster. Only nodes sharing (a clone of) this transport trust each other.
+    pub fn self_signed() -> Result<Self, QuicError> {
+        let certified = rcgen::generate_simple_self_signed(vec![QUIC_SERVER_NAME.to_string()])
+            .map_err(|err| QuicError::Certificate(err.to_string()))?;
+        let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+        Self::new(certified.cert.der().clone(), key.into())
+    }
+
+    /// Endpoint to connect from `own_addr`. Like TCP connections, outgoing connections come from another
+    /// port than the node listens on, otherwise the peer could not tell them apart from its own connection.
+    fn endpoint(&self, own_addr: SocketAddr) -> io::Result<Endpoint> {
+        let mut endpoints = self.endpoints.lock();
+        if let Some(endpoint) = endpoints.get(&own_addr) {
+            return Ok(endpoint.clone());
+        }
+        let unspecified = match own_addr {
+            SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+        };
+        let mut endpoint = Endpoint::client(unspecified)?;
+        endpoint.set_default_client_config(self.client.clone());
+        endpoints.insert(own_addr, endpoint.clone());
+        Ok(endpoint)
+    }
+}
+
+impl Transport for QuicTransport {
+    fn bind(&self, addr: NodeAddr) -> LocalBoxFuture<'static, io::Result<(Listener, NodeAddr)>> {
+        let server = self.server.clone();
+        async move {
+            let endpoint = Endpoint::server(server, socket_addrs(addr).await?[0])?;
+            let local_addr = NodeAddr::Tcp(endpoint.local_addr()?);
+            let incoming = stream::unfold(endpoint, |endpoint| async move {
+                let incoming = endpoint.accept().await?;
+                Some((incoming, endpoint))
+            });
+            let listener: Listener = incoming
+                .map(accept)
+                .buffer_unordered(CONCURRENT_HANDSHAKES)
+                .boxed_local();
+            Ok((listener, local_addr))
+        }
+        .boxed_local()
+    }
+
+    fn connect(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        let transport = self.clone();
+        async move {
+            let endpoint = transport.endpoint(socket_addrs(own_addr).await?[0])?;
+            let mut last_err = None;
+            // like the TCP transport, all addresses of a host name are tried
+            for peer in socket_addrs(addr).await? {
+                match connect_to(&endpoint, peer).await {
+                    Ok(connection) => return Ok(connection),
+                    Err(err) => last_err = Some(err),
+                }
+            }
+            Err(last_err.unwrap())
+        }
+        .boxed_local()
+    }
+}
+
+/// Socket addresses of `addr`; host names are resolved.
+async fn socket_addrs(addr: NodeAddr) -> io::Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = match &addr {
+        NodeAddr::Tcp(socket_addr) => vec![*socket_addr],
+        NodeAddr::Host(host, port) => lookup_host((host.as_str(), *port)).await?.collect(),
+        NodeAddr::Unix(_) => vec![],
+    };
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("QUIC cannot reach {}", addr),
+        ));
+    }
+    Ok(addrs)
+}
+
+async fn connect_to(endpoint: &Endpoint, peer: SocketAddr) -> io::Result<Connection> {
+    let connecting = endpoint
+        .connect(peer, QUIC_SERVER_NAME)
+        .map_err(io::Error::other)?;
+    let connection = connecting.await.map_err(io::Error::other)?;
+    let control = open_stream(&connection, CONTROL_STREAM).await?;
+    let messages = open_stream(&connection, MESSAGE_STREAM).await?;
+    Ok(Connection::from(control)
+        .with_message_stream(Connection::from(messages))
+        .with_peer_addr(NodeAddr::Tcp(peer)))
+}
+
+/// Opens a stream and tags it, because the peer only learns about a stream once data is sent on it.
+async fn open_stream(
+    connection: &quinn::Connection,
+    tag: u8,
+) -> io::Result<(SendStream, RecvStream)> {
+    let (mut send, recv) = connection.open_bi().await.map_err(io::Error::other)?;
+    send.write_u8(tag).await?;
+    Ok((send, recv))
+}
+
+async fn accept(incoming: quinn::Incoming) -> io::Result<(Connection, NodeAddr)> {
+    let connection = incoming.await.map_err(io::Error::other)?;
+    let peer = connection.remote_address();
+    let (mut control, mut messages) = (None, None);
+    timeout(STREAM_TIMEOUT, async {
+        while control.is_none() || messages.is_none() {
+            let (send, mut recv) = connection.accept_bi().await.map_err(io::Error::other)?;
+            match recv.read_u8().await? {
+                CONTROL_STREAM => control = Some((send, recv)),
+                MESSAGE_STREAM => messages = Some((send, recv)),
+                tag => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown stream {} from {}", tag, peer),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Peer did not open its streams"))??;
+
+    let connection =
+        Connection::from(control.unwrap()).with_message_stream(Connection::from(messages.unwrap()));
+    Ok((connection, NodeAddr::Tcp(peer)))
+}
+
+impl From<(SendStream, RecvStream)> for Connection {
+    fn from((send, recv): (SendStream, RecvStream)) -> Self {
+        Connection::new(Box::new(recv), Box::new(send))
+    }
+}
@@ -0,0 +1,216 @@
+use crate::network::{Connect, Resolver, DEFAULT_CONNECT_TIMEOUT};
+use crate::transport::{Connection, Listener, Transport};
+use crate::NodeAddr;
+use actix::SystemService;
+use futures::future::LocalBoxFuture;
+use futures::{FutureExt, StreamExt};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_stream::wrappers::TcpListenerStream;
+
+/// Default [Transport](./trait.Transport.html) via TCP sockets. Nodes with a
+/// [NodeAddr::Unix](./enum.NodeAddr.html#variant.Unix) address are reached via Unix domain sockets instead.
+/// Host names are resolved by the DNS resolver and all of their addresses are tried.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpTransport;
+
+impl TcpTransport {
+    fn incoming(listener: TcpListener) -> io::Result<(Listener, NodeAddr)> {
+        let local_addr = listener.local_addr()?;
+        let incoming: Listener = TcpListenerStream::new(listener)
+            .map(|stream| {
+                let stream = stream?;
+                let addr = canonical(stream.peer_addr()?);
+                Ok((Connection::from(stream), NodeAddr::Tcp(addr)))
+            })
+            .boxed_local();
+        Ok((incoming, NodeAddr::Tcp(local_addr)))
+    }
+
+    /// Listens on `addr`; the unspecified IPv6 address `[::]` also accepts IPv4 connections.
+    fn listen(addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        if addr.is_ipv6() && addr.ip().is_unspecified() {
+            socket.set_only_v6(false)?;
+        }
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(1024)?;
+        TcpListener::from_std(socket.into())
+    }
+}
+
+/// IPv4 peers of a dual-stack listener connect from IPv4-mapped IPv6 addresses, e.g. `[::ffff:127.0.0.1]`.
+fn canonical(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V6(v6) => match v6.ip().to_ipv4_mapped() {
+            Some(ip) => SocketAddr::new(ip.into(), v6.port()),
+            None => addr,
+        },
+        SocketAddr::V4(_) => addr,
+    }
+}
+
+impl Transport for TcpTransport {
+    fn bind(&self, addr: NodeAddr) -> LocalBoxFuture<'static, io::Result<(Listener, NodeAddr)>> {
+        match addr {
+            NodeAddr::Tcp(addr) => async move { Self::incoming(Self::listen(addr)?) }.boxed_local(),
+            NodeAddr::Unix(path) => unix::bind(path).boxed_local(),
+            NodeAddr::Host(host, port) => {
+                async move { Self::incoming(TcpListener::bind((host.as_str(), port)).await?) }
+                    .boxed_local()
+            }
+        }
+    }
+
+    fn connect(
+        &self,
+        own_addr: NodeAddr,
+        addr: NodeAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        self.connect_timeout(own_addr, addr, DEFAULT_CONNECT_TIMEOUT)
+    }
+
+    fn connect_timeout(
+        &self,
+        _own_addr: NodeAddr,
+        addr: NodeAddr,
+        timeout: Duration,
+    ) -> LocalBoxFuture<'static, io::Result<Connection>> {
+        if let NodeAddr::Unix(path) = addr {
+            return unix::connect(path, timeout).boxed_local();
+        }
+        let request =
+            Resolver::from_registry().send(Connect::host(addr.to_string()).timeout(timeout));
+        async move {
+            match request.await {
+                Ok(Ok(stream)) => Ok(Connection::from(stream)),
+                Ok(Err(err)) => Err(io::Error::other(err.to_string())),
+                Err(err) => Err(io::Error::other(err)),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+impl From<TcpStream> for Connection {
+    fn from(stream: TcpStream) -> Self {
+        let peer_addr = stream.peer_addr();
+        let (reader, writer) = stream.into_split();
+        let connection = Connection::new(Box::new(reader), Box::new(writer));
+        match peer_addr {
+            Ok(peer_addr) => connection.with_peer_addr(NodeAddr::Tcp(canonical(peer_addr))),
+            Err(_) => connection,
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use crate::network::{ConnectUnix, Resolver};
+    use crate::transport::{unnamed_peer, Connection, Listener};
+    use crate::NodeAddr;
+    use actix::SystemService;
+    use futures::StreamExt;
+    use std::io;
+    use std::os::unix::fs::FileTypeExt;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio_stream::wrappers::UnixListenerStream;
+
+    /// Removes the socket file once the listener is dropped, i.e. when the node stops.
+    struct SocketFile(PathBuf);
+
+    impl Drop for SocketFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    pub(super) async fn bind(path: PathBuf) -> io::Result<(Listener, NodeAddr)> {
+        remove_stale_socket(&path).await?;
+        let listener = UnixListenerStream::new(UnixListener::bind(&path)?);
+        let local_addr = NodeAddr::Unix(path.clone());
+        let socket_file = SocketFile(path);
+        let mut accepted = 0;
+        let incoming: Listener = listener
+            .map(move |stream| {
+                let stream = stream?;
+                accepted += 1;
+                Ok((
+                    Connection::from(stream),
+                    unnamed_peer(&socket_file.0, accepted),
+                ))
+            })
+            .boxed_local();
+        Ok((incoming, local_addr))
+    }
+
+    /// A socket file outlives a node that did not stop cleanly; it is only replaced if nobody listens
+    /// anymore. Other files are never removed.
+    async fn remove_stale_socket(path: &Path) -> io::Result<()> {
+        let metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is no socket", path.display()),
+            ));
+        }
+        if UnixStream::connect(path).await.is_err() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub(super) async fn connect(path: PathBuf, timeout: Duration) -> io::Result<Connection> {
+        match Resolver::from_registry()
+            .send(ConnectUnix::path(path).timeout(timeout))
+            .await
+        {
+            Ok(Ok(stream)) => Ok(Connection::from(stream)),
+            Ok(Err(err)) => Err(io::Error::other(err.to_string())),
+            Err(err) => Err(io::Error::other(err)),
+        }
+    }
+
+    impl From<UnixStream> for Connection {
+        fn from(stream: UnixStream) -> Self {
+            let (reader, writer) = stream.into_split();
+            Connection::new(Box::new(reader), Box::new(writer))
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod unix {
+    use crate::transport::{Connection, Listener};
+    use crate::NodeAddr;
+    use std::io;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix domain sockets are not supported on this platform",
+        )
+    }
+
+    pub(super) async fn bind(_path: PathBuf) -> io::Result<(Listener, NodeAddr)> {
+        Err(unsupported())
+    }
+
+    pub(super) async fn connect(_path: PathBuf, _timeout: Duration) -> io::Result<Connection> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,415 @@
+use crate::testkit::free_local_addrs;
+use crate::{
+    ClusterHandle, ClusterLog, ConnectionProtocol, FaultController, FaultyTransport, LinkFaults,
+    MemoryNetwork, Node, NodeAddr, RemoteActor, RemoteMessage, TcpTransport, Transport,
+};
+use actix::prelude::*;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{timeout, Duration};
+
+fn memory_addrs(n: u16) -> Vec<NodeAddr> {
+    (0..n)
+        .map(|i| SocketAddr::from(([10, 0, 0, 1], 1000 + i)).into())
+        .collect()
+}
+
+#[actix_rt::test]
+async fn memory_network_connects_bound_addresses() {
+    let network = MemoryNetwork::new();
+    let addrs = memory_addrs(2);
+    let (mut listener, _) = network.bind(addrs[0].clone()).await.unwrap();
+    assert!(network.is_bound(&addrs[0]));
+
+    let (_, mut writer) = network
+        .connect(addrs[1].clone(), addrs[0].clone())
+        .await
+        .unwrap()
+        .into_split();
+    writer.write_all(b"ping").await.unwrap();
+
+    let (connection, source) = listener.next().await.unwrap().unwrap();
+    assert_eq!(
+        source.socket_addr().unwrap().ip(),
+        addrs[1].socket_addr().unwrap().ip()
+    );
+    assert_ne!(source, addrs[1]);
+    let (mut reader, _) = connection.into_split();
+    let mut buffer = [0; 4];
+    reader.read_exact(&mut buffer).await.unwrap();
+    assert_eq!(&buffer, b"ping");
+}
+
+#[actix_rt::test]
+async fn memory_network_refuses_unbound_and_rebinding() {
+    let network = MemoryNetwork::new();
+    let addrs = memory_addrs(2);
+    let err = network
+        .connect(addrs[1].clone(), addrs[0].clone())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+
+    let listener = network.bind(addrs[0].clone()).await.unwrap();
+    let err = network.bind(addrs[0].clone()).await.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+
+    drop(listener);
+    assert!(!network.is_bound(&addrs[0]));
+    assert!(network.bind(addrs[0].clone()).await.is_ok());
+}
+
+#[actix_rt::test]
+async fn memory_network_binds_ephemeral_port() {
+    let network = MemoryNetwork::new();
+    let any_port = SocketAddr::from(([10, 0, 0, 1], 0));
+    let (_first, first) = network.bind(any_port.into()).await.unwrap();
+    let (_second, second) = network.bind(any_port.into()).await.unwrap();
+    assert_ne!(first, second);
+    assert!(network.is_bound(&first) && network.is_bound(&second));
+    assert_ne!(first.socket_addr().unwrap().port(), 0);
+}
+
+struct MemberForwarder(NodeAddr, UnboundedSender<(NodeAddr, Node)>);
+
+impl Actor for MemberForwarder {
+    type Context = Context<Self>;
+}
+
+impl Handler<ClusterLog> for MemberForwarder {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClusterLog, _ctx: &mut Self::Context) -> Self::Result {
+        if let ClusterLog::NewMember(node) = msg {
+            let _ = self.1.send((self.0.clone(), node));
+        }
+    }
+}
+
+/// Nodes of the cluster by (own address, member address)
+type Members = HashMap<(NodeAddr, NodeAddr), Node>;
+
+async fn form_cluster<T: Transport>(
+    addrs: &[NodeAddr],
+    connection_protocol: ConnectionProtocol,
+    transport: impl Fn() -> T,
+) -> (Vec<ClusterHandle>, Members) {
+    let (sender, mut receiver) = unbounded_channel();
+    let handles = addrs
+        .iter()
+        .map(|addr| {
+            let seed_nodes = if *addr == addrs[0] {
+                vec![]
+            } else {
+                vec![addrs[0].clone()]
+            };
+            let handle = ClusterHandle::new_with_transport(
+                addr.clone(),
+                seed_nodes,
+                connection_protocol,
+                transport(),
+            );
+            handle.subscribe(
+                MemberForwarder(addr.clone(), sender.clone())
+                    .start()
+                    .recipient(),
+            );
+            handle
+        })
+        .collect();
+
+    let expected = addrs.len() * (addrs.len() - 1);
+    let mut members = HashMap::new();
+    timeout(Duration::from_secs(5), async {
+        while members.len() < expected {
+            let (own_addr, node) = receiver.recv().await.unwrap();
+            members.insert((own_addr, node.addr.clone()), node);
+        }
+    })
+    .await
+    .expect("Cluster did not converge");
+    (handles, members)
+}
+
+async fn form_memory_cluster(n: u16, connection_protocol: ConnectionProtocol) {
+    let network = MemoryNetwork::new();
+    let addrs = memory_addrs(n);
+    let _cluster = form_cluster(&addrs, connection_protocol, || network.clone()).await;
+    assert!(addrs.iter().all(|addr| network.is_bound(addr)));
+}
+
+#[actix_rt::test]
+async fn single_seed_cluster_forms_in_memory() {
+    form_memory_cluster(8, ConnectionProtocol::SingleSeed).await;
+}
+
+#[actix_rt::test]
+async fn gossip_cluster_forms_in_memory() {
+    form_memory_cluster(8, ConnectionProtocol::Gossip).await;
+}
+
+#[cfg(unix)]
+#[actix_rt::test]
+async fn cluster_forms_across_unix_and_tcp_nodes() {
+    let socket = std::env::temp_dir().join(format!("telepathy-{}.sock", std::process::id()));
+    let addrs = vec![
+        NodeAddr::unix(&socket),
+        NodeAddr::from(free_local_addrs(1)[0]),
+    ];
+    let (_handles, members) =
+        form_cluster(&addrs, ConnectionProtocol::SingleSeed, || TcpTransport).await;
+    assert!(members.contains_key(&(addrs[0].clone(), addrs[1].clone())));
+    assert!(members.contains_key(&(addrs[1].clone(), addrs[0].clone())));
+}
+
+#[cfg(unix)]
+#[actix_rt::test]
+async fn unix_socket_replaces_only_stale_sockets_and_is_removed_when_dropped() {
+    let dir = std::env::temp_dir();
+    let file = dir.join(format!("telepathy-{}-file.sock", std::process::id()));
+    std::fs::write(&file, "not a socket").unwrap();
+    let err = TcpTransport
+        .bind(NodeAddr::unix(&file))
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    assert!(file.exists());
+    std::fs::remove_file(&file).unwrap();
+
+    let socket = dir.join(format!("telepathy-{}-stale.sock", std::process::id()));
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    let (listener, _) = TcpTransport.bind(NodeAddr::unix(&socket)).await.unwrap();
+    assert!(socket.exists());
+    drop(listener);
+    assert!(!socket.exists());
+}
+
+// Fault injection
+
+async fn form_faulty_cluster(connection_protocol: ConnectionProtocol) {
+    let network = MemoryNetwork::new();
+    let controller = FaultController::new();
+    controller.set_default(LinkFaults {
+        duplicate: 0.5,
+        reorder: 0.5,
+        delay: Duration::from_millis(5),
+        ..Default::default()
+    });
+    let _cluster = form_cluster(&memory_addrs(5), connection_protocol, || {
+        FaultyTransport::new(network.clone(), controller.clone())
+    })
+    .await;
+    assert!(controller.stats().delayed > 0);
+}
+
+#[actix_rt::test]
+async fn single_seed_cluster_forms_despite_faults() {
+    form_faulty_cluster(ConnectionProtocol::SingleSeed).await;
+}
+
+#[actix_rt::test]
+async fn gossip_cluster_forms_despite_faults() {
+    form_faulty_cluster(ConnectionProtocol::Gossip).await;
+}
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct Probe(u32);
+
+#[derive(RemoteActor)]
+#[remote_messages(Probe)]
+struct ProbeReceiver {
+    cluster: ClusterHandle,
+    received: UnboundedSender<u32>,
+}
+
+impl Actor for ProbeReceiver {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let cluster = self.cluster.clone();
+        self.register_with(ctx.address().recipient(), &cluster);
+    }
+}
+
+impl Handler<Probe> for ProbeReceiver {
+    type Result = ();
+
+    fn handle(&mut self, msg: Probe, _ctx: &mut Self::Context) -> Self::Result {
+        let _ = self.received.send(msg.0);
+    }
+}
+
+async fn received_within(receiver: &mut UnboundedReceiver<u32>, wait: Duration) -> Vec<u32> {
+    let mut received = vec![];
+    while let Ok(Some(probe)) = timeout(wait, receiver.recv()).await {
+        received.push(probe);
+    }
+    received
+}
+
+#[actix_rt::test]
+async fn fault_controller_changes_links_at_runtime() {
+    let network = MemoryNetwork::new();
+    let controller = FaultController::new();
+    let addrs = memory_addrs(2);
+    let (handles, members) = form_cluster(&addrs, ConnectionProtocol::SingleSeed, || {
+        FaultyTransport::new(network.clone(), controller.clone())
+    })
+    .await;
+    let (sender, mut receiver) = unbounded_channel();
+    let _receiver = ProbeReceiver {
+        cluster: handles[1].clone(),
+        received: sender,
+    }
+    .start();
+    let probe_receiver = members[&(addrs[0].clone(), addrs[1].clone())]
+        .get_remote_addr(ProbeReceiver::ACTOR_ID.to_string());
+    let wait = Duration::from_millis(200);
+
+    probe_receiver.do_send(Probe(0));
+    assert_eq!(received_within(&mut receiver, wait).await, vec![0]);
+
+    let dropping = LinkFaults {
+        drop: 1.0,
+        ..Default::default()
+    };
+    controller.set_link(addrs[0].clone(), addrs[1].clone(), dropping);
+    probe_receiver.do_send(Probe(1));
+    assert!(received_within(&mut receiver, wait).await.is_empty());
+    assert_eq!(controller.stats().dropped, 1);
+
+    let duplicating = LinkFaults {
+        duplicate: 1.0,
+        ..Default::default()
+    };
+    controller.set_link(addrs[0].clone(), addrs[1].clone(), duplicating);
+    probe_receiver.do_send(Probe(2));
+    assert_eq!(received_within(&mut receiver, wait).await, vec![2, 2]);
+
+    let reordering = LinkFaults {
+        reorder: 1.0,
+        ..Default::default()
+    };
+    controller.set_link(addrs[0].clone(), addrs[1].clone(), reordering);
+    probe_receiver.do_send(Probe(3));
+    probe_receiver.do_send(Probe(4));
+    assert_eq!(received_within(&mut receiver, wait).await, vec![4, 3]);
+
+    controller.reset();
+    controller.partition_one_way(&addrs[..1], &addrs[1..]);
+    probe_receiver.do_send(Probe(5));
+    assert!(received_within(&mut receiver, wait).await.is_empty());
+    let transport = FaultyTransport::new(network.clone(), controller.clone());
+    let refused = transport
+        .connect(addrs[0].clone(), addrs[1].clone())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(refused.kind(), ErrorKind::ConnectionRefused);
+    assert!(transport
+        .connect(addrs[1].clone(), addrs[0].clone())
+        .await
+        .is_ok());
+
+    controller.heal();
+    probe_receiver.do_send(Probe(6));
+    assert_eq!(received_within(&mut receiver, wait).await, vec![6]);
+}
+
+// QUIC
+
+#[cfg(feature = "quic")]
+fn quic_addrs(n: usize) -> Vec<NodeAddr> {
+    free_local_addrs(n)
+        .into_iter()
+        .map(NodeAddr::from)
+        .collect()
+}
+
+#[cfg(feature = "quic")]
+#[actix_rt::test]
+async fn gossip_cluster_forms_via_quic() {
+    let transport = crate::QuicTransport::self_signed().unwrap();
+    form_cluster(&quic_addrs(4), ConnectionProtocol::Gossip, || {
+        transport.clone()
+    })
+    .await;
+}
+
+#[cfg(feature = "quic")]
+#[actix_rt::test]
+async fn quic_cluster_delivers_messages_despite_faults() {
+    let transport = crate::QuicTransport::self_signed().unwrap();
+    let controller = FaultController::new();
+    let addrs = quic_addrs(2);
+    let (handles, members) = form_cluster(&addrs, ConnectionProtocol::SingleSeed, || {
+        FaultyTransport::new(transport.clone(), controller.clone())
+    })
+    .await;
+    let (sender, mut receiver) = unbounded_channel();
+    let _receiver = ProbeReceiver {
+        cluster: handles[1].clone(),
+        received: sender,
+    }
+    .start();
+    let probe_receiver = members[&(addrs[0].clone(), addrs[1].clone())]
+        .get_remote_addr(ProbeReceiver::ACTOR_ID.to_string());
+
+    for i in 0..3 {
+        probe_receiver.do_send(Probe(i));
+    }
+    let received = received_within(&mut receiver, Duration::from_millis(500)).await;
+    assert_eq!(received, vec![0, 1, 2]);
+
+    let refused = transport
+        .connect(NodeAddr::unix("/tmp/telepathy-quic.sock"), addrs[0].clone())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(refused.kind(), ErrorKind::Unsupported);
+}
+
+#[cfg(feature = "quic")]
+#[actix_rt::test]
+async fn quic_message_stream_does_not_block_control_stream() {
+    let transport = crate::QuicTransport::self_signed().unwrap();
+    let addrs = quic_addrs(2);
+    let (mut listener, _) = transport.bind(addrs[0].clone()).await.unwrap();
+    let (connection, incoming) = futures::join!(
+        transport.connect(addrs[1].clone(), addrs[0].clone()),
+        listener.next()
+    );
+    let mut connection = connection.unwrap();
+    let (mut accepted, _) = incoming.unwrap().unwrap();
+    let (_, mut message_writer) = connection.take_message_stream().unwrap().into_split();
+    let (_, mut control_writer) = connection.into_split();
+    let (mut message_reader, _) = accepted.take_message_stream().unwrap().into_split();
+    let (mut control_reader, _) = accepted.into_split();
+
+    // more than the flow control window, so the message stream stalls until it is read
+    let payload = vec![7; 16 * 1024 * 1024];
+    let expected = payload.clone();
+    let sending = actix_rt::spawn(async move { message_writer.write_all(&payload).await });
+    control_writer.write_all(b"ping").await.unwrap();
+    let mut buffer = [0; 4];
+    timeout(
+        Duration::from_secs(1),
+        control_reader.read_exact(&mut buffer),
+    )
+    .await
+    .expect("control stream is blocked")
+    .unwrap();
+    assert_eq!(&buffer, b"ping");
+
+    let mut received = vec![0; expected.len()];
+    message_reader.read_exact(&mut received).await.unwrap();
+    assert_eq!(received, expected);
+    sending.await.unwrap().unwrap();
+}
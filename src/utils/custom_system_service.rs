@@ -43,30 +43,33 @@ pub trait CustomSystemService: Actor<Context = Context<Self>> + SystemService {
     fn custom_service_started(&mut self, ctx: &mut Context<Self>) {}
 
     fn add_to_registry(addr: Addr<Self>) -> Addr<Self> {
-        let sys = System::current();
-        let mut sreg = SREG.lock();
-        let reg = sreg
-            .entry(sys.id())
-            .or_insert_with(|| PatchedSystemRegistry::new(sys.arbiter().clone()));
-        reg.registry
-            .insert(TypeId::of::<Self>(), Box::new(addr.clone()));
+        add_value_to_registry(addr.clone());
         addr
     }
 
     /// Get actor's address from system registry
     fn from_custom_registry() -> Addr<Self> {
-        let sys = System::current();
-        let mut sreg = SREG.lock();
-        let reg = sreg
-            .entry(sys.id())
-            .or_insert_with(|| PatchedSystemRegistry::new(sys.arbiter().clone()));
+        value_from_registry::<Addr<Self>>()
+            .expect("Please start Actor before asking for it in registry!")
+    }
+}
 
-        if let Some(addr) = reg.registry.get(&TypeId::of::<Self>()) {
-            if let Some(addr) = addr.downcast_ref::<Addr<Self>>() {
-                return addr.clone();
-            }
-        }
+/// Stores a value per system and type next to the custom system services.
+pub(crate) fn add_value_to_registry<T: Any + Send>(value: T) {
+    let sys = System::current();
+    let mut sreg = SREG.lock();
+    let reg = sreg
+        .entry(sys.id())
+        .or_insert_with(|| PatchedSystemRegistry::new(sys.arbiter().clone()));
+    reg.registry.insert(TypeId::of::<T>(), Box::new(value));
+}
 
-        panic!("Please start Actor before asking for it in registry!");
-    }
+pub(crate) fn value_from_registry<T: Any + Send + Clone>() -> Option<T> {
+    let sys = System::current();
+    let sreg = SREG.lock();
+    sreg.get(&sys.id())?
+        .registry
+        .get(&TypeId::of::<T>())?
+        .downcast_ref::<T>()
+        .cloned()
 }
@@ -3,3 +3,4 @@ mod custom_system_service;
 mod tests;
 
 pub use custom_system_service::CustomSystemService;
+pub(crate) use custom_system_service::{add_value_to_registry, value_from_registry};
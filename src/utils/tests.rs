@@ -1,6 +1,5 @@
 use crate::CustomSystemService;
 use actix::{Actor, Context, Handler, Message, Supervised, SystemService};
-use actix_rt;
 use futures::FutureExt;
 
 #[derive(Message)]
@@ -36,9 +35,7 @@ impl Handler<TestMessage> for TestService {
 #[actix_rt::test]
 async fn system_service_created_and_retrieved() {
     let value: usize = 7;
-    let _test_service = TestService::start_service_with(move || TestService {
-        value: value.clone(),
-    });
+    let _test_service = TestService::start_service_with(move || TestService { value });
 
     let test_service = TestService::from_custom_registry();
     test_service
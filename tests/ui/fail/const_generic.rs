@@ -0,0 +1,9 @@
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+struct Chunk<const N: usize> {
+    bytes: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+use actix::prelude::*;
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[remote_message(id = "Ping")]
+struct Ping {}
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[remote_message(id = "Ping")]
+struct OtherPing {}
+
+#[derive(RemoteActor)]
+#[remote_messages(Ping, OtherPing)]
+struct MyActor;
+
+impl Actor for MyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<Ping> for MyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+impl Handler<OtherPing> for MyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: OtherPing, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+fn main() {}
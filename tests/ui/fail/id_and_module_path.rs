@@ -0,0 +1,8 @@
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[remote_message(id = "my.Message", module_path)]
+struct MyMessage {}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use actix::prelude::*;
+use actix_telepathy::prelude::*;
+
+#[derive(RemoteActor)]
+struct MyActor;
+
+impl Actor for MyActor {
+    type Context = Context<Self>;
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use actix::prelude::*;
+use actix_telepathy::prelude::*;
+
+#[derive(RemoteActor)]
+#[remote_messages(MyMessage = "x")]
+struct MyActor;
+
+impl Actor for MyActor {
+    type Context = Context<Self>;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(output = "usize")]
+struct MyMessage {}
+
+fn main() {}
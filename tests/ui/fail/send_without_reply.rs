@@ -0,0 +1,13 @@
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// without `#[remote_message(reply)]`, the handler does not reply and `send` would only time out
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(result = "u32")]
+struct Count;
+
+fn send(addr: RemoteAddr) {
+    let _ = addr.send(Count);
+}
+
+fn main() {}
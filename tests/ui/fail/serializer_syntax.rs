@@ -0,0 +1,8 @@
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[serializer = "DefaultSerialization"]
+struct MyMessage {}
+
+fn main() {}
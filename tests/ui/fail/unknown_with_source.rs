@@ -0,0 +1,10 @@
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[with_source(sender)]
+struct MyMessage {
+    source: RemoteAddr,
+}
+
+fn main() {}
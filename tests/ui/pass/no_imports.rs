@@ -0,0 +1,37 @@
+// the generated code must not depend on the imports of the module
+#[derive(actix_telepathy::RemoteMessage, serde::Serialize, serde::Deserialize)]
+#[rtype(result = "usize")]
+#[remote_message(reply)]
+struct Count {
+    bytes: Vec<u8>,
+}
+
+#[derive(actix_telepathy::RemoteMessage, serde::Serialize, serde::Deserialize)]
+#[with_source(source)]
+struct Hello {
+    source: actix_telepathy::RemoteAddr,
+}
+
+#[derive(actix_telepathy::RemoteActor)]
+#[remote_messages(Count, Hello)]
+struct Counter;
+
+impl actix::Actor for Counter {
+    type Context = actix::Context<Self>;
+}
+
+impl actix::Handler<Count> for Counter {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Count, _ctx: &mut Self::Context) -> Self::Result {
+        msg.bytes.len()
+    }
+}
+
+impl actix::Handler<Hello> for Counter {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Hello, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+fn main() {}
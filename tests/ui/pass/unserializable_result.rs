@@ -0,0 +1,41 @@
+// without `#[remote_message(reply)]`, the result of a message need not be serializable
+use actix::prelude::*;
+use actix_telepathy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(result = "Addr<Store>")]
+struct Subscribe;
+
+#[derive(RemoteMessage, Serialize, Deserialize)]
+#[rtype(result = "Result<(), std::io::Error>")]
+struct Flush<T> {
+    entries: Vec<T>,
+}
+
+#[derive(RemoteActor)]
+#[remote_actor(id = "Store")]
+#[remote_messages(Subscribe, Flush<u32>)]
+struct Store;
+
+impl Actor for Store {
+    type Context = Context<Self>;
+}
+
+impl Handler<Subscribe> for Store {
+    type Result = MessageResult<Subscribe>;
+
+    fn handle(&mut self, _msg: Subscribe, ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(ctx.address())
+    }
+}
+
+impl Handler<Flush<u32>> for Store {
+    type Result = Result<(), std::io::Error>;
+
+    fn handle(&mut self, _msg: Flush<u32>, _ctx: &mut Self::Context) -> Self::Result {
+        Ok(())
+    }
+}
+
+fn main() {}